@@ -1,11 +1,15 @@
 use super::{
     canvas::Canvas, 
+    filter::FilterChain,
     window::{Config, Window, ONE_SECOND}
 };
 
 pub struct App {
     window: Window,
     canvas: Canvas,
+    // only allocated when the upscaler runs on the CPU
+    presentation: Option<Canvas>,
+    post_process: FilterChain,
     fps_print: bool,
 }
 
@@ -23,9 +27,18 @@ impl App {
         let dimension = window.get_canvas_dimension();
         let canvas = Canvas::new(dimension.0, dimension.1);
 
+        let presentation_dimension = window.get_presentation_dimension();
+        let presentation = if presentation_dimension != dimension {
+            Some(Canvas::new(presentation_dimension.0, presentation_dimension.1))
+        } else {
+            None
+        };
+
         App {
             window,
             canvas,
+            presentation,
+            post_process: FilterChain::new(),
             fps_print: false,
         }
 
@@ -53,8 +66,15 @@ impl App {
             // START
             logic.update_routine(self.window.now(), &self.window);
             logic.render_routine(&mut self.canvas);
+            self.post_process.apply(&mut self.canvas);
             // END
-            self.window.swap_buffers(&self.canvas);
+            match self.presentation.as_mut() {
+                Some(presentation) => {
+                    self.window.get_upscaler().upscale(&self.canvas, presentation);
+                    self.window.swap_buffers(presentation);
+                }
+                None => self.window.swap_buffers(&self.canvas),
+            }
             frame_count += 1;
         }
 
@@ -64,6 +84,11 @@ impl App {
         self.fps_print = !self.fps_print;
     }
 
+    // filters run on the canvas after every render_routine
+    pub fn get_post_process_mut(&mut self) -> &mut FilterChain {
+        &mut self.post_process
+    }
+
     pub fn get_window(&self) -> &Window {
         &self.window
     }
@@ -1,10 +1,27 @@
-use std::ops::Mul;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
 
-use super::mathsf::Vec4;
+use super::{
+    colorspace,
+    mathsf::{Vec3, Vec4},
+    paint::Paint
+};
 
-// format ABGR
+// format ABGR, channels are sRGB encoded with straight (non premultiplied) alpha.
+// See colorspace for conversions from/to linear light.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Color(u32);
 
+// how draw_pixel combines a color with what is already in the buffer
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Blending {
+    // overwrite, alpha is stored as is
+    Replace,
+    // source-over mixing of the encoded sRGB values (cheap, darkens edges)
+    Gamma,
+    // source-over mixing in linear light
+    Linear,
+}
+
 const ALPHA: u32 = 0b11111111_00000000_00000000_00000000u32;
 const BLUE: u32  = 0b00000000_11111111_00000000_00000000u32;
 const GREEN: u32 = 0b00000000_00000000_11111111_00000000u32;
@@ -15,77 +32,102 @@ impl Color {
         let f = (255.0 * f.clamp(0f32, 1f32)) as u8;
         Self::from_rgb(f, f, f)
     }
+    // components are taken as already sRGB encoded values in [0, 1],
+    // use Color::from_linear for linear-light input
     pub fn from_vec4(v: &Vec4) -> Self {
         Self::from_rgba((v.get_x() * 255f32) as u8, (v.get_y() * 255f32) as u8, (v.get_z() * 255f32) as u8, (v.get_w() * 255f32) as u8)
     }
     pub fn from_rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
-        let mut c = ALPHA;
-        c |= (red as u32) << (8*0); 
-        c |= (green as u32) << (8*1);
-        c |= (blue as u32) << (8*2);
-        c |= (alpha as u32) << (8*3);
+        let mut c = 0u32;
+        c |= red as u32;
+        c |= (green as u32) << 8;
+        c |= (blue as u32) << 16;
+        c |= (alpha as u32) << 24;
         Color(c)
     }
     pub fn from_rgb(red: u8, green: u8, blue: u8) -> Self {
-        let mut c = ALPHA;
-        c |= (red as u32) << (8*0); 
-        c |= (green as u32) << (8*1);
-        c |= (blue as u32) << (8*2);
-        Color(c)
+        Self::from_rgba(red, green, blue, u8::MAX)
     }
     pub fn get_red(&self) -> u8 {
-        (self.0 >> (8 * 0)) as u8
+        self.0 as u8
     }
     pub fn get_green(&self) -> u8 {
-        (self.0 >> (8 * 1)) as u8
+        (self.0 >> 8) as u8
     }
     pub fn get_blue(&self) -> u8 {
-        (self.0 >> (8 * 2)) as u8
+        (self.0 >> 16) as u8
     }
     pub fn get_alpha(&self) -> u8 {
-        (self.0 >> (8 * 3)) as u8
+        (self.0 >> 24) as u8
     }
     pub fn set_red(&mut self, value: u8) {
-        let value = (value as u32) << 8*0;
         self.0 &= !RED;
-        self.0 |= value;
+        self.0 |= value as u32;
     }
     pub fn set_green(&mut self, value: u8) {
-        let value = (value as u32) << 8*1;
         self.0 &= !GREEN;
-        self.0 |= value;
+        self.0 |= (value as u32) << 8;
     }
     pub fn set_blue(&mut self, value: u8) {
-        let value = (value as u32) << 8*2;
         self.0 &= !BLUE;
-        self.0 |= value;
+        self.0 |= (value as u32) << 16;
     }
     pub fn set_alpha(&mut self, value: u8) {
-        let value = (value as u32) << 8*3;
         self.0 &= !ALPHA;
-        self.0 |= value;
+        self.0 |= (value as u32) << 24;
+    }
+    fn map_channels<F>(&mut self, other: &Color, f: F) -> &mut Self
+    where F: Fn(u8, u8) -> u8
+    {
+        *self = Self::from_rgba(
+            f(self.get_red(), other.get_red()),
+            f(self.get_green(), other.get_green()),
+            f(self.get_blue(), other.get_blue()),
+            f(self.get_alpha(), other.get_alpha())
+        );
+        self
+    }
+    pub fn wrapping_add(&mut self, other: &Color) -> &mut Self {
+        self.map_channels(other, u8::wrapping_add)
     }
+    #[deprecated(note = "use `wrapping_add`, or `+=` to saturate")]
     pub fn add(&mut self, other: &Color) -> &mut Self {
-        self.set_red(self.get_red().wrapping_add(other.get_alpha()));
-        self.set_green(self.get_green().wrapping_add(other.get_alpha()));
-        self.set_blue(self.get_blue().wrapping_add(other.get_alpha()));
-        self.set_alpha(self.get_alpha().wrapping_add(other.get_alpha()));
-        self
+        self.wrapping_add(other)
     }
-    pub fn saturating_add(&mut self, other: &Color)-> &mut Self  {
-        self.set_red(self.get_red().saturating_add(other.get_alpha()));
-        self.set_green(self.get_green().saturating_add(other.get_alpha()));
-        self.set_blue(self.get_blue().saturating_add(other.get_alpha()));
-        self.set_alpha(self.get_alpha().saturating_add(other.get_alpha()));
-        self
+    pub fn saturating_add(&mut self, other: &Color) -> &mut Self {
+        self.map_channels(other, u8::saturating_add)
+    }
+    pub fn wrapping_sub(&mut self, other: &Color) -> &mut Self {
+        self.map_channels(other, u8::wrapping_sub)
+    }
+    #[deprecated(note = "use `wrapping_sub`, or `-=` to saturate")]
+    pub fn sub(&mut self, other: &Color) -> &mut Self {
+        self.wrapping_sub(other)
     }
+    pub fn saturating_sub(&mut self, other: &Color) -> &mut Self {
+        self.map_channels(other, u8::saturating_sub)
+    }
+    // per channel modulation, 255 acts as 1.0
     pub fn multiply(&mut self, other: &Color) -> &mut Self {
-        self.set_red(self.get_red().mul(other.get_alpha()) / 255);
-        self.set_green(self.get_green().mul(other.get_alpha()) / 255);
-        self.set_blue(self.get_blue().mul(other.get_alpha()) / 255);
-        self.set_alpha(self.get_alpha().mul(other.get_alpha()) / 255);
+        self.map_channels(other, |a, b| ((a as u32 * b as u32 + 127) / 255) as u8)
+    }
+    // scales red, green and blue (saturating), alpha is left untouched
+    pub fn scale(&mut self, f: f32) -> &mut Self {
+        let scale = |c: u8| (c as f32 * f).round().clamp(0f32, 255f32) as u8;
+        *self = Self::from_rgba(scale(self.get_red()), scale(self.get_green()), scale(self.get_blue()), self.get_alpha());
         self
     }
+    // interpolates all four channels of the encoded values, see Color::lerp_linear
+    pub fn lerp(c0: &Color, c1: &Color, f: f32) -> Self {
+        let f = f.clamp(0f32, 1f32);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * f).round() as u8;
+        Self::from_rgba(
+            mix(c0.get_red(), c1.get_red()),
+            mix(c0.get_green(), c1.get_green()),
+            mix(c0.get_blue(), c1.get_blue()),
+            mix(c0.get_alpha(), c1.get_alpha())
+        )
+    }
     pub fn complement_rgba(&self) -> Color {
         Self::from_rgba(
             255 - self.get_red(), 
@@ -127,6 +169,62 @@ impl Color {
     pub fn cyan() -> Self {
         Self::from_rgb(u8::MIN, u8::MAX, u8::MAX)
     }
+    pub fn transparent() -> Self {
+        Self::from_rgba(u8::MIN, u8::MIN, u8::MIN, u8::MIN)
+    }
+}
+
+// operators saturate instead of wrapping
+impl Add for Color {
+    type Output = Color;
+    fn add(mut self, rhs: Color) -> Color {
+        *self.saturating_add(&rhs)
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+    fn sub(mut self, rhs: Color) -> Color {
+        *self.saturating_sub(&rhs)
+    }
+}
+
+impl Mul for Color {
+    type Output = Color;
+    fn mul(mut self, rhs: Color) -> Color {
+        *self.multiply(&rhs)
+    }
+}
+
+impl Mul<f32> for Color {
+    type Output = Color;
+    fn mul(mut self, rhs: f32) -> Color {
+        *self.scale(rhs)
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, rhs: Color) {
+        self.saturating_add(&rhs);
+    }
+}
+
+impl SubAssign for Color {
+    fn sub_assign(&mut self, rhs: Color) {
+        self.saturating_sub(&rhs);
+    }
+}
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, rhs: Color) {
+        self.multiply(&rhs);
+    }
+}
+
+impl MulAssign<f32> for Color {
+    fn mul_assign(&mut self, rhs: f32) {
+        self.scale(rhs);
+    }
 }
 
 pub struct Canvas {
@@ -134,6 +232,7 @@ pub struct Canvas {
     color_buffer: Vec<u32>,
     width: u32,
     height: u32,
+    blending: Blending,
 }
 
 impl Canvas {
@@ -146,6 +245,7 @@ impl Canvas {
             color_buffer: vec,
             width,
             height,
+            blending: Blending::Replace,
         }
     }
 
@@ -162,8 +262,181 @@ impl Canvas {
             self.depth_buffer[index] = z;
         }
 
-        self.color_buffer[index] = color.0;
+        self.color_buffer[index] = Self::blend(self.blending, &color, &Color(self.color_buffer[index])).0;
+
+    }
+
+    // whether draw_pixel(i, j, ..) lands inside the buffer
+    pub fn contains(&self, i: i32, j: i32) -> bool {
+        let x = i + self.get_width() / 2;
+        let y = -j + self.get_height() / 2;
+        x >= 0 && x < self.get_width() && y >= 0 && y < self.get_height()
+    }
+
+    // Bresenham, both end points included
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, z: f32, paint: &Paint) {
+        self.trace_line((x0, y0), (x1, y1), z, paint, false);
+    }
+
+    // connected lines, every joint is drawn once so translucent paints don't stack up there
+    pub fn draw_polyline(&mut self, points: &[(i32, i32)], z: f32, paint: &Paint) {
+        if let [point] = points {
+            self.trace_line(*point, *point, z, paint, false);
+        }
+        for (k, pair) in points.windows(2).enumerate() {
+            self.trace_line(pair[0], pair[1], z, paint, k > 0);
+        }
+    }
+
+    fn trace_line(&mut self, (x0, y0): (i32, i32), (x1, y1): (i32, i32), z: f32, paint: &Paint, skip_first: bool) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = ((x1 - x0).signum(), (y1 - y0).signum());
+        let (mut i, mut j) = (x0, y0);
+        let mut error = dx + dy;
+        let mut skip = skip_first;
+        loop {
+            if !skip && self.contains(i, j) {
+                self.draw_pixel(i, j, z, paint.sample(i as f32, j as f32));
+            }
+            skip = false;
+            if i == x1 && j == y1 {
+                break;
+            }
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                i += sx;
+            }
+            if e2 <= dx {
+                error += dx;
+                j += sy;
+            }
+        }
+    }
+
+    // inclusive canvas coordinate range covered by the buffer, (min_i, max_i, min_j, max_j),
+    // used to clip the loops of the fill functions
+    fn extents(&self) -> (i32, i32, i32, i32) {
+        let (width, height) = (self.get_width(), self.get_height());
+        (-(width / 2), width - width / 2 - 1, height / 2 - height + 1, height / 2)
+    }
+
+    // intersection of [low, high] with [min, max], computed wide so huge shapes don't overflow
+    fn clip_span(low: i64, high: i64, min: i32, max: i32) -> (i32, i32) {
+        (low.max(min as i64) as i32, high.min(max as i64) as i32)
+    }
+
+    // axis aligned, (x, y) is the bottom-left corner
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, z: f32, paint: &Paint) {
+        let (min_i, max_i, min_j, max_j) = self.extents();
+        let (i0, i1) = Self::clip_span(x as i64, x as i64 + width as i64 - 1, min_i, max_i);
+        let (j0, j1) = Self::clip_span(y as i64, y as i64 + height as i64 - 1, min_j, max_j);
+        for j in j0..=j1 {
+            for i in i0..=i1 {
+                self.draw_pixel(i, j, z, paint.sample(i as f32, j as f32));
+            }
+        }
+    }
 
+    pub fn fill_circle(&mut self, cx: i32, cy: i32, radius: u32, z: f32, paint: &Paint) {
+        let (min_i, max_i, min_j, max_j) = self.extents();
+        let r = radius as i64;
+        let r2 = r as i128 * r as i128;
+        let (i0, i1) = Self::clip_span(cx as i64 - r, cx as i64 + r, min_i, max_i);
+        let (j0, j1) = Self::clip_span(cy as i64 - r, cy as i64 + r, min_j, max_j);
+        for j in j0..=j1 {
+            for i in i0..=i1 {
+                let (di, dj) = (i as i128 - cx as i128, j as i128 - cy as i128);
+                if di * di + dj * dj <= r2 {
+                    self.draw_pixel(i, j, z, paint.sample(i as f32, j as f32));
+                }
+            }
+        }
+    }
+
+    // vertices in canvas coordinates, z is interpolated across the triangle for the depth test
+    pub fn fill_triangle(&mut self, v0: &Vec3, v1: &Vec3, v2: &Vec3, paint: &Paint) {
+
+        let edge = |a: &Vec3, b: &Vec3, x: f32, y: f32| {
+            (b.get_x() - a.get_x()) * (y - a.get_y()) - (b.get_y() - a.get_y()) * (x - a.get_x())
+        };
+        let area = edge(v0, v1, v2.get_x(), v2.get_y());
+        if area == 0f32 {
+            return;
+        }
+
+        let (min_i, max_i, min_j, max_j) = self.extents();
+        let min_x = (v0.get_x().min(v1.get_x()).min(v2.get_x()).floor() as i32).max(min_i);
+        let max_x = (v0.get_x().max(v1.get_x()).max(v2.get_x()).ceil() as i32).min(max_i);
+        let min_y = (v0.get_y().min(v1.get_y()).min(v2.get_y()).floor() as i32).max(min_j);
+        let max_y = (v0.get_y().max(v1.get_y()).max(v2.get_y()).ceil() as i32).min(max_j);
+
+        for j in min_y..=max_y {
+            for i in min_x..=max_x {
+                let (x, y) = (i as f32, j as f32);
+                // barycentric weights, sign normalized so either winding works
+                let w0 = edge(v1, v2, x, y) / area;
+                let w1 = edge(v2, v0, x, y) / area;
+                let w2 = edge(v0, v1, x, y) / area;
+                if w0 < 0f32 || w1 < 0f32 || w2 < 0f32 {
+                    continue;
+                }
+                let z = w0 * v0.get_z() + w1 * v1.get_z() + w2 * v2.get_z();
+                self.draw_pixel(i, j, z, paint.sample(x, y));
+            }
+        }
+
+    }
+
+    pub fn blend(blending: Blending, src: &Color, dst: &Color) -> Color {
+        match blending {
+            Blending::Replace => *src,
+            Blending::Gamma => {
+                let a = src.get_alpha() as u32;
+                let mix = |s: u8, d: u8| ((s as u32 * a + d as u32 * (255 - a) + 127) / 255) as u8;
+                Color::from_rgba(
+                    mix(src.get_red(), dst.get_red()),
+                    mix(src.get_green(), dst.get_green()),
+                    mix(src.get_blue(), dst.get_blue()),
+                    mix(255, dst.get_alpha())
+                )
+            }
+            Blending::Linear => {
+                let a = src.get_alpha() as f32 / 255f32;
+                let mix = |s: u8, d: u8| colorspace::encode(colorspace::decode(s) * a + colorspace::decode(d) * (1f32 - a));
+                Color::from_rgba(
+                    mix(src.get_red(), dst.get_red()),
+                    mix(src.get_green(), dst.get_green()),
+                    mix(src.get_blue(), dst.get_blue()),
+                    (a * 255f32 + dst.get_alpha() as f32 * (1f32 - a) + 0.5f32) as u8
+                )
+            }
+        }
+    }
+
+    pub fn get_blending(&self) -> Blending {
+        self.blending
+    }
+
+    pub fn set_blending(&mut self, blending: Blending) {
+        self.blending = blending;
+    }
+
+    // buffer coordinates, origin at the top-left corner
+    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+        Color(self.color_buffer[(x + y * self.width) as usize])
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        self.color_buffer[(x + y * self.width) as usize] = color.0;
+    }
+
+    pub fn get_depth(&self, x: u32, y: u32) -> f32 {
+        self.depth_buffer[(x + y * self.width) as usize]
+    }
+
+    pub fn set_depth(&mut self, x: u32, y: u32, depth: f32) {
+        self.depth_buffer[(x + y * self.width) as usize] = depth;
     }
 
     pub fn clear_color(&mut self, color: Color) {
@@ -186,4 +459,182 @@ impl Canvas {
         self.color_buffer.as_ptr()
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn from_rgba_keeps_alpha() {
+        let c = Color::from_rgba(10, 20, 30, 40);
+        assert_eq!((c.get_red(), c.get_green(), c.get_blue(), c.get_alpha()), (10, 20, 30, 40));
+        assert_eq!(Color::from_rgb(1, 2, 3).get_alpha(), 255);
+    }
+
+    #[test]
+    fn setters_only_touch_their_channel() {
+        let mut c = Color::from_rgba(1, 2, 3, 4);
+        c.set_red(100);
+        c.set_green(101);
+        c.set_blue(102);
+        c.set_alpha(103);
+        assert_eq!(c, Color::from_rgba(100, 101, 102, 103));
+    }
+
+    #[test]
+    fn wrapping_add_is_per_channel() {
+        let mut c = Color::from_rgba(10, 20, 30, 40);
+        c.wrapping_add(&Color::from_rgba(1, 2, 3, 4));
+        assert_eq!(c, Color::from_rgba(11, 22, 33, 44));
+        c.wrapping_add(&Color::from_rgba(250, 0, 0, 0));
+        assert_eq!(c.get_red(), 5);
+    }
+
+    #[test]
+    fn saturating_add_and_sub() {
+        let mut c = Color::from_rgba(200, 20, 30, 40);
+        c.saturating_add(&Color::from_rgba(100, 2, 3, 4));
+        assert_eq!(c, Color::from_rgba(255, 22, 33, 44));
+        c.saturating_sub(&Color::from_rgba(0, 30, 3, 4));
+        assert_eq!(c, Color::from_rgba(255, 0, 30, 40));
+    }
+
+    #[test]
+    fn wrapping_sub_wraps() {
+        let mut c = Color::from_rgba(0, 20, 30, 40);
+        c.wrapping_sub(&Color::from_rgba(1, 20, 10, 0));
+        assert_eq!(c, Color::from_rgba(255, 0, 20, 40));
+    }
+
+    #[test]
+    fn multiply_modulates_each_channel() {
+        let mut c = Color::from_rgba(255, 128, 255, 255);
+        c.multiply(&Color::from_rgba(255, 255, 0, 128));
+        assert_eq!(c, Color::from_rgba(255, 128, 0, 128));
+        assert_eq!(Color::white() * Color::red(), Color::red());
+    }
+
+    #[test]
+    fn scale_keeps_alpha() {
+        let c = Color::from_rgba(100, 200, 50, 70) * 0.5;
+        assert_eq!(c, Color::from_rgba(50, 100, 25, 70));
+        let c = Color::from_rgba(100, 200, 50, 70) * 2.0;
+        assert_eq!(c, Color::from_rgba(200, 255, 100, 70));
+    }
+
+    #[test]
+    fn lerp_endpoints_and_midpoint() {
+        let (a, b) = (Color::from_rgba(0, 100, 200, 0), Color::from_rgba(100, 200, 0, 255));
+        assert_eq!(Color::lerp(&a, &b, 0.0), a);
+        assert_eq!(Color::lerp(&a, &b, 1.0), b);
+        assert_eq!(Color::lerp(&a, &b, 0.5), Color::from_rgba(50, 150, 100, 128));
+    }
+
+    // std::ops::Add and Sub are not in scope here, as for most callers
+    mod deprecated {
+
+        use super::super::Color;
+
+        #[test]
+        #[allow(deprecated)]
+        fn in_place_arithmetic_wraps() {
+            let mut c = Color::from_rgba(250, 20, 30, 40);
+            c.add(&Color::from_rgba(10, 1, 1, 1));
+            assert_eq!(c, Color::from_rgba(4, 21, 31, 41));
+            c.sub(&Color::from_rgba(5, 1, 1, 1));
+            assert_eq!(c, Color::from_rgba(255, 20, 30, 40));
+        }
+
+    }
+
+    #[test]
+    fn operators_saturate() {
+        assert_eq!(Color::red() + Color::green(), Color::yellow());
+        assert_eq!(Color::white() - Color::red(), Color::from_rgba(0, 255, 255, 0));
+        let mut c = Color::gray();
+        c += Color::gray();
+        assert_eq!(c, Color::from_rgb(254, 254, 254));
+        c -= Color::from_rgba(254, 0, 0, 0);
+        assert_eq!(c, Color::from_rgb(0, 254, 254));
+        c *= 0.5;
+        assert_eq!(c, Color::from_rgb(0, 127, 127));
+        c *= Color::from_rgba(255, 255, 0, 255);
+        assert_eq!(c, Color::from_rgb(0, 127, 0));
+    }
+
+    #[test]
+    fn value_semantics() {
+        use std::collections::HashSet;
+        let c = Color::cyan();
+        let copy = c;
+        assert_eq!(c, copy);
+        let set: HashSet<Color> = [Color::red(), Color::red(), Color::blue()].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn polyline_joints_blend_once() {
+        let mut canvas = Canvas::new(8, 8);
+        canvas.set_blending(Blending::Gamma);
+        let paint = Paint::Solid(Color::from_rgba(0, 0, 0, 128));
+        canvas.draw_polyline(&[(0, 0), (2, 0), (2, 2), (2, 2), (0, 2)], 0f32, &paint);
+        let once = Canvas::blend(Blending::Gamma, &Color::from_rgba(0, 0, 0, 128), &Color::white());
+        let mut painted = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                let c = canvas.get_pixel(x, y);
+                assert!(c == once || c == Color::white());
+                painted += (c == once) as u32;
+            }
+        }
+        assert_eq!(painted, 7);
+        assert_eq!(canvas.get_pixel(6, 4), once);
+    }
+
+
+    fn covered(canvas: &Canvas) -> usize {
+        let (width, height) = (canvas.get_width() as u32, canvas.get_height() as u32);
+        (0..width * height).filter(|k| canvas.get_pixel(k % width, k / width) == Color::red()).count()
+    }
+
+    #[test]
+    fn fills_are_clipped_to_the_canvas() {
+        let red = Paint::Solid(Color::red());
+        for (width, height) in [(7, 5), (8, 6), (1, 1), (0, 0)] {
+            let mut canvas = Canvas::new(width, height);
+            canvas.fill_rect(-1000, -1000, 4000, 4000, 0f32, &red);
+            assert_eq!(covered(&canvas), (width * height) as usize);
+
+            let mut canvas = Canvas::new(width, height);
+            canvas.fill_circle(0, 0, 70000, 0f32, &red);
+            assert_eq!(covered(&canvas), (width * height) as usize);
+
+            let mut canvas = Canvas::new(width, height);
+            let (a, b, c) = (Vec3::new(-1e6, -1e6, 0f32), Vec3::new(1e6, -1e6, 0f32), Vec3::new(0f32, 1e6, 0f32));
+            canvas.fill_triangle(&a, &b, &c, &red);
+            assert_eq!(covered(&canvas), (width * height) as usize);
+        }
+    }
+
+    #[test]
+    fn fills_partly_off_screen() {
+        let red = Paint::Solid(Color::red());
+        let mut canvas = Canvas::new(8, 8);
+        // canvas coordinates run from -4 to 3 across and from -3 to 4 upwards
+        canvas.fill_rect(2, 3, 10, 10, 0f32, &red);
+        assert_eq!(covered(&canvas), 4);
+        assert_eq!(canvas.get_pixel(7, 0), Color::red());
+        assert_eq!(canvas.get_pixel(6, 1), Color::red());
+
+        let mut canvas = Canvas::new(8, 8);
+        canvas.fill_circle(-4, -3, 1, 0f32, &red);
+        assert_eq!(covered(&canvas), 3);
+        canvas.fill_circle(i32::MIN, i32::MAX, u32::MAX / 2, 0f32, &red);
+        assert_eq!(covered(&canvas), 3);
+        canvas.fill_rect(i32::MAX, i32::MAX, u32::MAX, u32::MAX, 0f32, &red);
+        assert_eq!(covered(&canvas), 3);
+    }
+
+}
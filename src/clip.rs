@@ -0,0 +1,267 @@
+use super::mathsf::Vec4;
+
+pub use super::interpolate::Interpolate;
+
+// Sutherland-Hodgman clipping in homogeneous clip space, before the perspective
+// divide. A vertex is inside when -w <= x, y, z <= w (OpenGL conventions, as
+// produced by Mat4::perspective and Mat4::orthographic).
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClipPlane {
+    Left,
+    Right,
+    Bottom,
+    Top,
+    Near,
+    Far,
+}
+
+pub const CLIP_PLANES: [ClipPlane; 6] = [
+    ClipPlane::Near,
+    ClipPlane::Far,
+    ClipPlane::Left,
+    ClipPlane::Right,
+    ClipPlane::Bottom,
+    ClipPlane::Top,
+];
+
+// a clip-space position with its attributes
+pub type ClipVertex<A> = (Vec4, A);
+
+impl ClipPlane {
+    // positive inside, `guard_band` widens the side planes to |x|, |y| <= guard_band * w
+    pub fn distance(&self, p: &Vec4, guard_band: f32) -> f32 {
+        let (x, y, z, w) = (p.get_x(), p.get_y(), p.get_z(), p.get_w());
+        match self {
+            ClipPlane::Left => x + guard_band * w,
+            ClipPlane::Right => guard_band * w - x,
+            ClipPlane::Bottom => y + guard_band * w,
+            ClipPlane::Top => guard_band * w - y,
+            ClipPlane::Near => z + w,
+            ClipPlane::Far => w - z,
+        }
+    }
+}
+
+// With a guard band the rasterizer must still scissor to the canvas; the band only
+// trades expensive clipping for cheap per-pixel rejection of slightly offscreen
+// triangles. Near and far are always clipped so nothing behind the camera survives.
+#[derive(Clone, Copy, Debug)]
+pub struct Clipper {
+    guard_band: f32,
+}
+
+impl Clipper {
+
+    pub fn new() -> Self {
+        Clipper { guard_band: 1f32 }
+    }
+
+    // factor >= 1 by which the side planes are pushed outwards
+    pub fn with_guard_band(factor: f32) -> Self {
+        let mut clipper = Self::new();
+        clipper.set_guard_band(factor);
+        clipper
+    }
+
+    pub fn set_guard_band(&mut self, factor: f32) {
+        assert!(factor >= 1f32, "The guard band factor must be at least 1.");
+        self.guard_band = factor;
+    }
+
+    pub fn get_guard_band(&self) -> f32 {
+        self.guard_band
+    }
+
+    // bit i set when outside CLIP_PLANES[i]
+    pub fn outcode(&self, p: &Vec4) -> u8 {
+        CLIP_PLANES.iter().enumerate().fold(0, |code, (i, plane)| {
+            if plane.distance(p, self.guard_band) < 0f32 { code | 1 << i } else { code }
+        })
+    }
+
+    // convex polygon in, convex polygon out; empty when entirely outside
+    pub fn clip_polygon<A: Interpolate>(&self, polygon: Vec<ClipVertex<A>>) -> Vec<ClipVertex<A>> {
+        let codes: Vec<u8> = polygon.iter().map(|(p, _)| self.outcode(p)).collect();
+        // trivial reject, every vertex outside the same plane
+        if codes.iter().fold(0xff, |acc, code| acc & code) != 0 {
+            return Vec::new();
+        }
+        let outside = codes.iter().fold(0, |acc, code| acc | code);
+        let mut polygon = polygon;
+        for (i, plane) in CLIP_PLANES.iter().enumerate() {
+            if outside & (1 << i) == 0 {
+                continue;
+            }
+            polygon = self.clip_against(&polygon, plane);
+            if polygon.is_empty() {
+                break;
+            }
+        }
+        polygon
+    }
+
+    // clipped and fan triangulated, keeps the winding of the input
+    pub fn clip_triangle<A: Interpolate>(&self, triangle: [ClipVertex<A>; 3]) -> Vec<[ClipVertex<A>; 3]> {
+        let polygon = self.clip_polygon(triangle.into());
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+        (1..polygon.len() - 1)
+            .map(|i| [polygon[0].clone(), polygon[i].clone(), polygon[i + 1].clone()])
+            .collect()
+    }
+
+    pub fn clip_line<A: Interpolate>(&self, a: ClipVertex<A>, b: ClipVertex<A>) -> Option<(ClipVertex<A>, ClipVertex<A>)> {
+        let (mut t0, mut t1) = (0f32, 1f32);
+        for plane in CLIP_PLANES.iter() {
+            let d0 = plane.distance(&a.0, self.guard_band);
+            let d1 = plane.distance(&b.0, self.guard_band);
+            if d0 < 0f32 && d1 < 0f32 {
+                return None;
+            }
+            if d0 < 0f32 {
+                t0 = t0.max(d0 / (d0 - d1));
+            } else if d1 < 0f32 {
+                t1 = t1.min(d0 / (d0 - d1));
+            }
+        }
+        if t0 > t1 {
+            return None;
+        }
+        Some((lerp_vertex(&a, &b, t0), lerp_vertex(&a, &b, t1)))
+    }
+
+    fn clip_against<A: Interpolate>(&self, polygon: &[ClipVertex<A>], plane: &ClipPlane) -> Vec<ClipVertex<A>> {
+        let mut clipped = Vec::with_capacity(polygon.len() + 2);
+        for (i, current) in polygon.iter().enumerate() {
+            let next = &polygon[(i + 1) % polygon.len()];
+            let d0 = plane.distance(&current.0, self.guard_band);
+            let d1 = plane.distance(&next.0, self.guard_band);
+            if d0 >= 0f32 {
+                clipped.push(current.clone());
+            }
+            // the edge strictly crosses the plane, a vertex on it is already kept
+            if (d0 > 0f32 && d1 < 0f32) || (d0 < 0f32 && d1 > 0f32) {
+                clipped.push(lerp_vertex(current, next, d0 / (d0 - d1)));
+            }
+        }
+        clipped
+    }
+
+}
+
+impl Default for Clipper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn lerp_vertex<A: Interpolate>(a: &ClipVertex<A>, b: &ClipVertex<A>, t: f32) -> ClipVertex<A> {
+    (Vec4::lerp(&a.0, &b.0, t), A::interpolate(&a.1, &b.1, t))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn vertex(x: f32, y: f32, z: f32) -> ClipVertex<f32> {
+        // the attribute tracks z to check the interpolation
+        (Vec4::new(x, y, z, 1f32), z)
+    }
+
+    #[test]
+    fn outcode_bits_follow_clip_planes() {
+        let clipper = Clipper::new();
+        assert_eq!(clipper.outcode(&Vec4::new(0.5, -0.5, 0.9, 1f32)), 0);
+        assert_eq!(clipper.outcode(&Vec4::new(0f32, 0f32, -2f32, 1f32)), 1);
+        assert_eq!(clipper.outcode(&Vec4::new(0f32, 0f32, 2f32, 1f32)), 2);
+        assert_eq!(clipper.outcode(&Vec4::new(2f32, 0f32, 0f32, 1f32)), 8);
+        assert_eq!(clipper.outcode(&Vec4::new(0f32, 2f32, 0f32, 1f32)), 32);
+        assert_eq!(clipper.outcode(&Vec4::new(-2f32, -2f32, 0f32, 1f32)), 4 | 16);
+        // the sides scale with w
+        assert_eq!(clipper.outcode(&Vec4::new(2f32, 0f32, 0f32, 4f32)), 0);
+        assert_eq!(Clipper::with_guard_band(4f32).outcode(&Vec4::new(2f32, -3f32, 0f32, 1f32)), 0);
+        assert_eq!(Clipper::with_guard_band(4f32).outcode(&Vec4::new(0f32, 0f32, -2f32, 1f32)), 1);
+    }
+
+    #[test]
+    fn triangle_behind_the_near_plane_is_rejected() {
+        let clipper = Clipper::new();
+        let behind = [vertex(-0.5, 0f32, -2f32), vertex(0.5, 0f32, -3f32), vertex(0f32, 0.5, -1.5)];
+        assert!(clipper.clip_triangle(behind).is_empty());
+        // outside different planes but across a corner of the frustum
+        let corner = [vertex(0.5, 3f32, 0f32), vertex(3f32, 0.5, 0f32), vertex(3f32, 3f32, 0f32)];
+        assert!(clipper.clip_triangle(corner).is_empty());
+    }
+
+    #[test]
+    fn inside_triangle_passes_through() {
+        let triangle = [vertex(-0.5, -0.5, 0f32), vertex(0.5, -0.5, 0.2), vertex(0f32, 0.5, 0.4)];
+        assert_eq!(Clipper::new().clip_triangle(triangle), vec![triangle]);
+    }
+
+    #[test]
+    fn one_vertex_behind_splits_into_two() {
+        let triangle = [vertex(-0.5, -0.5, 0f32), vertex(0.5, -0.5, 0f32), vertex(0f32, 0.5, -3f32)];
+        let clipped = Clipper::new().clip_triangle(triangle);
+        assert_eq!(clipped.len(), 2);
+        let vertices: Vec<&ClipVertex<f32>> = clipped.iter().flatten().collect();
+        for (p, z) in &vertices {
+            assert!(p.get_z() + p.get_w() >= -1e-6);
+            assert!((p.get_z() - z).abs() < 1e-6);
+        }
+        // b -> c crosses at a third, c -> a at two thirds
+        let expected = [Vec4::new(1f32 / 3f32, -1f32 / 6f32, -1f32, 1f32), Vec4::new(-1f32 / 3f32, -1f32 / 6f32, -1f32, 1f32)];
+        for e in expected {
+            assert!(vertices.iter().any(|(p, z)| (*p - e).length() < 1e-5 && (z + 1f32).abs() < 1e-5));
+        }
+        // both triangles keep the counter-clockwise winding
+        for [a, b, c] in &clipped {
+            let (ab, ac) = (b.0 - a.0, c.0 - a.0);
+            assert!(ab.get_x() * ac.get_y() - ab.get_y() * ac.get_x() > 0f32);
+        }
+    }
+
+    #[test]
+    fn guard_band_skips_side_clipping() {
+        let triangle = [vertex(-1.5, -0.5, 0f32), vertex(1.5, -0.5, 0f32), vertex(0f32, 0.5, 0f32)];
+        let clipped = Clipper::new().clip_triangle(triangle);
+        assert!(clipped.len() > 1);
+        assert!(clipped.iter().flatten().all(|(p, _)| p.get_x().abs() <= 1f32 + 1e-6));
+        let mut guard_band = Clipper::new();
+        guard_band.set_guard_band(2f32);
+        assert_eq!(guard_band.get_guard_band(), 2f32);
+        assert_eq!(guard_band.clip_triangle(triangle), vec![triangle]);
+        // beyond the band it clips again
+        let wide = [vertex(-3f32, -0.5, 0f32), vertex(3f32, -0.5, 0f32), vertex(0f32, 0.5, 0f32)];
+        assert!(guard_band.clip_triangle(wide).iter().flatten().all(|(p, _)| p.get_x().abs() <= 2f32 + 1e-6));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1")]
+    fn guard_band_below_one() {
+        Clipper::with_guard_band(0.5);
+    }
+
+    #[test]
+    fn lines_are_trimmed_or_rejected() {
+        let clipper = Clipper::new();
+        let a = (Vec4::new(-2f32, 0f32, 0f32, 1f32), -2f32);
+        let b = (Vec4::new(2f32, 0f32, 0f32, 1f32), 2f32);
+        let (start, end) = clipper.clip_line(a, b).unwrap();
+        assert_eq!(start, (Vec4::new(-1f32, 0f32, 0f32, 1f32), -1f32));
+        assert_eq!(end, (Vec4::new(1f32, 0f32, 0f32, 1f32), 1f32));
+        let inside = (vertex(0.1, 0.2, 0.3), vertex(-0.4, 0.5, -0.6));
+        assert_eq!(clipper.clip_line(inside.0, inside.1), Some(inside));
+        // both beyond the right plane
+        assert!(clipper.clip_line(vertex(2f32, 0f32, 0f32), vertex(3f32, 1f32, 0f32)).is_none());
+        // each end outside a different plane, missing the corner
+        assert!(clipper.clip_line(vertex(0f32, 2.5, 0f32), vertex(2.5, 0f32, 0f32)).is_none());
+        // crossing the near plane
+        let (start, _) = clipper.clip_line(vertex(0f32, 0f32, -3f32), vertex(0f32, 0f32, 0f32)).unwrap();
+        assert_eq!(start, vertex(0f32, 0f32, -1f32));
+    }
+
+}
@@ -0,0 +1,262 @@
+use super::{
+    canvas::{Canvas, Color},
+    paint::{ColorRamp, Interpolation}
+};
+
+// Scalar -> color maps for data visualization, t is clamped to [0, 1].
+// The matplotlib maps use the degree 6 polynomial fits by Matt Zucker (mattz on Shadertoy), within
+// five 8-bit steps of the tables. Turbo uses Google's degree 5 fit, which is close in the middle
+// but drifts by up to ~30 steps over the last few percent at either end.
+#[derive(Clone, Debug)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    Cividis,
+    Turbo,
+    // diverging, blue -> gray -> red (Moreland)
+    CoolWarm,
+    // diverging, ColorBrewer red -> white -> blue
+    RdBu,
+    // diverging, ColorBrewer red -> yellow -> purple
+    Spectral,
+    Grayscale,
+    Custom(ColorRamp),
+}
+
+type Polynomial = [[f32; 3]; 7];
+
+#[allow(clippy::excessive_precision)]
+const VIRIDIS: Polynomial = [
+    [0.2777273272234177, 0.005407344544966578, 0.3340998053353061],
+    [0.1050930431085774, 1.404613529898575, 1.384590162594685],
+    [-0.3308618287255563, 0.214847559468213, 0.09509516302823659],
+    [-4.634230498983486, -5.799100973351585, -19.33244095627987],
+    [6.228269936347081, 14.17993336680509, 56.69055260068105],
+    [4.776384997670288, -13.74514537774601, -65.35303263337234],
+    [-5.435455855934631, 4.645852612178535, 26.3124352495832],
+];
+
+#[allow(clippy::excessive_precision)]
+const MAGMA: Polynomial = [
+    [-0.002136485053939582, -0.000749655052795221, -0.005386127855323933],
+    [0.2516605407371642, 0.6775232436837668, 2.494026599312351],
+    [8.353717279216625, -3.577719514958484, 0.3144679030132573],
+    [-27.66873308576866, 14.26473078096533, -13.64921318813922],
+    [52.17613981234068, -27.94360607168351, 12.94416944238394],
+    [-50.76852536473588, 29.04658282127291, 4.23415299384598],
+    [18.65570506591883, -11.48977351997711, -5.601961508734096],
+];
+
+#[allow(clippy::excessive_precision)]
+const INFERNO: Polynomial = [
+    [0.0002189403691192265, 0.001651004631001012, -0.01948089843709184],
+    [0.1065134194856116, 0.5639564367884091, 3.932712388889277],
+    [11.60249308247187, -3.972853965665698, -15.9423941062914],
+    [-41.70399613139459, 17.43639888205313, 44.35414519872813],
+    [77.162935699427, -33.40235894210092, -81.80730925738993],
+    [-71.31942824499214, 32.62606426397723, 73.20951985803202],
+    [25.13112622477341, -12.24266895238567, -23.07032500287172],
+];
+
+#[allow(clippy::excessive_precision)]
+const PLASMA: Polynomial = [
+    [0.05873234392399702, 0.02333670892565664, 0.5433401826748754],
+    [2.176514634195958, 0.2383834171260182, 0.7539604599784036],
+    [-2.689460476458034, -7.455851135738909, 3.110799939717086],
+    [6.130348345893603, 42.3461881477227, -28.51885465332158],
+    [-11.10743619062271, -82.66631109428045, 60.13984767418263],
+    [10.02306557647065, 71.41361770095349, -54.07218655560067],
+    [-3.658713842777788, -22.93153465461149, 18.19190778539828],
+];
+
+// coefficients of 1, t, .., t^5 per channel
+#[allow(clippy::excessive_precision)]
+const TURBO: [[f32; 6]; 3] = [
+    [0.13572138, 4.61539260, -42.66032258, 132.13108234, -152.94239396, 59.28637943],
+    [0.09140261, 2.19418839, 4.84296658, -14.18503333, 4.27729857, 2.82956604],
+    [0.10667330, 12.64194608, -60.58204836, 110.36276771, -89.90310912, 27.34824973],
+];
+
+const CIVIDIS: [u32; 6] = [0x00224e, 0x35456c, 0x666970, 0x948e77, 0xc8b866, 0xfee838];
+const COOL_WARM: [u32; 5] = [0x3b4cc0, 0x8db0fe, 0xdddddd, 0xf49a7b, 0xb40426];
+const RD_BU: [u32; 11] = [
+    0x67001f, 0xb2182b, 0xd6604d, 0xf4a582, 0xfddbc7, 0xf7f7f7, 0xd1e5f0, 0x92c5de, 0x4393c3, 0x2166ac, 0x053061,
+];
+const SPECTRAL: [u32; 11] = [
+    0x9e0142, 0xd53e4f, 0xf46d43, 0xfdae61, 0xfee08b, 0xffffbf, 0xe6f598, 0xabdda4, 0x66c2a5, 0x3288bd, 0x5e4fa2,
+];
+
+fn polynomial(c: &Polynomial, t: f32) -> Color {
+    let mut rgb = [0f32; 3];
+    for (k, v) in rgb.iter_mut().enumerate() {
+        // Horner
+        *v = c.iter().rev().fold(0f32, |acc, coefficients| acc * t + coefficients[k]);
+    }
+    normalized(rgb)
+}
+
+fn turbo(t: f32) -> Color {
+    let mut rgb = [0f32; 3];
+    for (v, c) in rgb.iter_mut().zip(TURBO.iter()) {
+        *v = c.iter().rev().fold(0f32, |acc, coefficient| acc * t + coefficient);
+    }
+    normalized(rgb)
+}
+
+fn normalized(rgb: [f32; 3]) -> Color {
+    let channel = |v: f32| (v.clamp(0f32, 1f32) * 255f32).round() as u8;
+    Color::from_rgb(channel(rgb[0]), channel(rgb[1]), channel(rgb[2]))
+}
+
+// evenly spaced hex stops blended in Oklab
+fn stops(hex: &[u32], t: f32) -> Color {
+    let position = t * (hex.len() - 1) as f32;
+    let i = (position as usize).min(hex.len() - 2);
+    let color = |v: u32| Color::from_rgb((v >> 16) as u8, (v >> 8) as u8, v as u8);
+    Interpolation::Oklab.mix(&color(hex[i]), &color(hex[i + 1]), position - i as f32)
+}
+
+impl Colormap {
+
+    // evenly spaced colors, blended in Oklab
+    pub fn from_colors(colors: &[Color]) -> Self {
+        let mut ramp = ColorRamp::from_colors(colors);
+        ramp.set_interpolation(Interpolation::Oklab);
+        Colormap::Custom(ramp)
+    }
+
+    pub fn sample(&self, t: f32) -> Color {
+        let t = if t.is_nan() { 0f32 } else { t.clamp(0f32, 1f32) };
+        match self {
+            Colormap::Viridis => polynomial(&VIRIDIS, t),
+            Colormap::Magma => polynomial(&MAGMA, t),
+            Colormap::Inferno => polynomial(&INFERNO, t),
+            Colormap::Plasma => polynomial(&PLASMA, t),
+            Colormap::Cividis => stops(&CIVIDIS, t),
+            Colormap::Turbo => turbo(t),
+            Colormap::CoolWarm => stops(&COOL_WARM, t),
+            Colormap::RdBu => stops(&RD_BU, t),
+            Colormap::Spectral => stops(&SPECTRAL, t),
+            Colormap::Grayscale => Color::gray_shade(t),
+            Colormap::Custom(ramp) => ramp.sample(t),
+        }
+    }
+
+    // maps value from [min, max] to [0, 1] first
+    pub fn sample_range(&self, value: f32, min: f32, max: f32) -> Color {
+        let span = max - min;
+        self.sample(if span != 0f32 { (value - min) / span } else { 0f32 })
+    }
+
+    // `values` is row major with the canvas dimension, origin at the top-left corner
+    pub fn plot(&self, canvas: &mut Canvas, values: &[f32], min: f32, max: f32) {
+        let (width, height) = (canvas.get_width() as u32, canvas.get_height() as u32);
+        assert_eq!(values.len(), (width * height) as usize, "Value count does not match the canvas dimension.");
+        for y in 0..height {
+            for x in 0..width {
+                canvas.set_pixel(x, y, self.sample_range(values[(x + y * width) as usize], min, max));
+            }
+        }
+    }
+
+    // replaces the color buffer with the depth buffer, depths outside [near, far] are clamped
+    pub fn plot_depth(&self, canvas: &mut Canvas, near: f32, far: f32) {
+        for y in 0..canvas.get_height() as u32 {
+            for x in 0..canvas.get_width() as u32 {
+                let color = self.sample_range(canvas.get_depth(x, y), near, far);
+                canvas.set_pixel(x, y, color);
+            }
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close(color: Color, expected: (u8, u8, u8), tolerance: i32) -> bool {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).abs() <= tolerance;
+        d(color.get_red(), expected.0) && d(color.get_green(), expected.1) && d(color.get_blue(), expected.2)
+    }
+
+    #[test]
+    fn matplotlib_maps_follow_the_published_tables() {
+        // matplotlib's _cm_listed, scaled to 8 bits
+        let cases = [
+            (Colormap::Viridis, (68, 1, 84), (33, 145, 140), (253, 231, 37)),
+            (Colormap::Magma, (0, 0, 4), (183, 55, 121), (252, 253, 191)),
+            (Colormap::Inferno, (0, 0, 4), (188, 55, 84), (252, 255, 164)),
+            (Colormap::Plasma, (13, 8, 135), (204, 71, 120), (240, 249, 33)),
+        ];
+        for (map, low, middle, high) in cases {
+            for (t, expected) in [(0f32, low), (0.5, middle), (1f32, high)] {
+                let color = map.sample(t);
+                let rgb = (color.get_red(), color.get_green(), color.get_blue());
+                assert!(close(color, expected, 5), "{:?} at {}: {:?}", map, t, rgb);
+            }
+        }
+    }
+
+    #[test]
+    fn turbo_follows_the_published_table() {
+        // the fit's end drift, see the note on Colormap
+        assert!(close(Colormap::Turbo.sample(0f32), (48, 18, 59), 32));
+        assert!(close(Colormap::Turbo.sample(1f32), (122, 4, 3), 22));
+        assert!(close(Colormap::Turbo.sample(0.5), (164, 252, 60), 20));
+        // dark blue -> cyan -> green -> orange -> dark red
+        let c = Colormap::Turbo.sample(0.1);
+        assert!(c.get_blue() > c.get_green() && c.get_green() > c.get_red());
+        let c = Colormap::Turbo.sample(0.25);
+        assert!(c.get_blue() > c.get_red() && c.get_green() > c.get_red());
+        let c = Colormap::Turbo.sample(0.75);
+        assert!(c.get_red() > c.get_green() && c.get_green() > c.get_blue());
+        let c = Colormap::Turbo.sample(1f32);
+        assert!(c.get_red() > 100 && c.get_green() < 20 && c.get_blue() < 20);
+    }
+
+    #[test]
+    fn stop_maps_hit_their_stops() {
+        assert!(close(Colormap::Cividis.sample(0f32), (0x00, 0x22, 0x4e), 0));
+        assert!(close(Colormap::Cividis.sample(1f32), (0xfe, 0xe8, 0x38), 0));
+        assert!(close(Colormap::CoolWarm.sample(0.5), (0xdd, 0xdd, 0xdd), 0));
+        assert!(close(Colormap::RdBu.sample(0f32), (0x67, 0x00, 0x1f), 0));
+        assert!(close(Colormap::RdBu.sample(0.5), (0xf7, 0xf7, 0xf7), 0));
+        assert!(close(Colormap::Spectral.sample(1f32), (0x5e, 0x4f, 0xa2), 0));
+        assert!(close(Colormap::Grayscale.sample(1f32), (255, 255, 255), 0));
+    }
+
+    #[test]
+    fn out_of_range_input_is_clamped() {
+        let map = Colormap::Viridis;
+        assert_eq!(map.sample(-3f32), map.sample(0f32));
+        assert_eq!(map.sample(7f32), map.sample(1f32));
+        assert_eq!(map.sample(f32::NAN), map.sample(0f32));
+        assert_eq!(map.sample_range(15f32, 10f32, 20f32), map.sample(0.5));
+        assert_eq!(map.sample_range(3f32, 2f32, 2f32), map.sample(0f32));
+    }
+
+    #[test]
+    fn viridis_lightness_increases() {
+        let luma = |c: Color| 0.2126 * c.get_red() as f32 + 0.7152 * c.get_green() as f32 + 0.0722 * c.get_blue() as f32;
+        let mut previous = luma(Colormap::Viridis.sample(0f32));
+        for k in 1..=32 {
+            let value = luma(Colormap::Viridis.sample(k as f32 / 32f32));
+            assert!(value > previous);
+            previous = value;
+        }
+    }
+
+    #[test]
+    fn plot_writes_rows_from_the_top() {
+        let mut canvas = Canvas::new(2, 2);
+        let map = Colormap::Grayscale;
+        map.plot(&mut canvas, &[0f32, 1f32, 2f32, 3f32], 0f32, 3f32);
+        assert_eq!(canvas.get_pixel(0, 0), map.sample(0f32));
+        assert_eq!(canvas.get_pixel(1, 1), map.sample(1f32));
+    }
+
+}
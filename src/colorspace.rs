@@ -0,0 +1,531 @@
+use std::sync::OnceLock;
+
+use super::{
+    canvas::Color,
+    mathsf::Vec4
+};
+
+// Color channels are sRGB encoded (what ends up on screen), while blending,
+// lighting and interpolation are only physically meaningful in linear light.
+// Functions taking f32 work on normalized [0, 1] values.
+
+const ENCODE_LUT_SIZE: usize = 1 << 12;
+
+static DECODE_LUT: OnceLock<[f32; 256]> = OnceLock::new();
+static ENCODE_LUT: OnceLock<Vec<u8>> = OnceLock::new();
+
+// exact sRGB EOTF
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// exact inverse sRGB EOTF
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// 8-bit sRGB channel -> linear [0, 1], table lookup
+pub fn decode(c: u8) -> f32 {
+    DECODE_LUT.get_or_init(|| {
+        let mut lut = [0f32; 256];
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = srgb_to_linear(i as f32 / 255f32);
+        }
+        lut
+    })[c as usize]
+}
+
+// linear [0, 1] -> 8-bit sRGB channel, table lookup (input is clamped)
+pub fn encode(v: f32) -> u8 {
+    let lut = ENCODE_LUT.get_or_init(|| {
+        (0..ENCODE_LUT_SIZE)
+            .map(|i| (linear_to_srgb(i as f32 / (ENCODE_LUT_SIZE - 1) as f32) * 255f32 + 0.5f32) as u8)
+            .collect()
+    });
+    let index = (v.clamp(0f32, 1f32) * (ENCODE_LUT_SIZE - 1) as f32 + 0.5f32) as usize;
+    lut[index]
+}
+
+impl Color {
+    // linear-light RGBA in [0, 1]; alpha is never gamma encoded
+    pub fn from_linear(v: &Vec4) -> Self {
+        let alpha = (v.get_w().clamp(0f32, 1f32) * 255f32 + 0.5f32) as u8;
+        Self::from_rgba(encode(v.get_x()), encode(v.get_y()), encode(v.get_z()), alpha)
+    }
+    pub fn to_linear(&self) -> Vec4 {
+        Vec4::new(
+            decode(self.get_red()),
+            decode(self.get_green()),
+            decode(self.get_blue()),
+            self.get_alpha() as f32 / 255f32
+        )
+    }
+    // interpolates in linear light, avoiding the dark fringes of gamma-space mixing
+    pub fn lerp_linear(c0: &Color, c1: &Color, f: f32) -> Self {
+        let (v0, v1) = (c0.to_linear(), c1.to_linear());
+        Self::from_linear(&Vec4::lerp(&v0, &v1, f))
+    }
+}
+
+// Cylindrical and perceptual color models. Hue is in degrees [0, 360), every other
+// component is normalized to [0, 1] except Lab/LCh (L in [0, 100]) and XYZ (Y = 1 is white).
+// HSV/HSL/HWB are computed from the encoded sRGB values, as CSS does, while XYZ, Lab and
+// Oklab go through linear light. Converting back to Color clamps out of gamut values and
+// yields an opaque color.
+
+macro_rules! color_model {
+    ($name:ident { $($field:ident: $getter:ident),* }) => {
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub struct $name {
+            $($field: f32),*
+        }
+        impl $name {
+            pub fn new($($field: f32),*) -> Self {
+                $name { $($field),* }
+            }
+            $(pub fn $getter(&self) -> f32 {
+                self.$field
+            })*
+        }
+    };
+}
+
+color_model!(Hsv { hue: get_hue, saturation: get_saturation, value: get_value });
+color_model!(Hsl { hue: get_hue, saturation: get_saturation, lightness: get_lightness });
+color_model!(Hwb { hue: get_hue, whiteness: get_whiteness, blackness: get_blackness });
+color_model!(Xyz { x: get_x, y: get_y, z: get_z });
+color_model!(Lab { l: get_l, a: get_a, b: get_b });
+color_model!(Lch { l: get_l, c: get_c, h: get_h });
+color_model!(Oklab { l: get_l, a: get_a, b: get_b });
+color_model!(Oklch { l: get_l, c: get_c, h: get_h });
+
+// D65, the white point of sRGB
+const WHITE_X: f32 = 0.95047;
+const WHITE_Y: f32 = 1.0;
+const WHITE_Z: f32 = 1.08883;
+
+fn normalized_rgb(c: &Color) -> (f32, f32, f32) {
+    (c.get_red() as f32 / 255f32, c.get_green() as f32 / 255f32, c.get_blue() as f32 / 255f32)
+}
+
+fn from_normalized_rgb(r: f32, g: f32, b: f32) -> Color {
+    let channel = |v: f32| (v.clamp(0f32, 1f32) * 255f32).round() as u8;
+    Color::from_rgb(channel(r), channel(g), channel(b))
+}
+
+fn linear_rgb(c: &Color) -> (f32, f32, f32) {
+    (decode(c.get_red()), decode(c.get_green()), decode(c.get_blue()))
+}
+
+fn from_linear_rgb(r: f32, g: f32, b: f32) -> Color {
+    Color::from_rgb(encode(r), encode(g), encode(b))
+}
+
+// hue in degrees, chroma and max channel of encoded sRGB
+fn hue_chroma(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let hue = if chroma <= 0f32 {
+        0f32
+    } else if max == r {
+        60f32 * ((g - b) / chroma).rem_euclid(6f32)
+    } else if max == g {
+        60f32 * ((b - r) / chroma + 2f32)
+    } else {
+        60f32 * ((r - g) / chroma + 4f32)
+    };
+    (hue, chroma, max)
+}
+
+// rgb from hue, chroma and the amount added to every channel
+fn from_hue_chroma(hue: f32, chroma: f32, m: f32) -> Color {
+    let h = hue.rem_euclid(360f32) / 60f32;
+    let x = chroma * (1f32 - (h.rem_euclid(2f32) - 1f32).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0f32),
+        1 => (x, chroma, 0f32),
+        2 => (0f32, chroma, x),
+        3 => (0f32, x, chroma),
+        4 => (x, 0f32, chroma),
+        _ => (chroma, 0f32, x),
+    };
+    from_normalized_rgb(r + m, g + m, b + m)
+}
+
+fn polar(a: f32, b: f32) -> (f32, f32) {
+    ((a * a + b * b).sqrt(), b.atan2(a).to_degrees().rem_euclid(360f32))
+}
+
+fn cartesian(c: f32, h: f32) -> (f32, f32) {
+    let h = h.to_radians();
+    (c * h.cos(), c * h.sin())
+}
+
+impl From<Color> for Hsv {
+    fn from(c: Color) -> Self {
+        let (r, g, b) = normalized_rgb(&c);
+        let (hue, chroma, max) = hue_chroma(r, g, b);
+        let saturation = if max > 0f32 { chroma / max } else { 0f32 };
+        Hsv::new(hue, saturation, max)
+    }
+}
+
+impl From<Hsv> for Color {
+    fn from(c: Hsv) -> Self {
+        let chroma = c.value * c.saturation;
+        from_hue_chroma(c.hue, chroma, c.value - chroma)
+    }
+}
+
+impl From<Color> for Hsl {
+    fn from(c: Color) -> Self {
+        let (r, g, b) = normalized_rgb(&c);
+        let (hue, chroma, max) = hue_chroma(r, g, b);
+        let lightness = max - chroma / 2f32;
+        let saturation = if lightness <= 0f32 || lightness >= 1f32 {
+            0f32
+        } else {
+            chroma / (1f32 - (2f32 * lightness - 1f32).abs())
+        };
+        Hsl::new(hue, saturation, lightness)
+    }
+}
+
+impl From<Hsl> for Color {
+    fn from(c: Hsl) -> Self {
+        let chroma = (1f32 - (2f32 * c.lightness - 1f32).abs()) * c.saturation;
+        from_hue_chroma(c.hue, chroma, c.lightness - chroma / 2f32)
+    }
+}
+
+impl From<Color> for Hwb {
+    fn from(c: Color) -> Self {
+        let (r, g, b) = normalized_rgb(&c);
+        let (hue, _, max) = hue_chroma(r, g, b);
+        Hwb::new(hue, r.min(g).min(b), 1f32 - max)
+    }
+}
+
+impl From<Hwb> for Color {
+    fn from(c: Hwb) -> Self {
+        let (mut w, mut b) = (c.whiteness, c.blackness);
+        // whiteness + blackness >= 1 is a shade of gray
+        if w + b >= 1f32 {
+            let gray = w / (w + b);
+            return from_normalized_rgb(gray, gray, gray);
+        }
+        w = w.max(0f32);
+        b = b.max(0f32);
+        let value = 1f32 - b;
+        Color::from(Hsv::new(c.hue, 1f32 - w / value, value))
+    }
+}
+
+// published constants, kept verbatim
+#[allow(clippy::excessive_precision)]
+impl From<Color> for Xyz {
+    fn from(c: Color) -> Self {
+        let (r, g, b) = linear_rgb(&c);
+        Xyz::new(
+            0.4124564 * r + 0.3575761 * g + 0.1804375 * b,
+            0.2126729 * r + 0.7151522 * g + 0.0721750 * b,
+            0.0193339 * r + 0.1191920 * g + 0.9503041 * b
+        )
+    }
+}
+
+// published constants, kept verbatim
+#[allow(clippy::excessive_precision)]
+impl From<Xyz> for Color {
+    fn from(c: Xyz) -> Self {
+        from_linear_rgb(
+            3.2404542 * c.x - 1.5371385 * c.y - 0.4985314 * c.z,
+            -0.9692660 * c.x + 1.8760108 * c.y + 0.0415560 * c.z,
+            0.0556434 * c.x - 0.2040259 * c.y + 1.0572252 * c.z
+        )
+    }
+}
+
+const LAB_DELTA: f32 = 6.0 / 29.0;
+
+fn lab_f(t: f32) -> f32 {
+    if t > LAB_DELTA * LAB_DELTA * LAB_DELTA {
+        t.cbrt()
+    } else {
+        t / (3f32 * LAB_DELTA * LAB_DELTA) + 4f32 / 29f32
+    }
+}
+
+fn lab_f_inverse(t: f32) -> f32 {
+    if t > LAB_DELTA {
+        t * t * t
+    } else {
+        3f32 * LAB_DELTA * LAB_DELTA * (t - 4f32 / 29f32)
+    }
+}
+
+impl From<Xyz> for Lab {
+    fn from(c: Xyz) -> Self {
+        let (fx, fy, fz) = (lab_f(c.x / WHITE_X), lab_f(c.y / WHITE_Y), lab_f(c.z / WHITE_Z));
+        Lab::new(116f32 * fy - 16f32, 500f32 * (fx - fy), 200f32 * (fy - fz))
+    }
+}
+
+impl From<Lab> for Xyz {
+    fn from(c: Lab) -> Self {
+        let fy = (c.l + 16f32) / 116f32;
+        let fx = fy + c.a / 500f32;
+        let fz = fy - c.b / 200f32;
+        Xyz::new(WHITE_X * lab_f_inverse(fx), WHITE_Y * lab_f_inverse(fy), WHITE_Z * lab_f_inverse(fz))
+    }
+}
+
+impl From<Color> for Lab {
+    fn from(c: Color) -> Self {
+        Lab::from(Xyz::from(c))
+    }
+}
+
+impl From<Lab> for Color {
+    fn from(c: Lab) -> Self {
+        Color::from(Xyz::from(c))
+    }
+}
+
+impl From<Lab> for Lch {
+    fn from(c: Lab) -> Self {
+        let (chroma, hue) = polar(c.a, c.b);
+        Lch::new(c.l, chroma, hue)
+    }
+}
+
+impl From<Lch> for Lab {
+    fn from(c: Lch) -> Self {
+        let (a, b) = cartesian(c.c, c.h);
+        Lab::new(c.l, a, b)
+    }
+}
+
+impl From<Color> for Lch {
+    fn from(c: Color) -> Self {
+        Lch::from(Lab::from(c))
+    }
+}
+
+impl From<Lch> for Color {
+    fn from(c: Lch) -> Self {
+        Color::from(Lab::from(c))
+    }
+}
+
+// published constants, kept verbatim
+#[allow(clippy::excessive_precision)]
+impl Oklab {
+    pub fn from_linear_rgb(r: f32, g: f32, b: f32) -> Self {
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        Oklab::new(
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s
+        )
+    }
+    pub fn to_linear_rgb(&self) -> (f32, f32, f32) {
+        let l = self.l + 0.3963377774 * self.a + 0.2158037573 * self.b;
+        let m = self.l - 0.1055613458 * self.a - 0.0638541728 * self.b;
+        let s = self.l - 0.0894841775 * self.a - 1.2914855480 * self.b;
+        let (l, m, s) = (l * l * l, m * m * m, s * s * s);
+        (
+            4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s
+        )
+    }
+    pub fn lerp(c0: &Oklab, c1: &Oklab, f: f32) -> Self {
+        Oklab::new(
+            c0.l + (c1.l - c0.l) * f,
+            c0.a + (c1.a - c0.a) * f,
+            c0.b + (c1.b - c0.b) * f
+        )
+    }
+}
+
+impl From<Color> for Oklab {
+    fn from(c: Color) -> Self {
+        let (r, g, b) = linear_rgb(&c);
+        Oklab::from_linear_rgb(r, g, b)
+    }
+}
+
+impl From<Oklab> for Color {
+    fn from(c: Oklab) -> Self {
+        let (r, g, b) = c.to_linear_rgb();
+        from_linear_rgb(r, g, b)
+    }
+}
+
+impl From<Oklab> for Oklch {
+    fn from(c: Oklab) -> Self {
+        let (chroma, hue) = polar(c.a, c.b);
+        Oklch::new(c.l, chroma, hue)
+    }
+}
+
+impl From<Oklch> for Oklab {
+    fn from(c: Oklch) -> Self {
+        let (a, b) = cartesian(c.c, c.h);
+        Oklab::new(c.l, a, b)
+    }
+}
+
+impl From<Color> for Oklch {
+    fn from(c: Color) -> Self {
+        Oklch::from(Oklab::from(c))
+    }
+}
+
+impl From<Oklch> for Color {
+    fn from(c: Oklch) -> Self {
+        Color::from(Oklab::from(c))
+    }
+}
+
+impl Color {
+    // perceptually even blend, alpha is interpolated linearly
+    pub fn lerp_oklab(c0: &Color, c1: &Color, f: f32) -> Self {
+        let mixed = Oklab::lerp(&Oklab::from(*c0), &Oklab::from(*c1), f);
+        let mut c = Color::from(mixed);
+        c.set_alpha((c0.get_alpha() as f32 + (c1.get_alpha() as f32 - c0.get_alpha() as f32) * f).round() as u8);
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::canvas::{Blending, Canvas};
+
+    #[test]
+    fn lookup_tables_round_trip() {
+        for c in 0..=255u8 {
+            assert_eq!(encode(decode(c)), c);
+            assert!((decode(c) - srgb_to_linear(c as f32 / 255f32)).abs() < 1e-6);
+        }
+        assert_eq!(encode(-1f32), 0);
+        assert_eq!(encode(2f32), 255);
+    }
+
+    #[test]
+    fn transfer_functions_are_inverse() {
+        for k in 0..=1000 {
+            let v = k as f32 / 1000f32;
+            assert!((srgb_to_linear(linear_to_srgb(v)) - v).abs() < 1e-5);
+        }
+        // both pieces meet at the threshold
+        assert!((srgb_to_linear(0.04045) - 0.04045 / 12.92).abs() < 1e-7);
+        assert!((linear_to_srgb(1f32) - 1f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn linear_colors() {
+        let c = Color::from_rgba(10, 128, 250, 77);
+        assert_eq!(Color::from_linear(&c.to_linear()), c);
+        assert_eq!(Color::from_linear(&Vec4::new(0.5, 0f32, 1f32, 0.5)), Color::from_rgba(188, 0, 255, 128));
+        // the midpoint of black and white is 50% light, not 50% code value
+        let mid = Color::lerp_linear(&Color::black(), &Color::white(), 0.5);
+        assert_eq!(mid.get_red(), 188);
+    }
+
+    #[test]
+    fn linear_blending() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.clear_color(Color::black());
+        canvas.set_blending(Blending::Linear);
+        canvas.draw_pixel(0, 0, 0f32, Color::from_rgba(255, 255, 255, 128));
+        let c = canvas.get_pixel(1, 1);
+        assert_eq!(c.get_red(), encode(128f32 / 255f32));
+        assert_eq!(c.get_alpha(), 255);
+        canvas.set_blending(Blending::Gamma);
+        canvas.clear_color(Color::black());
+        canvas.draw_pixel(0, 0, 0f32, Color::from_rgba(255, 255, 255, 128));
+        assert_eq!(canvas.get_pixel(1, 1).get_red(), 128);
+    }
+
+
+    fn close(a: &Color, b: &Color, tolerance: u8) -> bool {
+        a.get_red().abs_diff(b.get_red()) <= tolerance
+            && a.get_green().abs_diff(b.get_green()) <= tolerance
+            && a.get_blue().abs_diff(b.get_blue()) <= tolerance
+    }
+
+    // every 5th code value per channel, 52^3 colors
+    fn samples() -> impl Iterator<Item = Color> {
+        (0..=255u8).step_by(5).flat_map(|r| {
+            (0..=255u8).step_by(5).flat_map(move |g| (0..=255u8).step_by(5).map(move |b| Color::from_rgb(r, g, b)))
+        })
+    }
+
+    #[test]
+    fn cylindrical_models_round_trip() {
+        for c in samples() {
+            assert_eq!(Color::from(Hsv::from(c)), c);
+            assert_eq!(Color::from(Hsl::from(c)), c);
+            assert_eq!(Color::from(Hwb::from(c)), c);
+        }
+    }
+
+    #[test]
+    fn perceptual_models_round_trip() {
+        for c in samples() {
+            assert!(close(&Color::from(Xyz::from(c)), &c, 1));
+            assert!(close(&Color::from(Lab::from(c)), &c, 1));
+            assert!(close(&Color::from(Lch::from(c)), &c, 1));
+            assert!(close(&Color::from(Oklab::from(c)), &c, 1));
+            assert!(close(&Color::from(Oklch::from(c)), &c, 1));
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        let hsv = Hsv::from(Color::from_rgb(255, 128, 0));
+        assert!((hsv.get_hue() - 30.12).abs() < 0.01 && hsv.get_saturation() == 1f32 && hsv.get_value() == 1f32);
+        let hsl = Hsl::from(Color::from_rgb(0, 0, 255));
+        assert_eq!((hsl.get_hue(), hsl.get_saturation(), hsl.get_lightness()), (240f32, 1f32, 0.5));
+        assert_eq!(Color::from(Hsl::new(120f32, 1f32, 0.25)), Color::from_rgb(0, 128, 0));
+        // whiteness + blackness past 1 is gray
+        assert_eq!(Color::from(Hwb::new(0f32, 0.6, 0.6)), Color::from_rgb(128, 128, 128));
+
+        let white = Lab::from(Color::white());
+        assert!((white.get_l() - 100f32).abs() < 0.01 && white.get_a().abs() < 0.01 && white.get_b().abs() < 0.01);
+        let red = Lab::from(Color::red());
+        assert!((red.get_l() - 53.24).abs() < 0.05 && (red.get_a() - 80.09).abs() < 0.1 && (red.get_b() - 67.20).abs() < 0.1);
+        let white = Oklab::from(Color::white());
+        assert!((white.get_l() - 1f32).abs() < 1e-3 && white.get_a().abs() < 1e-3 && white.get_b().abs() < 1e-3);
+        let red = Oklch::from(Color::red());
+        assert!((red.get_l() - 0.628).abs() < 1e-3 && (red.get_c() - 0.2577).abs() < 1e-3 && (red.get_h() - 29.23).abs() < 0.05);
+    }
+
+    #[test]
+    fn oklab_interpolation() {
+        let (a, b) = (Color::from_rgba(255, 0, 0, 0), Color::from_rgba(0, 0, 255, 255));
+        assert!(close(&Color::lerp_oklab(&a, &b, 0f32), &a, 1));
+        assert!(close(&Color::lerp_oklab(&a, &b, 1f32), &b, 1));
+        let mid = Color::lerp_oklab(&a, &b, 0.5);
+        assert_eq!(mid.get_alpha(), 128);
+        // lightness is halfway in Oklab
+        let l = |c: &Color| Oklab::from(*c).get_l();
+        assert!((l(&mid) - (l(&a) + l(&b)) / 2f32).abs() < 0.01);
+    }
+
+}
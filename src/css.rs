@@ -0,0 +1,437 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    str::FromStr
+};
+
+use super::{
+    canvas::Color,
+    colorspace::Hsl
+};
+
+// Accepted syntax: #rgb, #rgba, #rrggbb, #rrggbbaa, rgb()/rgba() and hsl()/hsla() in both
+// the legacy comma and the modern space/slash forms, the CSS named colors and `transparent`.
+// Display writes #rrggbb for opaque colors and #rrggbbaa otherwise, which parses back exactly.
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseColorError {
+    Empty,
+    InvalidHex(String),
+    UnknownName(String),
+    UnknownFunction(String),
+    InvalidArguments(String),
+}
+
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseColorError::Empty => write!(f, "empty color string"),
+            ParseColorError::InvalidHex(s) => write!(f, "invalid hex color '{}'", s),
+            ParseColorError::UnknownName(s) => write!(f, "unknown color name '{}'", s),
+            ParseColorError::UnknownFunction(s) => write!(f, "unknown color function '{}'", s),
+            ParseColorError::InvalidArguments(s) => write!(f, "invalid color function arguments '{}'", s),
+        }
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        if s.is_empty() {
+            return Err(ParseColorError::Empty);
+        }
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex).ok_or(ParseColorError::InvalidHex(s.clone()));
+        }
+        if let Some(open) = s.find('(') {
+            return parse_function(&s[..open], &s[open + 1..], &s);
+        }
+        if s == "transparent" {
+            return Ok(Color::transparent());
+        }
+        named(&s).ok_or(ParseColorError::UnknownName(s))
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.get_red(), self.get_green(), self.get_blue())?;
+        if self.get_alpha() != u8::MAX {
+            write!(f, "{:02x}", self.get_alpha())?;
+        }
+        Ok(())
+    }
+}
+
+impl Color {
+    // always #rrggbbaa
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}{:02x}", self.get_red(), self.get_green(), self.get_blue(), self.get_alpha())
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        named(&name.to_ascii_lowercase())
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    match hex.len() {
+        3 | 4 => {
+            let short = |i: usize| digit(i).map(|d| d * 17);
+            let alpha = if hex.len() == 4 { short(3)? } else { u8::MAX };
+            Some(Color::from_rgba(short(0)?, short(1)?, short(2)?, alpha))
+        }
+        6 | 8 => {
+            let alpha = if hex.len() == 8 { pair(6)? } else { u8::MAX };
+            Some(Color::from_rgba(pair(0)?, pair(2)?, pair(4)?, alpha))
+        }
+        _ => None,
+    }
+}
+
+fn parse_function(name: &str, rest: &str, source: &str) -> Result<Color, ParseColorError> {
+
+    let invalid = || ParseColorError::InvalidArguments(source.to_string());
+
+    let body = rest.strip_suffix(')').ok_or_else(invalid)?;
+    // legacy syntax separates with commas, modern one with spaces and a slash before alpha
+    let (channels, alpha) = match body.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha.trim())),
+        None => (body, None),
+    };
+    let mut args: Vec<&str> = channels
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .collect();
+    let alpha = match (alpha, args.len()) {
+        (Some(a), 3) => Some(a),
+        (None, 4) => args.pop(),
+        (None, 3) => None,
+        _ => return Err(invalid()),
+    };
+    let alpha = match alpha {
+        Some(a) => (parse_fraction(a).ok_or_else(invalid)? * 255f32).round() as u8,
+        None => u8::MAX,
+    };
+
+    let mut color = match name.trim() {
+        "rgb" | "rgba" => {
+            let channel = |a: &str| -> Option<u8> {
+                let v = match a.strip_suffix('%') {
+                    Some(p) => number(p)? / 100f32 * 255f32,
+                    None => number(a)?,
+                };
+                Some(v.clamp(0f32, 255f32).round() as u8)
+            };
+            Color::from_rgb(
+                channel(args[0]).ok_or_else(invalid)?,
+                channel(args[1]).ok_or_else(invalid)?,
+                channel(args[2]).ok_or_else(invalid)?
+            )
+        }
+        "hsl" | "hsla" => {
+            let hue = parse_angle(args[0]).ok_or_else(invalid)?;
+            let percent = |a: &str| -> Option<f32> {
+                Some((number(a.strip_suffix('%').unwrap_or(a))? / 100f32).clamp(0f32, 1f32))
+            };
+            Color::from(Hsl::new(
+                hue,
+                percent(args[1]).ok_or_else(invalid)?,
+                percent(args[2]).ok_or_else(invalid)?
+            ))
+        }
+        other => return Err(ParseColorError::UnknownFunction(other.to_string())),
+    };
+    color.set_alpha(alpha);
+    Ok(color)
+
+}
+
+// f32::from_str also takes nan, inf and infinity, which CSS doesn't
+fn number(a: &str) -> Option<f32> {
+    a.parse::<f32>().ok().filter(|v| v.is_finite())
+}
+
+// alpha: a number in [0, 1] or a percentage
+fn parse_fraction(a: &str) -> Option<f32> {
+    let v = match a.strip_suffix('%') {
+        Some(p) => number(p)? / 100f32,
+        None => number(a)?,
+    };
+    Some(v.clamp(0f32, 1f32))
+}
+
+// degrees when unitless
+fn parse_angle(a: &str) -> Option<f32> {
+    let units: [(&str, f32); 4] = [
+        ("deg", 1f32),
+        ("grad", 0.9),
+        ("rad", 180f32 / std::f32::consts::PI),
+        ("turn", 360f32),
+    ];
+    for (unit, scale) in units {
+        if let Some(v) = a.strip_suffix(unit) {
+            return Some(number(v)? * scale);
+        }
+    }
+    number(a)
+}
+
+fn named(name: &str) -> Option<Color> {
+    NAMED_COLORS
+        .binary_search_by(|(n, _)| n.cmp(&name))
+        .ok()
+        .map(|i| {
+            let v = NAMED_COLORS[i].1;
+            Color::from_rgb((v >> 16) as u8, (v >> 8) as u8, v as u8)
+        })
+}
+
+// CSS Color Module Level 4, sorted for binary search
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn parse(s: &str) -> Color {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn hex_forms() {
+        assert_eq!(parse("#f80"), Color::from_rgba(0xff, 0x88, 0x00, 255));
+        assert_eq!(parse("#f808"), Color::from_rgba(0xff, 0x88, 0x00, 0x88));
+        assert_eq!(parse("#FF8001"), Color::from_rgba(0xff, 0x80, 0x01, 255));
+        assert_eq!(parse("  #ff800140 "), Color::from_rgba(0xff, 0x80, 0x01, 0x40));
+    }
+
+    #[test]
+    fn rgb_functions() {
+        let orange = Color::from_rgba(255, 128, 0, 255);
+        assert_eq!(parse("rgb(255, 128, 0)"), orange);
+        assert_eq!(parse("rgb(255 128 0)"), orange);
+        assert_eq!(parse("RGB(100%, 50.2%, 0%)"), orange);
+        assert_eq!(parse("rgba(255, 128, 0, 0.5)"), Color::from_rgba(255, 128, 0, 128));
+        assert_eq!(parse("rgb(255 128 0 / 25%)"), Color::from_rgba(255, 128, 0, 64));
+        assert_eq!(parse("rgba(255 128 0 / 1)"), orange);
+        // out of range values are clamped
+        assert_eq!(parse("rgb(300, -5, 0, 2)"), Color::from_rgba(255, 0, 0, 255));
+    }
+
+    #[test]
+    fn hsl_functions_and_angles() {
+        assert_eq!(parse("hsl(120, 100%, 25%)"), Color::from_rgb(0, 128, 0));
+        assert_eq!(parse("hsl(120deg 100% 25%)"), Color::from_rgb(0, 128, 0));
+        assert_eq!(parse("hsla(240, 100%, 50%, 0.5)"), Color::from_rgba(0, 0, 255, 128));
+        assert_eq!(parse("hsl(240 100% 50% / 50%)"), Color::from_rgba(0, 0, 255, 128));
+        for angle in ["0.5turn", "200grad", "3.14159265rad", "180deg", "180", "-180"] {
+            assert_eq!(parse(&format!("hsl({} 100% 50%)", angle)), Color::from_rgb(0, 255, 255), "{}", angle);
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(parse("rebeccapurple"), Color::from_rgb(0x66, 0x33, 0x99));
+        assert_eq!(parse("Gray"), parse("grey"));
+        assert_eq!(parse("transparent"), Color::transparent());
+        assert_eq!(Color::from_name("AliceBlue"), Some(Color::from_rgb(0xf0, 0xf8, 0xff)));
+        assert!(NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+        for (name, _) in NAMED_COLORS {
+            assert!(Color::from_name(name).is_some());
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s: &str| s.parse::<Color>().unwrap_err();
+        assert_eq!(error("   "), ParseColorError::Empty);
+        assert_eq!(error("#12"), ParseColorError::InvalidHex("#12".to_string()));
+        assert_eq!(error("#12345g"), ParseColorError::InvalidHex("#12345g".to_string()));
+        assert_eq!(error("#ü23"), ParseColorError::InvalidHex("#ü23".to_string()));
+        assert_eq!(error("blurple"), ParseColorError::UnknownName("blurple".to_string()));
+        assert_eq!(error("lab(50 0 0)"), ParseColorError::UnknownFunction("lab".to_string()));
+        for invalid in ["rgb(1, 2)", "rgb(1, 2, 3", "rgb(1 2 3 4 5)", "rgb(1, 2, 3 / 4, 5)", "rgb(a, 2, 3)", "hsl(1x, 2%, 3%)", "rgb(1 2 / 3)"] {
+            assert_eq!(error(invalid), ParseColorError::InvalidArguments(invalid.to_string()), "{}", invalid);
+        }
+        assert_eq!(error("Blurple").to_string(), "unknown color name 'blurple'");
+    }
+
+    #[test]
+    fn non_finite_components_are_rejected() {
+        for invalid in ["rgb(nan, inf, 1)", "rgb(1 2 infinity)", "rgb(1, 2, 3, nan)", "rgb(nan% 0 0)", "hsl(inf, 50%, 50%)", "hsl(nandeg 1% 1%)", "hsl(1 -inf% 1%)"] {
+            assert!(matches!(invalid.parse::<Color>(), Err(ParseColorError::InvalidArguments(_))), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        assert_eq!(Color::from_rgb(255, 128, 0).to_string(), "#ff8000");
+        assert_eq!(Color::from_rgba(1, 2, 3, 4).to_string(), "#01020304");
+        assert_eq!(Color::from_rgb(1, 2, 3).to_hex(), "#010203ff");
+        for v in (0..=255u32).step_by(3) {
+            let c = Color::from_rgba(v as u8, (v * 7 % 256) as u8, (255 - v) as u8, (v * 13 % 256) as u8);
+            assert_eq!(parse(&c.to_string()), c);
+            assert_eq!(parse(&c.to_hex()), c);
+        }
+    }
+
+}
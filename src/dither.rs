@@ -0,0 +1,384 @@
+use std::sync::OnceLock;
+
+use super::{
+    canvas::{Canvas, Color},
+    palette::Palette
+};
+
+pub enum Dithering {
+    None,
+    Bayer2,
+    Bayer4,
+    Bayer8,
+    BlueNoise,
+    FloydSteinberg,
+    Atkinson,
+    Sierra,
+}
+
+// what the canvas is reduced to
+pub enum Quantizer {
+    Palette(Palette),
+    // bits per channel, e.g. (5, 6, 5)
+    BitDepth { red: u8, green: u8, blue: u8 },
+}
+
+const BLUE_NOISE_SIZE: usize = 32;
+
+static BLUE_NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+
+// (dx, dy, weight)
+const FLOYD_STEINBERG: [(i32, i32, f32); 4] = [
+    (1, 0, 7.0 / 16.0), (-1, 1, 3.0 / 16.0), (0, 1, 5.0 / 16.0), (1, 1, 1.0 / 16.0),
+];
+// only 6/8 of the error is propagated, which keeps highlights and shadows clean
+const ATKINSON: [(i32, i32, f32); 6] = [
+    (1, 0, 1.0 / 8.0), (2, 0, 1.0 / 8.0),
+    (-1, 1, 1.0 / 8.0), (0, 1, 1.0 / 8.0), (1, 1, 1.0 / 8.0),
+    (0, 2, 1.0 / 8.0),
+];
+const SIERRA: [(i32, i32, f32); 10] = [
+    (1, 0, 5.0 / 32.0), (2, 0, 3.0 / 32.0),
+    (-2, 1, 2.0 / 32.0), (-1, 1, 4.0 / 32.0), (0, 1, 5.0 / 32.0), (1, 1, 4.0 / 32.0), (2, 1, 2.0 / 32.0),
+    (-1, 2, 2.0 / 32.0), (0, 2, 3.0 / 32.0), (1, 2, 2.0 / 32.0),
+];
+
+impl Quantizer {
+
+    pub fn bit_depth(bits: u8) -> Self {
+        Quantizer::BitDepth { red: bits, green: bits, blue: bits }
+    }
+
+    pub fn quantize(&self, red: f32, green: f32, blue: f32) -> (u8, u8, u8) {
+        match self {
+            // nothing to snap to, pixels are left as they are
+            Quantizer::Palette(palette) if palette.is_empty() => {
+                let keep = |v: f32| (v.clamp(0f32, 255f32) + 0.5f32) as u8;
+                (keep(red), keep(green), keep(blue))
+            }
+            Quantizer::Palette(palette) => {
+                let c = palette.get(palette.nearest(red, green, blue));
+                (c.get_red(), c.get_green(), c.get_blue())
+            }
+            Quantizer::BitDepth { red: r, green: g, blue: b } => {
+                (quantize_channel(red, *r), quantize_channel(green, *g), quantize_channel(blue, *b))
+            }
+        }
+    }
+
+    // typical distance between two output levels, scales the ordered dither threshold
+    fn spread(&self) -> f32 {
+        match self {
+            Quantizer::Palette(palette) => {
+                // mean distance to the closest other entry, per channel
+                let colors = palette.colors();
+                if colors.len() < 2 {
+                    return 0f32;
+                }
+                let mut total = 0f32;
+                for (i, a) in colors.iter().enumerate() {
+                    let mut closest = f32::INFINITY;
+                    for (j, b) in colors.iter().enumerate() {
+                        if i == j {
+                            continue;
+                        }
+                        let dr = a.get_red() as f32 - b.get_red() as f32;
+                        let dg = a.get_green() as f32 - b.get_green() as f32;
+                        let db = a.get_blue() as f32 - b.get_blue() as f32;
+                        closest = closest.min(dr * dr + dg * dg + db * db);
+                    }
+                    total += closest.sqrt();
+                }
+                total / colors.len() as f32 / 3f32.sqrt()
+            }
+            Quantizer::BitDepth { red, green, blue } => {
+                let bits = (*red).min(*green).min(*blue).clamp(1, 8);
+                255f32 / ((1u32 << bits) - 1) as f32
+            }
+        }
+    }
+
+}
+
+fn quantize_channel(value: f32, bits: u8) -> u8 {
+    let levels = ((1u32 << bits.clamp(1, 8)) - 1) as f32;
+    let step = (value.clamp(0f32, 255f32) / 255f32 * levels).round();
+    (step * 255f32 / levels + 0.5f32) as u8
+}
+
+pub fn dither(canvas: &mut Canvas, quantizer: &Quantizer, method: Dithering) {
+    match method {
+        Dithering::None => ordered(canvas, quantizer, 1, &[0f32]),
+        Dithering::Bayer2 => ordered(canvas, quantizer, 2, &bayer(2)),
+        Dithering::Bayer4 => ordered(canvas, quantizer, 4, &bayer(4)),
+        Dithering::Bayer8 => ordered(canvas, quantizer, 8, &bayer(8)),
+        Dithering::BlueNoise => ordered(canvas, quantizer, BLUE_NOISE_SIZE, blue_noise()),
+        Dithering::FloydSteinberg => diffuse(canvas, quantizer, &FLOYD_STEINBERG),
+        Dithering::Atkinson => diffuse(canvas, quantizer, &ATKINSON),
+        Dithering::Sierra => diffuse(canvas, quantizer, &SIERRA),
+    }
+}
+
+// thresholds in [-0.5, 0.5), row major
+fn bayer(size: usize) -> Vec<f32> {
+    let mut m = vec![0u32];
+    let mut n = 1;
+    while n < size {
+        let mut next = vec![0u32; 4 * n * n];
+        for y in 0..n {
+            for x in 0..n {
+                let v = 4 * m[x + y * n];
+                next[x + y * 2 * n] = v;
+                next[(x + n) + y * 2 * n] = v + 2;
+                next[x + (y + n) * 2 * n] = v + 3;
+                next[(x + n) + (y + n) * 2 * n] = v + 1;
+            }
+        }
+        m = next;
+        n *= 2;
+    }
+    let cells = (n * n) as f32;
+    m.iter().map(|v| (*v as f32 + 0.5f32) / cells - 0.5f32).collect()
+}
+
+fn ordered(canvas: &mut Canvas, quantizer: &Quantizer, size: usize, thresholds: &[f32]) {
+    let spread = quantizer.spread();
+    for y in 0..canvas.get_height() as u32 {
+        for x in 0..canvas.get_width() as u32 {
+            let c = canvas.get_pixel(x, y);
+            let offset = thresholds[(x as usize % size) + (y as usize % size) * size] * spread;
+            let (r, g, b) = quantizer.quantize(
+                c.get_red() as f32 + offset,
+                c.get_green() as f32 + offset,
+                c.get_blue() as f32 + offset
+            );
+            canvas.set_pixel(x, y, Color::from_rgba(r, g, b, c.get_alpha()));
+        }
+    }
+}
+
+fn diffuse(canvas: &mut Canvas, quantizer: &Quantizer, kernel: &[(i32, i32, f32)]) {
+
+    let width = canvas.get_width();
+    let height = canvas.get_height();
+
+    let mut buffer: Vec<[f32; 3]> = Vec::with_capacity((width * height) as usize);
+    for y in 0..height as u32 {
+        for x in 0..width as u32 {
+            let c = canvas.get_pixel(x, y);
+            buffer.push([c.get_red() as f32, c.get_green() as f32, c.get_blue() as f32]);
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let old = buffer[(x + y * width) as usize];
+            let (r, g, b) = quantizer.quantize(old[0], old[1], old[2]);
+            let error = [old[0] - r as f32, old[1] - g as f32, old[2] - b as f32];
+            for (dx, dy, weight) in kernel {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || nx >= width || ny >= height {
+                    continue;
+                }
+                let texel = &mut buffer[(nx + ny * width) as usize];
+                for k in 0..3 {
+                    texel[k] += error[k] * weight;
+                }
+            }
+            let alpha = canvas.get_pixel(x as u32, y as u32).get_alpha();
+            canvas.set_pixel(x as u32, y as u32, Color::from_rgba(r, g, b, alpha));
+        }
+    }
+
+}
+
+// void-and-cluster (Ulichney) threshold map, built once on first use
+fn blue_noise() -> &'static [f32] {
+    BLUE_NOISE.get_or_init(|| {
+
+        const N: usize = BLUE_NOISE_SIZE;
+        const SIGMA: f32 = 1.5;
+
+        // toroidal gaussian energy contribution of a point at offset (dx, dy)
+        let mut kernel = vec![0f32; N * N];
+        for dy in 0..N {
+            for dx in 0..N {
+                let wx = dx.min(N - dx) as f32;
+                let wy = dy.min(N - dy) as f32;
+                kernel[dx + dy * N] = (-(wx * wx + wy * wy) / (2f32 * SIGMA * SIGMA)).exp();
+            }
+        }
+
+        let splat = |energy: &mut [f32], p: usize, sign: f32| {
+            let (px, py) = (p % N, p / N);
+            for y in 0..N {
+                for x in 0..N {
+                    let dx = (x + N - px) % N;
+                    let dy = (y + N - py) % N;
+                    energy[x + y * N] += sign * kernel[dx + dy * N];
+                }
+            }
+        };
+        // tightest cluster among set cells, or largest void among empty ones
+        let extreme = |energy: &[f32], pattern: &[bool], set: bool| {
+            let mut best = 0;
+            let mut best_energy = if set { f32::NEG_INFINITY } else { f32::INFINITY };
+            for i in 0..N * N {
+                if pattern[i] != set {
+                    continue;
+                }
+                if (set && energy[i] > best_energy) || (!set && energy[i] < best_energy) {
+                    best = i;
+                    best_energy = energy[i];
+                }
+            }
+            best
+        };
+
+        // initial pattern: a deterministic ~10% random fill
+        let mut pattern = vec![false; N * N];
+        let mut energy = vec![0f32; N * N];
+        let mut state = 0x2545f491u32;
+        let mut ones = 0;
+        while ones < N * N / 10 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let p = state as usize % (N * N);
+            if !pattern[p] {
+                pattern[p] = true;
+                splat(&mut energy, p, 1f32);
+                ones += 1;
+            }
+        }
+
+        // relax it until moving the tightest cluster into the largest void is a no-op
+        loop {
+            let cluster = extreme(&energy, &pattern, true);
+            pattern[cluster] = false;
+            splat(&mut energy, cluster, -1f32);
+            let void = extreme(&energy, &pattern, false);
+            pattern[void] = true;
+            splat(&mut energy, void, 1f32);
+            if void == cluster {
+                break;
+            }
+        }
+
+        let mut rank = vec![0usize; N * N];
+
+        // ranks below the initial fill: peel off clusters
+        let mut peeled = pattern.clone();
+        let mut peeled_energy = energy.clone();
+        for r in (0..ones).rev() {
+            let cluster = extreme(&peeled_energy, &peeled, true);
+            peeled[cluster] = false;
+            splat(&mut peeled_energy, cluster, -1f32);
+            rank[cluster] = r;
+        }
+
+        // ranks above: keep filling the largest void
+        for r in ones..N * N {
+            let void = extreme(&energy, &pattern, false);
+            pattern[void] = true;
+            splat(&mut energy, void, 1f32);
+            rank[void] = r;
+        }
+
+        rank.iter().map(|r| (*r as f32 + 0.5f32) / (N * N) as f32 - 0.5f32).collect()
+
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn empty_palette_leaves_pixels_unchanged() {
+        let mut canvas = Canvas::new(4, 4);
+        canvas.set_pixel(1, 2, Color::from_rgb(10, 120, 230));
+        let before: Vec<Color> = (0..16).map(|i| canvas.get_pixel(i % 4, i / 4)).collect();
+        for method in [Dithering::Bayer4, Dithering::FloydSteinberg, Dithering::Atkinson] {
+            dither(&mut canvas, &Quantizer::Palette(Palette::new(vec![])), method);
+        }
+        let after: Vec<Color> = (0..16).map(|i| canvas.get_pixel(i % 4, i / 4)).collect();
+        assert_eq!(before, after);
+    }
+
+
+    fn gray(size: u32, level: u8) -> Canvas {
+        let mut canvas = Canvas::new(size, size);
+        canvas.clear_color(Color::from_rgba(level, level, level, 200));
+        canvas
+    }
+
+    fn pixels(canvas: &Canvas) -> Vec<Color> {
+        let (width, height) = (canvas.get_width() as u32, canvas.get_height() as u32);
+        (0..width * height).map(|i| canvas.get_pixel(i % width, i / width)).collect()
+    }
+
+    fn methods() -> Vec<Dithering> {
+        vec![
+            Dithering::None, Dithering::Bayer2, Dithering::Bayer4, Dithering::Bayer8, Dithering::BlueNoise,
+            Dithering::FloydSteinberg, Dithering::Atkinson, Dithering::Sierra,
+        ]
+    }
+
+    #[test]
+    fn bit_depth_levels() {
+        assert_eq!(Quantizer::bit_depth(1).quantize(127f32, 128f32, 300f32), (0, 255, 255));
+        assert_eq!(Quantizer::bit_depth(8).quantize(17f32, 18f32, 19f32), (17, 18, 19));
+        let rgb565 = Quantizer::BitDepth { red: 5, green: 6, blue: 5 };
+        assert_eq!(rgb565.quantize(100f32, 100f32, 100f32), (99, 101, 99));
+    }
+
+    #[test]
+    fn threshold_maps_are_permutations() {
+        for (size, thresholds) in [(2, bayer(2)), (4, bayer(4)), (8, bayer(8)), (BLUE_NOISE_SIZE, blue_noise().to_vec())] {
+            let cells = size * size;
+            let mut ranks: Vec<usize> = thresholds.iter().map(|t| ((t + 0.5f32) * cells as f32) as usize).collect();
+            ranks.sort();
+            assert_eq!(ranks, (0..cells).collect::<Vec<usize>>());
+        }
+        assert_eq!(bayer(2), vec![-0.375, 0.125, 0.375, -0.125]);
+    }
+
+    #[test]
+    fn output_stays_in_the_palette() {
+        let palette = Palette::game_boy();
+        for method in methods() {
+            let mut canvas = Canvas::new(16, 16);
+            for i in 0..256u32 {
+                canvas.set_pixel(i % 16, i / 16, Color::from_rgba(i as u8, (255 - i) as u8, 90, 17));
+            }
+            dither(&mut canvas, &Quantizer::Palette(Palette::game_boy()), method);
+            for c in pixels(&canvas) {
+                assert!(palette.colors().contains(&Color::from_rgb(c.get_red(), c.get_green(), c.get_blue())));
+                assert_eq!(c.get_alpha(), 17);
+            }
+        }
+    }
+
+    #[test]
+    fn mid_gray_becomes_half_white() {
+        let quantizer = Quantizer::Palette(Palette::monochrome());
+        for method in methods() {
+            let plain = matches!(method, Dithering::None);
+            let mut canvas = gray(16, 128);
+            dither(&mut canvas, &quantizer, method);
+            let white = pixels(&canvas).iter().filter(|c| c.get_red() == 255).count();
+            if plain {
+                assert_eq!(white, 256);
+            } else {
+                assert!((120..=136).contains(&white), "{} white pixels", white);
+            }
+        }
+        // ordered patterns are exact
+        let mut canvas = gray(8, 128);
+        dither(&mut canvas, &quantizer, Dithering::Bayer8);
+        assert_eq!(pixels(&canvas).iter().filter(|c| c.get_red() == 255).count(), 32);
+    }
+
+}
@@ -0,0 +1,499 @@
+use super::canvas::{Canvas, Color};
+
+// row major weights, the anchor is the center cell
+pub struct Kernel {
+    width: u32,
+    height: u32,
+    weights: Vec<f32>,
+    bias: f32,
+}
+
+pub enum Filter {
+    Convolution(Kernel),
+    GaussianBlur { sigma: f32 },
+    BoxBlur { radius: u32 },
+    Sharpen { amount: f32 },
+    UnsharpMask { sigma: f32, amount: f32, threshold: f32 },
+    Sobel,
+    Laplacian,
+    Emboss,
+    Median { radius: u32 },
+    Erode { radius: u32 },
+    Dilate { radius: u32 },
+}
+
+// filters applied in insertion order, see App::get_post_process_mut
+pub struct FilterChain {
+    filters: Vec<Filter>,
+    enabled: bool,
+}
+
+// RGBA in [0, 255], kept as floats between passes
+struct Image {
+    pixels: Vec<[f32; 4]>,
+    width: i32,
+    height: i32,
+}
+
+impl Kernel {
+
+    pub fn new(width: u32, height: u32, weights: Vec<f32>) -> Self {
+        assert!(width % 2 == 1 && height % 2 == 1, "Kernel dimensions must be odd.");
+        assert_eq!(weights.len(), (width * height) as usize, "Kernel weight count does not match its dimension.");
+        Kernel { width, height, weights, bias: 0.0 }
+    }
+
+    // weights are divided by their sum (when non-zero)
+    pub fn normalized(width: u32, height: u32, weights: Vec<f32>) -> Self {
+        let sum: f32 = weights.iter().sum();
+        let weights = if sum.abs() > f32::EPSILON { weights.iter().map(|w| w / sum).collect() } else { weights };
+        Self::new(width, height, weights)
+    }
+
+    pub fn set_bias(&mut self, bias: f32) {
+        self.bias = bias;
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+}
+
+impl Filter {
+
+    pub fn apply(&self, canvas: &mut Canvas) {
+        if canvas.get_width() == 0 || canvas.get_height() == 0 {
+            return;
+        }
+        let mut image = Image::load(canvas);
+        self.process(&mut image);
+        image.store(canvas);
+    }
+
+    fn process(&self, image: &mut Image) {
+        match self {
+            Filter::Convolution(kernel) => *image = image.convolve(kernel),
+            Filter::GaussianBlur { sigma } => *image = image.gaussian_blur(*sigma),
+            Filter::BoxBlur { radius } => {
+                let weights = vec![1f32; (2 * radius + 1) as usize];
+                *image = image.separable(&weights);
+            }
+            Filter::Sharpen { amount } => {
+                let a = *amount;
+                let kernel = Kernel::new(3, 3, vec![
+                    0.0, -a, 0.0,
+                    -a, 1.0 + 4.0 * a, -a,
+                    0.0, -a, 0.0,
+                ]);
+                *image = image.convolve(&kernel);
+            }
+            Filter::UnsharpMask { sigma, amount, threshold } => {
+                let blurred = image.gaussian_blur(*sigma);
+                for (p, b) in image.pixels.iter_mut().zip(blurred.pixels.iter()) {
+                    for k in 0..3 {
+                        let detail = p[k] - b[k];
+                        if detail.abs() >= *threshold {
+                            p[k] = (p[k] + amount * detail).clamp(0f32, 255f32);
+                        }
+                    }
+                }
+            }
+            Filter::Sobel => {
+                let gx = Kernel::new(3, 3, vec![-1.0, 0.0, 1.0, -2.0, 0.0, 2.0, -1.0, 0.0, 1.0]);
+                let gy = Kernel::new(3, 3, vec![-1.0, -2.0, -1.0, 0.0, 0.0, 0.0, 1.0, 2.0, 1.0]);
+                let luma = image.luminance();
+                let (dx, dy) = (luma.correlate(&gx), luma.correlate(&gy));
+                for (i, p) in image.pixels.iter_mut().enumerate() {
+                    let magnitude = (dx.pixels[i][0].powi(2) + dy.pixels[i][0].powi(2)).sqrt().min(255f32);
+                    *p = [magnitude, magnitude, magnitude, p[3]];
+                }
+            }
+            Filter::Laplacian => {
+                let kernel = Kernel::new(3, 3, vec![0.0, 1.0, 0.0, 1.0, -4.0, 1.0, 0.0, 1.0, 0.0]);
+                let edges = image.luminance().correlate(&kernel);
+                for (i, p) in image.pixels.iter_mut().enumerate() {
+                    let magnitude = edges.pixels[i][0].abs().min(255f32);
+                    *p = [magnitude, magnitude, magnitude, p[3]];
+                }
+            }
+            Filter::Emboss => {
+                let kernel = Kernel::new(3, 3, vec![-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0]);
+                *image = image.convolve(&kernel);
+            }
+            Filter::Median { radius } => *image = image.rank(*radius, |window| {
+                window.sort_by(f32::total_cmp);
+                window[window.len() / 2]
+            }),
+            Filter::Erode { radius } => *image = image.rank(*radius, |window| {
+                window.iter().copied().fold(f32::INFINITY, f32::min)
+            }),
+            Filter::Dilate { radius } => *image = image.rank(*radius, |window| {
+                window.iter().copied().fold(f32::NEG_INFINITY, f32::max)
+            }),
+        }
+    }
+
+}
+
+impl FilterChain {
+
+    pub fn new() -> Self {
+        FilterChain { filters: Vec::new(), enabled: true }
+    }
+
+    pub fn push(&mut self, filter: Filter) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    pub fn clear(&mut self) {
+        self.filters.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    pub fn filters_mut(&mut self) -> &mut Vec<Filter> {
+        &mut self.filters
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn apply(&self, canvas: &mut Canvas) {
+        if !self.enabled || self.filters.is_empty() || canvas.get_width() == 0 || canvas.get_height() == 0 {
+            return;
+        }
+        let mut image = Image::load(canvas);
+        for filter in &self.filters {
+            filter.process(&mut image);
+        }
+        image.store(canvas);
+    }
+
+}
+
+impl Default for FilterChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Image {
+
+    fn load(canvas: &Canvas) -> Self {
+        let (width, height) = (canvas.get_width(), canvas.get_height());
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as u32 {
+            for x in 0..width as u32 {
+                let c = canvas.get_pixel(x, y);
+                pixels.push([c.get_red() as f32, c.get_green() as f32, c.get_blue() as f32, c.get_alpha() as f32]);
+            }
+        }
+        Image { pixels, width, height }
+    }
+
+    fn store(&self, canvas: &mut Canvas) {
+        let channel = |v: f32| (v.clamp(0f32, 255f32) + 0.5f32) as u8;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let p = self.pixels[(x + y * self.width) as usize];
+                canvas.set_pixel(x as u32, y as u32, Color::from_rgba(channel(p[0]), channel(p[1]), channel(p[2]), channel(p[3])));
+            }
+        }
+    }
+
+    // clamp-to-edge addressing
+    fn at(&self, x: i32, y: i32) -> &[f32; 4] {
+        let x = x.clamp(0, self.width - 1);
+        let y = y.clamp(0, self.height - 1);
+        &self.pixels[(x + y * self.width) as usize]
+    }
+
+    fn luminance(&self) -> Image {
+        let pixels = self.pixels.iter()
+            .map(|p| {
+                let l = 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2];
+                [l, l, l, p[3]]
+            })
+            .collect();
+        Image { pixels, width: self.width, height: self.height }
+    }
+
+    // raw kernel response without clamping, alpha untouched
+    fn correlate(&self, kernel: &Kernel) -> Image {
+        let (rx, ry) = ((kernel.width / 2) as i32, (kernel.height / 2) as i32);
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut sum = [kernel.bias; 3];
+                for ky in -ry..=ry {
+                    for kx in -rx..=rx {
+                        let w = kernel.weights[((kx + rx) + (ky + ry) * kernel.width as i32) as usize];
+                        let p = self.at(x + kx, y + ky);
+                        for k in 0..3 {
+                            sum[k] += w * p[k];
+                        }
+                    }
+                }
+                pixels.push([sum[0], sum[1], sum[2], self.at(x, y)[3]]);
+            }
+        }
+        Image { pixels, width: self.width, height: self.height }
+    }
+
+    // the kernel rotated by 180 degrees and correlated, clamped to [0, 255]
+    fn convolve(&self, kernel: &Kernel) -> Image {
+        let flipped = Kernel {
+            width: kernel.width,
+            height: kernel.height,
+            weights: kernel.weights.iter().rev().copied().collect(),
+            bias: kernel.bias,
+        };
+        let mut image = self.correlate(&flipped);
+        for p in image.pixels.iter_mut() {
+            for v in p.iter_mut().take(3) {
+                *v = v.clamp(0f32, 255f32);
+            }
+        }
+        image
+    }
+
+    fn gaussian_blur(&self, sigma: f32) -> Image {
+        if sigma <= 0f32 {
+            return Image { pixels: self.pixels.clone(), width: self.width, height: self.height };
+        }
+        let radius = (3f32 * sigma).ceil() as i32;
+        let weights: Vec<f32> = (-radius..=radius)
+            .map(|i| (-(i * i) as f32 / (2f32 * sigma * sigma)).exp())
+            .collect();
+        self.separable(&weights)
+    }
+
+    // horizontal then vertical pass of a normalized 1D kernel, alpha included
+    fn separable(&self, weights: &[f32]) -> Image {
+        let sum: f32 = weights.iter().sum();
+        let weights: Vec<f32> = weights.iter().map(|w| w / sum).collect();
+        let radius = (weights.len() / 2) as i32;
+        let pass = |source: &Image, dx: i32, dy: i32| {
+            let mut pixels = Vec::with_capacity(source.pixels.len());
+            for y in 0..source.height {
+                for x in 0..source.width {
+                    let mut acc = [0f32; 4];
+                    for (i, w) in weights.iter().enumerate() {
+                        let offset = i as i32 - radius;
+                        let p = source.at(x + offset * dx, y + offset * dy);
+                        for k in 0..4 {
+                            acc[k] += w * p[k];
+                        }
+                    }
+                    pixels.push(acc);
+                }
+            }
+            Image { pixels, width: source.width, height: source.height }
+        };
+        pass(&pass(self, 1, 0), 0, 1)
+    }
+
+    // per channel order statistic over a square window
+    fn rank<F>(&self, radius: u32, select: F) -> Image
+    where F: Fn(&mut [f32]) -> f32
+    {
+        let r = radius as i32;
+        let mut window: Vec<f32> = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut out = [0f32; 4];
+                for (k, v) in out.iter_mut().enumerate() {
+                    window.clear();
+                    for wy in -r..=r {
+                        for wx in -r..=r {
+                            window.push(self.at(x + wx, y + wy)[k]);
+                        }
+                    }
+                    *v = select(&mut window);
+                }
+                pixels.push(out);
+            }
+        }
+        Image { pixels, width: self.width, height: self.height }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // 5x5, a bright pixel in the middle of a dark gray field
+    fn dot() -> Canvas {
+        let mut canvas = Canvas::new(5, 5);
+        canvas.clear_color(Color::from_rgba(40, 40, 40, 255));
+        canvas.set_pixel(2, 2, Color::from_rgba(240, 200, 160, 255));
+        canvas
+    }
+
+    fn pixels(canvas: &Canvas) -> Vec<Color> {
+        (0..25).map(|i| canvas.get_pixel(i % 5, i / 5)).collect()
+    }
+
+    #[test]
+    fn identity_kernel_and_flat_images() {
+        let canvas = dot();
+        let mut filtered = dot();
+        Filter::Convolution(Kernel::new(3, 3, vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0])).apply(&mut filtered);
+        assert_eq!(pixels(&filtered), pixels(&canvas));
+        Filter::Sharpen { amount: 0f32 }.apply(&mut filtered);
+        assert_eq!(pixels(&filtered), pixels(&canvas));
+
+        // every normalized smoothing filter leaves a flat image alone
+        for filter in [
+            Filter::BoxBlur { radius: 2 },
+            Filter::GaussianBlur { sigma: 1.5 },
+            Filter::Sharpen { amount: 1f32 },
+            Filter::UnsharpMask { sigma: 1f32, amount: 2f32, threshold: 0f32 },
+            Filter::Median { radius: 1 },
+            Filter::Erode { radius: 1 },
+            Filter::Dilate { radius: 1 },
+        ] {
+            let mut flat = Canvas::new(5, 5);
+            flat.clear_color(Color::from_rgba(90, 120, 150, 200));
+            filter.apply(&mut flat);
+            assert!(pixels(&flat).iter().all(|c| *c == Color::from_rgba(90, 120, 150, 200)));
+        }
+    }
+
+    #[test]
+    fn box_blur_spreads_evenly() {
+        let mut canvas = dot();
+        Filter::BoxBlur { radius: 1 }.apply(&mut canvas);
+        // (8 * 40 + 240) / 9
+        for (x, y) in [(1, 1), (2, 1), (3, 3), (2, 2)] {
+            assert_eq!(canvas.get_pixel(x, y).get_red(), 62);
+        }
+        assert_eq!(canvas.get_pixel(0, 0).get_red(), 40);
+    }
+
+    #[test]
+    fn convolution_flips_the_kernel() {
+        // a single weight on the right neighbor shifts the image to the right
+        let mut canvas = dot();
+        Filter::Convolution(Kernel::new(3, 3, vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0])).apply(&mut canvas);
+        assert_eq!(canvas.get_pixel(3, 2).get_red(), 240);
+        assert_eq!(canvas.get_pixel(2, 2).get_red(), 40);
+        // and one below shifts it down
+        let mut canvas = dot();
+        Filter::Convolution(Kernel::new(3, 3, vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0])).apply(&mut canvas);
+        assert_eq!(canvas.get_pixel(2, 3).get_red(), 240);
+    }
+
+    #[test]
+    fn emboss_brightens_edges_facing_the_bottom_right() {
+        let mut canvas = Canvas::new(5, 5);
+        canvas.clear_color(Color::black());
+        for y in 0..5 {
+            for x in 0..3 {
+                canvas.set_pixel(x, y, Color::white());
+            }
+        }
+        let mut mirrored = Canvas::new(5, 5);
+        mirrored.clear_color(Color::white());
+        for y in 0..5 {
+            for x in 0..2 {
+                mirrored.set_pixel(x, y, Color::black());
+            }
+        }
+        Filter::Emboss.apply(&mut canvas);
+        Filter::Emboss.apply(&mut mirrored);
+        // the weights sum to one, flat areas keep their value
+        assert_eq!(canvas.get_pixel(0, 2).get_red(), 255);
+        assert_eq!(canvas.get_pixel(4, 2).get_red(), 0);
+        assert_eq!(canvas.get_pixel(2, 2).get_red(), 255);
+        assert_eq!(canvas.get_pixel(3, 2).get_red(), 255);
+        assert_eq!(mirrored.get_pixel(1, 2).get_red(), 0);
+        assert_eq!(mirrored.get_pixel(2, 2).get_red(), 0);
+    }
+
+    #[test]
+    fn kernel_normalization() {
+        let kernel = Kernel::normalized(3, 1, vec![1f32, 2f32, 1f32]);
+        assert_eq!(kernel.weights(), &[0.25, 0.5, 0.25]);
+        let kernel = Kernel::normalized(3, 1, vec![-1f32, 0f32, 1f32]);
+        assert_eq!(kernel.weights(), &[-1f32, 0f32, 1f32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "odd")]
+    fn even_kernels_are_rejected() {
+        Kernel::new(2, 3, vec![0f32; 6]);
+    }
+
+    #[test]
+    fn edges_and_rank_filters() {
+        let mut canvas = dot();
+        Filter::Median { radius: 1 }.apply(&mut canvas);
+        assert!(pixels(&canvas).iter().all(|c| c.get_red() == 40));
+
+        let mut canvas = dot();
+        Filter::Dilate { radius: 1 }.apply(&mut canvas);
+        assert_eq!(canvas.get_pixel(1, 3).get_green(), 200);
+        assert_eq!(canvas.get_pixel(0, 0).get_green(), 40);
+        Filter::Erode { radius: 1 }.apply(&mut canvas);
+        assert_eq!(pixels(&canvas), pixels(&dot()));
+
+        let mut canvas = dot();
+        Filter::Laplacian.apply(&mut canvas);
+        assert_eq!(canvas.get_pixel(0, 0).get_red(), 0);
+        assert_eq!(canvas.get_pixel(2, 2).get_red(), 255);
+        let mut canvas = dot();
+        Filter::Sobel.apply(&mut canvas);
+        assert_eq!(canvas.get_pixel(0, 0).get_red(), 0);
+        assert!(canvas.get_pixel(1, 2).get_red() > 100);
+        assert_eq!(canvas.get_pixel(2, 2).get_alpha(), 255);
+    }
+
+    #[test]
+    fn chains() {
+        let mut chain = FilterChain::new();
+        chain.push(Filter::Dilate { radius: 1 }).push(Filter::Erode { radius: 1 });
+        let mut canvas = dot();
+        chain.apply(&mut canvas);
+        assert_eq!(pixels(&canvas), pixels(&dot()));
+        chain.filters_mut().pop();
+        chain.toggle();
+        chain.apply(&mut canvas);
+        assert_eq!(pixels(&canvas), pixels(&dot()));
+        chain.toggle();
+        chain.apply(&mut canvas);
+        assert_eq!(canvas.get_pixel(1, 1).get_red(), 240);
+    }
+
+    #[test]
+    fn empty_canvases_are_left_alone() {
+        let mut chain = FilterChain::new();
+        chain.push(Filter::BoxBlur { radius: 1 }).push(Filter::Sobel);
+        for (width, height) in [(0, 0), (0, 4), (4, 0)] {
+            let mut canvas = Canvas::new(width, height);
+            Filter::GaussianBlur { sigma: 1f32 }.apply(&mut canvas);
+            chain.apply(&mut canvas);
+        }
+    }
+
+}
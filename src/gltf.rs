@@ -0,0 +1,1165 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf}
+};
+
+use super::{
+    canvas::Color,
+    mathsf::{Mat4, Quat, Vec2, Vec3, Vec4},
+    mesh::{Indices, Mesh, Topology}
+};
+
+// glTF 2.0, as .gltf (JSON with external or base64 data URI buffers) or .glb (binary
+// container). Loads meshes, metallic-roughness materials, texture and image references
+// (images aren't decoded), cameras and the node hierarchy. Animations, skins, morph
+// targets, sparse accessors and accessors without a bufferView are not supported;
+// files requiring an extension fail.
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io(String, std::io::Error),
+    // byte offset into the JSON text
+    Json { offset: usize, message: String },
+    Glb(String),
+    // `path` locates the property, like meshes[0].primitives[1].indices
+    Invalid { path: String, message: String },
+    Unsupported(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Gltf {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<PbrMaterial>,
+    pub textures: Vec<Texture>,
+    pub samplers: Vec<Sampler>,
+    pub images: Vec<Image>,
+    pub cameras: Vec<Camera>,
+    pub nodes: Vec<Node>,
+    pub scenes: Vec<Scene>,
+    // the scene to show first, None leaves it to the application
+    pub scene: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+}
+
+// triangles without normals get flat normals, unwelded as the specification asks
+#[derive(Clone, Debug)]
+pub struct Primitive {
+    pub mesh: Mesh,
+    // None is the default material, PbrMaterial::default()
+    pub material: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AlphaMode {
+    Opaque,
+    // alpha below the cutoff is discarded
+    Mask(f32),
+    Blend,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TextureRef {
+    pub texture: usize,
+    // which TEXCOORD_n, only TEXCOORD_0 is loaded
+    pub tex_coord: usize,
+    // normal map scale or occlusion strength, 1 otherwise
+    pub scale: f32,
+}
+
+// factors are linear, textures multiply them
+#[derive(Clone, PartialEq, Debug)]
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    pub base_color: Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+    // roughness in green, metallic in blue
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub occlusion_texture: Option<TextureRef>,
+    pub emissive: Vec3,
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Texture {
+    pub name: Option<String>,
+    pub source: Option<usize>,
+    // None repeats with filtering left to the application
+    pub sampler: Option<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Wrap {
+    Repeat,
+    ClampToEdge,
+    MirroredRepeat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sampler {
+    // OpenGL enums: 9728 NEAREST, 9729 LINEAR, and the mipmap variants for minification
+    pub mag_filter: Option<u32>,
+    pub min_filter: Option<u32>,
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ImageSource {
+    // external file, resolved against the glTF file's directory
+    File(PathBuf),
+    // encoded bytes from a buffer view or data URI
+    Embedded(Vec<u8>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Image {
+    pub name: Option<String>,
+    pub mime_type: Option<String>,
+    pub source: ImageSource,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    // fov_y in radians, None for the aspect ratio of the viewport and an infinite far plane
+    Perspective { fov_y: f32, aspect: Option<f32>, near: f32, far: Option<f32> },
+    // half extents of the view volume
+    Orthographic { x_mag: f32, y_mag: f32, near: f32, far: f32 },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Camera {
+    pub name: Option<String>,
+    pub projection: Projection,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transform {
+    Matrix(Mat4),
+    // applied scale first, then rotation, then translation
+    Decomposed { translation: Vec3, rotation: Quat, scale: Vec3 },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Node {
+    pub name: Option<String>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    // cameras look down their node's -z
+    pub camera: Option<usize>,
+    pub transform: Transform,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Scene {
+    pub name: Option<String>,
+    pub nodes: Vec<usize>,
+}
+
+impl Display for GltfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io(path, e) => write!(f, "cannot read '{}': {}", path, e),
+            GltfError::Json { offset, message } => write!(f, "invalid JSON at byte {}: {}", offset, message),
+            GltfError::Glb(message) => write!(f, "invalid GLB: {}", message),
+            GltfError::Invalid { path, message } => write!(f, "{}: {}", path, message),
+            GltfError::Unsupported(feature) => write!(f, "unsupported: {}", feature),
+        }
+    }
+}
+
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GltfError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        PbrMaterial {
+            name: None,
+            base_color: Vec4::new(1f32, 1f32, 1f32, 1f32),
+            base_color_texture: None,
+            metallic: 1f32,
+            roughness: 1f32,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            occlusion_texture: None,
+            emissive: Vec3::zero(),
+            emissive_texture: None,
+            alpha_mode: AlphaMode::Opaque,
+            double_sided: false,
+        }
+    }
+}
+
+impl PbrMaterial {
+    pub fn base_color(&self) -> Color {
+        Color::from_linear(&self.base_color)
+    }
+}
+
+impl Camera {
+
+    // `aspect` (width / height) is used when the camera doesn't fix one
+    pub fn projection_matrix(&self, aspect: f32) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fov_y, aspect: fixed, near, far: Some(far) } => {
+                Mat4::perspective(fov_y, fixed.unwrap_or(aspect), near, far)
+            }
+            Projection::Perspective { fov_y, aspect: fixed, near, far: None } => {
+                // the limit of the finite one as far goes to infinity
+                let mut m = Mat4::perspective(fov_y, fixed.unwrap_or(aspect), near, 1f32);
+                m.set(2, 2, -1f32);
+                m.set(2, 3, -2f32 * near);
+                m
+            }
+            Projection::Orthographic { x_mag, y_mag, near, far } => {
+                Mat4::orthographic(-x_mag, x_mag, -y_mag, y_mag, near, far)
+            }
+        }
+    }
+
+}
+
+impl Transform {
+
+    pub fn matrix(&self) -> Mat4 {
+        match self {
+            Transform::Matrix(m) => *m,
+            Transform::Decomposed { translation, rotation, scale } => {
+                Mat4::translation(translation) * rotation.to_mat4() * Mat4::scale(scale)
+            }
+        }
+    }
+
+}
+
+impl Gltf {
+
+    // .gltf or .glb, told apart by the GLB magic
+    pub fn load(path: impl AsRef<Path>) -> Result<Gltf, GltfError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| GltfError::Io(path.display().to_string(), e))?;
+        Self::from_slice(&bytes, path.parent().unwrap_or(Path::new("")))
+    }
+
+    // `directory` resolves external buffers and images
+    pub fn from_slice(bytes: &[u8], directory: &Path) -> Result<Gltf, GltfError> {
+        let (json, binary) = if bytes.starts_with(b"glTF") { split_glb(bytes)? } else { (bytes, None) };
+        let text = std::str::from_utf8(json)
+            .map_err(|e| GltfError::Json { offset: e.valid_up_to(), message: "invalid UTF-8".to_string() })?;
+        let root = parse_json(text.strip_prefix('\u{feff}').unwrap_or(text))?;
+        load_document(&Object::new(&root, String::new())?, binary, directory)
+    }
+
+    pub fn world_transform(&self, node: usize) -> Mat4 {
+        let mut m = self.nodes[node].transform.matrix();
+        let mut parent = self.nodes[node].parent;
+        while let Some(p) = parent {
+            m = self.nodes[p].transform.matrix() * m;
+            parent = self.nodes[p].parent;
+        }
+        m
+    }
+
+    // every node of a scene with its world transform, parents before children
+    pub fn traverse(&self, scene: usize) -> Vec<(usize, Mat4)> {
+        let mut visited = Vec::new();
+        let mut stack: Vec<(usize, Mat4)> = self.scenes[scene].nodes.iter().rev()
+            .map(|node| (*node, Mat4::identity()))
+            .collect();
+        while let Some((node, parent)) = stack.pop() {
+            let world = parent * self.nodes[node].transform.matrix();
+            visited.push((node, world));
+            stack.extend(self.nodes[node].children.iter().rev().map(|child| (*child, world)));
+        }
+        visited
+    }
+
+}
+
+const GLB_JSON: u32 = 0x4E4F534A;
+const GLB_BIN: u32 = 0x004E4942;
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+// the JSON chunk and the optional BIN chunk
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    if bytes.len() < 12 {
+        return Err(GltfError::Glb("truncated header".to_string()));
+    }
+    let version = read_u32(bytes, 4);
+    if version != 2 {
+        return Err(GltfError::Unsupported(format!("GLB version {}", version)));
+    }
+    let length = read_u32(bytes, 8) as usize;
+    if length > bytes.len() {
+        return Err(GltfError::Glb(format!("header length {} but the file has {} bytes", length, bytes.len())));
+    }
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let (size, kind) = (read_u32(bytes, offset) as usize, read_u32(bytes, offset + 4));
+        let start = offset + 8;
+        if size > length - start {
+            return Err(GltfError::Glb(format!("chunk at byte {} runs past the end", offset)));
+        }
+        chunks.push((kind, &bytes[start..start + size]));
+        offset = start + size;
+    }
+    match chunks.as_slice() {
+        [(GLB_JSON, json), rest @ ..] => {
+            // unknown chunk types are skipped
+            let binary = rest.first().filter(|(kind, _)| *kind == GLB_BIN).map(|(_, data)| *data);
+            Ok((json, binary))
+        }
+        _ => Err(GltfError::Glb("the first chunk must be JSON".to_string())),
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // in file order, glTF keys are unique
+    Object(Vec<(String, Json)>),
+}
+
+// nesting deeper than this is certainly not glTF, and would overflow the stack
+const JSON_MAX_DEPTH: usize = 64;
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+fn parse_json(text: &str) -> Result<Json, GltfError> {
+    let mut parser = JsonParser { bytes: text.as_bytes(), position: 0, depth: 0 };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.position < parser.bytes.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+impl JsonParser<'_> {
+
+    fn error(&self, message: &str) -> GltfError {
+        GltfError::Json { offset: self.position, message: message.to_string() }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), GltfError> {
+        self.whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Json, GltfError> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, GltfError>) -> Result<Json, GltfError> {
+        if self.depth == JSON_MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, GltfError> {
+        if !self.bytes[self.position..].starts_with(word.as_bytes()) {
+            return Err(self.error("unexpected character"));
+        }
+        self.position += word.len();
+        Ok(value)
+    }
+
+    fn object(&mut self) -> Result<Json, GltfError> {
+        self.position += 1;
+        let mut members = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, GltfError> {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, GltfError> {
+        let digits = self.bytes.get(self.position..self.position + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+
+    fn string(&mut self) -> Result<String, GltfError> {
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(byte) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            // a high surrogate must be followed by an escaped low one
+                            if (0xD800..0xDC00).contains(&code) && self.bytes[self.position..].starts_with(b"\\u") {
+                                self.position += 2;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("invalid surrogate pair"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0..=0x1F => return Err(self.error("control character in string")),
+                _ => bytes.push(byte),
+            }
+        }
+        // the source is a str and escapes are whole characters
+        Ok(String::from_utf8(bytes).expect("JSON strings are valid UTF-8."))
+    }
+
+    fn number(&mut self) -> Result<Json, GltfError> {
+        let start = self.position;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.position]).ok()
+            .and_then(|text| text.parse::<f64>().ok())
+            .map(Json::Number)
+            .ok_or_else(|| GltfError::Json { offset: start, message: "invalid number".to_string() })
+    }
+
+}
+
+// a JSON object and where it is in the document, for error messages
+struct Object<'a> {
+    members: &'a [(String, Json)],
+    path: String,
+}
+
+impl<'a> Object<'a> {
+
+    fn new(json: &'a Json, path: String) -> Result<Self, GltfError> {
+        match json {
+            Json::Object(members) => Ok(Object { members, path }),
+            _ => Err(GltfError::Invalid { path, message: "expected an object".to_string() }),
+        }
+    }
+
+    fn path(&self, key: &str) -> String {
+        if self.path.is_empty() { key.to_string() } else { format!("{}.{}", self.path, key) }
+    }
+
+    fn invalid(&self, key: &str, message: impl Into<String>) -> GltfError {
+        GltfError::Invalid { path: self.path(key), message: message.into() }
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Json> {
+        self.members.iter().find(|(k, _)| k == key).map(|(_, value)| value)
+    }
+
+    fn required<T>(&self, key: &str, value: Option<T>) -> Result<T, GltfError> {
+        value.ok_or_else(|| self.invalid(key, "missing"))
+    }
+
+    fn number(&self, key: &str) -> Result<Option<f32>, GltfError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Json::Number(n)) if n.is_finite() => Ok(Some(*n as f32)),
+            Some(_) => Err(self.invalid(key, "expected a number")),
+        }
+    }
+
+    fn unsigned(&self, key: &str) -> Result<Option<usize>, GltfError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Json::Number(n)) if *n >= 0f64 && n.fract() == 0f64 && *n <= u32::MAX as f64 => Ok(Some(*n as usize)),
+            Some(_) => Err(self.invalid(key, "expected a non-negative integer")),
+        }
+    }
+
+    // an index into a top-level array of `count` items
+    fn reference(&self, key: &str, count: usize) -> Result<Option<usize>, GltfError> {
+        match self.unsigned(key)? {
+            Some(index) if index >= count => Err(self.invalid(key, format!("index {} out of range", index))),
+            index => Ok(index),
+        }
+    }
+
+    fn string(&self, key: &str) -> Result<Option<String>, GltfError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Json::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(self.invalid(key, "expected a string")),
+        }
+    }
+
+    fn boolean(&self, key: &str) -> Result<Option<bool>, GltfError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Json::Bool(b)) => Ok(Some(*b)),
+            Some(_) => Err(self.invalid(key, "expected a boolean")),
+        }
+    }
+
+    fn array(&self, key: &str) -> Result<&'a [Json], GltfError> {
+        match self.get(key) {
+            None => Ok(&[]),
+            Some(Json::Array(values)) => Ok(values),
+            Some(_) => Err(self.invalid(key, "expected an array")),
+        }
+    }
+
+    fn floats<const N: usize>(&self, key: &str) -> Result<Option<[f32; N]>, GltfError> {
+        let values = self.array(key)?;
+        if self.get(key).is_none() {
+            return Ok(None);
+        }
+        let mut floats = [0f32; N];
+        if values.len() != N {
+            return Err(self.invalid(key, format!("expected {} numbers", N)));
+        }
+        for (f, value) in floats.iter_mut().zip(values) {
+            match value {
+                Json::Number(n) if n.is_finite() => *f = *n as f32,
+                _ => return Err(self.invalid(key, format!("expected {} numbers", N))),
+            }
+        }
+        Ok(Some(floats))
+    }
+
+    fn references(&self, key: &str, count: usize) -> Result<Vec<usize>, GltfError> {
+        self.array(key)?.iter().map(|value| match value {
+            Json::Number(n) if *n >= 0f64 && n.fract() == 0f64 && (*n as usize) < count => Ok(*n as usize),
+            _ => Err(self.invalid(key, "expected indices in range")),
+        }).collect()
+    }
+
+    fn object(&self, key: &str) -> Result<Option<Object<'a>>, GltfError> {
+        self.get(key).map(|json| Object::new(json, self.path(key))).transpose()
+    }
+
+    fn objects(&self, key: &str) -> Result<Vec<Object<'a>>, GltfError> {
+        self.array(key)?.iter().enumerate()
+            .map(|(i, json)| Object::new(json, format!("{}[{}]", self.path(key), i)))
+            .collect()
+    }
+
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// standard alphabet, padding optional
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes() {
+        let value = BASE64.iter().position(|b| *b == c)? as u32;
+        bits = (bits << 6) | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    // a single leftover character can't encode a byte
+    if text.len() % 4 == 1 {
+        return None;
+    }
+    Some(bytes)
+}
+
+// %XX escapes in relative URIs
+fn decode_percent(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+enum Uri {
+    Data(Vec<u8>),
+    File(PathBuf),
+}
+
+fn resolve_uri(object: &Object, directory: &Path) -> Result<Option<Uri>, GltfError> {
+    let Some(uri) = object.string("uri")? else {
+        return Ok(None);
+    };
+    if let Some(data) = uri.strip_prefix("data:") {
+        let Some((header, payload)) = data.split_once(',') else {
+            return Err(object.invalid("uri", "invalid data URI"));
+        };
+        if !header.ends_with(";base64") {
+            return Err(GltfError::Unsupported(format!("{}: data URI without base64", object.path("uri"))));
+        }
+        let bytes = decode_base64(payload).ok_or_else(|| object.invalid("uri", "invalid base64"))?;
+        return Ok(Some(Uri::Data(bytes)));
+    }
+    if uri.contains("://") {
+        return Err(GltfError::Unsupported(format!("{}: remote URI '{}'", object.path("uri"), uri)));
+    }
+    Ok(Some(Uri::File(directory.join(decode_percent(&uri)))))
+}
+
+struct View {
+    buffer: usize,
+    offset: usize,
+    length: usize,
+    stride: Option<usize>,
+}
+
+// where the elements of an accessor are
+struct Layout<'a> {
+    data: &'a [u8],
+    stride: usize,
+    component_type: usize,
+    components: usize,
+    count: usize,
+    normalized: bool,
+}
+
+// everything accessors need to read vertex data
+struct Document<'a> {
+    buffers: Vec<Vec<u8>>,
+    views: Vec<View>,
+    accessors: Vec<Object<'a>>,
+}
+
+impl Document<'_> {
+
+    // `types` lists the accepted component counts, SCALAR is 1, VEC2 to VEC4 are 2 to 4
+    fn layout(&self, index: usize, types: &[usize]) -> Result<Layout<'_>, GltfError> {
+        let accessor = &self.accessors[index];
+        if accessor.get("sparse").is_some() {
+            return Err(GltfError::Unsupported(format!("{}: sparse accessor", accessor.path)));
+        }
+        let component_type = accessor.required("componentType", accessor.unsigned("componentType")?)?;
+        let size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(accessor.invalid("componentType", format!("unknown component type {}", component_type))),
+        };
+        let kind = accessor.required("type", accessor.string("type")?)?;
+        let components = match kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            _ => 0,
+        };
+        if !types.contains(&components) {
+            return Err(accessor.invalid("type", format!("unexpected type {}", kind)));
+        }
+        let count = accessor.required("count", accessor.unsigned("count")?)?;
+        let normalized = accessor.boolean("normalized")?.unwrap_or(false);
+        let offset = accessor.unsigned("byteOffset")?.unwrap_or(0);
+        let element = size * components;
+        // without a view the elements are zeros, only useful with sparse substitution, and
+        // `count` alone would size the allocation
+        let Some(view) = accessor.reference("bufferView", self.views.len())? else {
+            return Err(GltfError::Unsupported(format!("{}: accessor without bufferView", accessor.path)));
+        };
+        let view = &self.views[view];
+        let stride = view.stride.unwrap_or(element);
+        let end = count.checked_sub(1)
+            .and_then(|last| last.checked_mul(stride))
+            .and_then(|last| last.checked_add(offset + element));
+        if count > 0 && end.is_none_or(|end| end > view.length) {
+            return Err(accessor.invalid("count", "the accessor runs past its buffer view"));
+        }
+        let data = &self.buffers[view.buffer][view.offset + offset.min(view.length)..view.offset + view.length];
+        Ok(Layout { data, stride, component_type, components, count, normalized })
+    }
+
+    // count * components values, normalized integers mapped to [0, 1] or [-1, 1]
+    fn floats(&self, index: usize, types: &[usize]) -> Result<(Vec<f32>, usize), GltfError> {
+        let layout = self.layout(index, types)?;
+        let data = layout.data;
+        let size = match layout.component_type { 5120 | 5121 => 1, 5122 | 5123 => 2, _ => 4 };
+        let mut values = Vec::with_capacity(layout.count * layout.components);
+        for i in 0..layout.count {
+            for c in 0..layout.components {
+                let at = i * layout.stride + c * size;
+                let (value, max) = match layout.component_type {
+                    5120 => (data[at] as i8 as f32, 127f32),
+                    5121 => (data[at] as f32, 255f32),
+                    5122 => (i16::from_le_bytes([data[at], data[at + 1]]) as f32, 32767f32),
+                    5123 => (u16::from_le_bytes([data[at], data[at + 1]]) as f32, 65535f32),
+                    5125 => (read_u32(data, at) as f32, 1f32),
+                    _ => (f32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]), 1f32),
+                };
+                values.push(if layout.normalized { (value / max).max(-1f32) } else { value });
+            }
+        }
+        Ok((values, layout.components))
+    }
+
+    fn indices(&self, index: usize) -> Result<Vec<u32>, GltfError> {
+        let layout = self.layout(index, &[1])?;
+        let accessor = &self.accessors[index];
+        if !matches!(layout.component_type, 5121 | 5123 | 5125) {
+            return Err(accessor.invalid("componentType", "indices must be unsigned integers"));
+        }
+        let data = layout.data;
+        Ok((0..layout.count).map(|i| {
+            let at = i * layout.stride;
+            match layout.component_type {
+                5121 => data[at] as u32,
+                5123 => u16::from_le_bytes([data[at], data[at + 1]]) as u32,
+                _ => read_u32(data, at),
+            }
+        }).collect())
+    }
+
+}
+
+fn load_document(root: &Object, binary: Option<&[u8]>, directory: &Path) -> Result<Gltf, GltfError> {
+    let asset = root.required("asset", root.object("asset")?)?;
+    let version = asset.required("version", asset.string("version")?)?;
+    if !version.starts_with("2.") {
+        return Err(GltfError::Unsupported(format!("glTF version {}", version)));
+    }
+    if let Some(extension) = root.array("extensionsRequired")?.first() {
+        let name = match extension {
+            Json::String(name) => name.as_str(),
+            _ => "?",
+        };
+        return Err(GltfError::Unsupported(format!("required extension {}", name)));
+    }
+
+    let mut buffers = Vec::new();
+    for (i, buffer) in root.objects("buffers")?.iter().enumerate() {
+        let length = buffer.required("byteLength", buffer.unsigned("byteLength")?)?;
+        let data = match resolve_uri(buffer, directory)? {
+            Some(Uri::Data(data)) => data,
+            Some(Uri::File(path)) => fs::read(&path).map_err(|e| GltfError::Io(path.display().to_string(), e))?,
+            // the BIN chunk of a GLB
+            None => match binary {
+                Some(data) if i == 0 => data.to_vec(),
+                _ => return Err(buffer.invalid("uri", "missing")),
+            },
+        };
+        if data.len() < length {
+            return Err(buffer.invalid("byteLength", format!("{} bytes but only {} available", length, data.len())));
+        }
+        buffers.push(data);
+    }
+
+    let mut views = Vec::new();
+    for view in root.objects("bufferViews")? {
+        let buffer = view.required("buffer", view.reference("buffer", buffers.len())?)?;
+        let offset = view.unsigned("byteOffset")?.unwrap_or(0);
+        let length = view.required("byteLength", view.unsigned("byteLength")?)?;
+        if offset + length > buffers[buffer].len() {
+            return Err(view.invalid("byteLength", "the view runs past its buffer"));
+        }
+        let stride = view.unsigned("byteStride")?;
+        if stride.is_some_and(|stride| !(4..=252).contains(&stride) || stride % 4 != 0) {
+            return Err(view.invalid("byteStride", "expected a multiple of 4 between 4 and 252"));
+        }
+        views.push(View { buffer, offset, length, stride });
+    }
+    let document = Document { buffers, views, accessors: root.objects("accessors")? };
+
+    let mut images = Vec::new();
+    for image in root.objects("images")? {
+        let source = match (resolve_uri(&image, directory)?, image.reference("bufferView", document.views.len())?) {
+            (Some(Uri::File(path)), _) => ImageSource::File(path),
+            (Some(Uri::Data(data)), _) => ImageSource::Embedded(data),
+            (None, Some(view)) => {
+                let view = &document.views[view];
+                ImageSource::Embedded(document.buffers[view.buffer][view.offset..view.offset + view.length].to_vec())
+            }
+            (None, None) => return Err(image.invalid("uri", "missing, and no bufferView either")),
+        };
+        images.push(Image { name: image.string("name")?, mime_type: image.string("mimeType")?, source });
+    }
+
+    let mut samplers = Vec::new();
+    for sampler in root.objects("samplers")? {
+        let wrap = |key: &str| match sampler.unsigned(key)? {
+            None | Some(10497) => Ok(Wrap::Repeat),
+            Some(33071) => Ok(Wrap::ClampToEdge),
+            Some(33648) => Ok(Wrap::MirroredRepeat),
+            Some(mode) => Err(sampler.invalid(key, format!("unknown wrap mode {}", mode))),
+        };
+        samplers.push(Sampler {
+            mag_filter: sampler.unsigned("magFilter")?.map(|f| f as u32),
+            min_filter: sampler.unsigned("minFilter")?.map(|f| f as u32),
+            wrap_s: wrap("wrapS")?,
+            wrap_t: wrap("wrapT")?,
+        });
+    }
+
+    let mut textures = Vec::new();
+    for texture in root.objects("textures")? {
+        textures.push(Texture {
+            name: texture.string("name")?,
+            source: texture.reference("source", images.len())?,
+            sampler: texture.reference("sampler", samplers.len())?,
+        });
+    }
+
+    let mut materials = Vec::new();
+    for material in root.objects("materials")? {
+        materials.push(load_material(&material, textures.len())?);
+    }
+
+    let mut meshes = Vec::new();
+    for mesh in root.objects("meshes")? {
+        let mut primitives = Vec::new();
+        for primitive in mesh.objects("primitives")? {
+            primitives.push(load_primitive(&document, &primitive, materials.len())?);
+        }
+        if primitives.is_empty() {
+            return Err(mesh.invalid("primitives", "missing"));
+        }
+        meshes.push(GltfMesh { name: mesh.string("name")?, primitives });
+    }
+
+    let mut cameras = Vec::new();
+    for camera in root.objects("cameras")? {
+        cameras.push(load_camera(&camera)?);
+    }
+
+    let node_objects = root.objects("nodes")?;
+    let mut nodes = Vec::new();
+    for node in &node_objects {
+        let transform = match node.floats::<16>("matrix")? {
+            Some(m) => Transform::Matrix(Mat4::from_cols([
+                [m[0], m[1], m[2], m[3]],
+                [m[4], m[5], m[6], m[7]],
+                [m[8], m[9], m[10], m[11]],
+                [m[12], m[13], m[14], m[15]],
+            ])),
+            None => {
+                let [x, y, z, w] = node.floats::<4>("rotation")?.unwrap_or([0f32, 0f32, 0f32, 1f32]);
+                Transform::Decomposed {
+                    translation: Vec3::from(node.floats::<3>("translation")?.unwrap_or([0f32; 3])),
+                    rotation: Quat::new(x, y, z, w),
+                    scale: Vec3::from(node.floats::<3>("scale")?.unwrap_or([1f32; 3])),
+                }
+            }
+        };
+        nodes.push(Node {
+            name: node.string("name")?,
+            parent: None,
+            children: node.references("children", node_objects.len())?,
+            mesh: node.reference("mesh", meshes.len())?,
+            camera: node.reference("camera", cameras.len())?,
+            transform,
+        });
+    }
+    link_parents(&mut nodes, &node_objects)?;
+
+    let mut scenes = Vec::new();
+    for scene in root.objects("scenes")? {
+        scenes.push(Scene { name: scene.string("name")?, nodes: scene.references("nodes", nodes.len())? });
+    }
+    let scene = root.reference("scene", scenes.len())?;
+
+    Ok(Gltf { meshes, materials, textures, samplers, images, cameras, nodes, scenes, scene })
+}
+
+// the hierarchy must be a forest: one parent at most and no cycles
+fn link_parents(nodes: &mut [Node], objects: &[Object]) -> Result<(), GltfError> {
+    for i in 0..nodes.len() {
+        for child in nodes[i].children.clone() {
+            if nodes[child].parent.is_some() || child == i {
+                return Err(objects[i].invalid("children", format!("node {} has more than one parent", child)));
+            }
+            nodes[child].parent = Some(i);
+        }
+    }
+    for (i, object) in objects.iter().enumerate() {
+        let mut parent = nodes[i].parent;
+        for _ in 0..nodes.len() {
+            parent = parent.and_then(|p| nodes[p].parent);
+        }
+        if parent.is_some() {
+            return Err(object.invalid("children", "the node hierarchy has a cycle"));
+        }
+    }
+    Ok(())
+}
+
+fn load_texture_ref(object: &Object, key: &str, textures: usize, scale: &str) -> Result<Option<TextureRef>, GltfError> {
+    let Some(info) = object.object(key)? else {
+        return Ok(None);
+    };
+    Ok(Some(TextureRef {
+        texture: info.required("index", info.reference("index", textures)?)?,
+        tex_coord: info.unsigned("texCoord")?.unwrap_or(0),
+        scale: info.number(scale)?.unwrap_or(1f32),
+    }))
+}
+
+fn load_material(material: &Object, textures: usize) -> Result<PbrMaterial, GltfError> {
+    let mut loaded = PbrMaterial { name: material.string("name")?, ..PbrMaterial::default() };
+    if let Some(pbr) = material.object("pbrMetallicRoughness")? {
+        if let Some(color) = pbr.floats::<4>("baseColorFactor")? {
+            loaded.base_color = Vec4::from(color);
+        }
+        loaded.base_color_texture = load_texture_ref(&pbr, "baseColorTexture", textures, "scale")?;
+        loaded.metallic = pbr.number("metallicFactor")?.unwrap_or(1f32);
+        loaded.roughness = pbr.number("roughnessFactor")?.unwrap_or(1f32);
+        loaded.metallic_roughness_texture = load_texture_ref(&pbr, "metallicRoughnessTexture", textures, "scale")?;
+    }
+    loaded.normal_texture = load_texture_ref(material, "normalTexture", textures, "scale")?;
+    loaded.occlusion_texture = load_texture_ref(material, "occlusionTexture", textures, "strength")?;
+    loaded.emissive_texture = load_texture_ref(material, "emissiveTexture", textures, "scale")?;
+    if let Some(emissive) = material.floats::<3>("emissiveFactor")? {
+        loaded.emissive = Vec3::from(emissive);
+    }
+    loaded.alpha_mode = match material.string("alphaMode")?.as_deref() {
+        None | Some("OPAQUE") => AlphaMode::Opaque,
+        Some("MASK") => AlphaMode::Mask(material.number("alphaCutoff")?.unwrap_or(0.5)),
+        Some("BLEND") => AlphaMode::Blend,
+        Some(mode) => return Err(material.invalid("alphaMode", format!("unknown alpha mode {}", mode))),
+    };
+    loaded.double_sided = material.boolean("doubleSided")?.unwrap_or(false);
+    Ok(loaded)
+}
+
+fn load_camera(camera: &Object) -> Result<Camera, GltfError> {
+    let kind = camera.required("type", camera.string("type")?)?;
+    let projection = match kind.as_str() {
+        "perspective" => {
+            let p = camera.required("perspective", camera.object("perspective")?)?;
+            Projection::Perspective {
+                fov_y: p.required("yfov", p.number("yfov")?)?,
+                aspect: p.number("aspectRatio")?,
+                near: p.required("znear", p.number("znear")?)?,
+                far: p.number("zfar")?,
+            }
+        }
+        "orthographic" => {
+            let o = camera.required("orthographic", camera.object("orthographic")?)?;
+            Projection::Orthographic {
+                x_mag: o.required("xmag", o.number("xmag")?)?,
+                y_mag: o.required("ymag", o.number("ymag")?)?,
+                near: o.required("znear", o.number("znear")?)?,
+                far: o.required("zfar", o.number("zfar")?)?,
+            }
+        }
+        _ => return Err(camera.invalid("type", format!("unknown camera type {}", kind))),
+    };
+    Ok(Camera { name: camera.string("name")?, projection })
+}
+
+fn load_primitive(document: &Document, primitive: &Object, materials: usize) -> Result<Primitive, GltfError> {
+    let attributes = primitive.required("attributes", primitive.object("attributes")?)?;
+    let accessors = document.accessors.len();
+    let position = attributes.required("POSITION", attributes.reference("POSITION", accessors)?)?;
+    let (positions, _) = document.floats(position, &[3])?;
+    let vertex_count = positions.len() / 3;
+
+    // every attribute as a flat list of `vertex_count` elements
+    let attribute = |name: &str, types: &[usize]| -> Result<Option<(Vec<f32>, usize)>, GltfError> {
+        let Some(index) = attributes.reference(name, accessors)? else {
+            return Ok(None);
+        };
+        let (values, components) = document.floats(index, types)?;
+        if values.len() != vertex_count * components {
+            return Err(attributes.invalid(name, "count differs from POSITION"));
+        }
+        Ok(Some((values, components)))
+    };
+
+    let mut mesh = Mesh::new(positions.chunks_exact(3).map(|p| Vec3::new(p[0], p[1], p[2])).collect());
+    let normals = attribute("NORMAL", &[3])?;
+    if let Some((normals, _)) = &normals {
+        mesh.set_normals(normals.chunks_exact(3).map(|n| Vec3::new(n[0], n[1], n[2])).collect());
+    }
+    if let Some((uvs, _)) = attribute("TEXCOORD_0", &[2])? {
+        mesh.set_uvs(uvs.chunks_exact(2).map(|uv| Vec2::new(uv[0], uv[1])).collect());
+    }
+    if let Some((colors, components)) = attribute("COLOR_0", &[3, 4])? {
+        mesh.set_colors(colors.chunks_exact(components).map(|c| {
+            Color::from_linear(&Vec4::new(c[0], c[1], c[2], if components == 4 { c[3] } else { 1f32 }))
+        }).collect());
+    }
+    if let Some((tangents, _)) = attribute("TANGENT", &[4])? {
+        mesh.set_tangents(tangents.chunks_exact(4).map(|t| Vec4::new(t[0], t[1], t[2], t[3])).collect());
+    }
+
+    let mut indices = primitive.reference("indices", accessors)?
+        .map(|index| document.indices(index))
+        .transpose()?;
+    if let Some(out_of_range) = indices.iter().flatten().find(|i| **i as usize >= vertex_count) {
+        return Err(primitive.invalid("indices", format!("vertex {} out of range", out_of_range)));
+    }
+    let topology = match primitive.unsigned("mode")?.unwrap_or(4) {
+        0 => Topology::PointList,
+        1 => Topology::LineList,
+        // a line loop is a strip back to its first vertex
+        2 => {
+            let mut looped = indices.take().unwrap_or_else(|| (0..vertex_count as u32).collect());
+            if let Some(first) = looped.first().copied() {
+                looped.push(first);
+            }
+            indices = Some(looped);
+            Topology::LineStrip
+        }
+        3 => Topology::LineStrip,
+        4 => Topology::TriangleList,
+        5 => Topology::TriangleStrip,
+        6 => Topology::TriangleFan,
+        mode => return Err(primitive.invalid("mode", format!("unknown mode {}", mode))),
+    };
+    mesh.set_indices(indices.map(Indices::from_u32)).set_topology(topology);
+    if topology.is_triangles() && normals.is_none() {
+        mesh = flat_shaded(&mesh);
+    }
+    Ok(Primitive { mesh, material: primitive.reference("material", materials)? })
+}
+
+fn pick<T: Copy>(buffer: &[T], corners: &[u32]) -> Vec<T> {
+    if buffer.is_empty() {
+        return Vec::new();
+    }
+    corners.iter().map(|i| buffer[*i as usize]).collect()
+}
+
+// a triangle list with its own three vertices per triangle, and their face normals
+fn flat_shaded(mesh: &Mesh) -> Mesh {
+    let mut corners = Vec::new();
+    mesh.get_topology().assemble(mesh.element_count(), |triangle| {
+        corners.extend(triangle.iter().map(|k| mesh.element(*k)));
+    });
+    let mut flat = Mesh::new(pick(mesh.get_positions(), &corners));
+    flat.set_uvs(pick(mesh.get_uvs(), &corners))
+        .set_colors(pick(mesh.get_colors(), &corners))
+        .set_tangents(pick(mesh.get_tangents(), &corners))
+        .compute_normals();
+    flat
+}
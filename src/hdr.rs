@@ -0,0 +1,278 @@
+use super::{
+    canvas::{Canvas, Color},
+    colorspace,
+    mathsf::Vec4
+};
+
+// operators mapping [0, inf) radiance into [0, 1]; `white` values below MIN_WHITE are raised to it
+pub enum ToneMapping {
+    Clamp,
+    Reinhard,
+    ExtendedReinhard { white: f32 },
+    AcesFilmic,
+    Uncharted2 { white: f32 },
+}
+
+// linear-light float RGBA pixels, resolved into an 8-bit sRGB Canvas at presentation
+pub struct HdrCanvas {
+    depth_buffer: Vec<f32>,
+    color_buffer: Vec<f32>,
+    width: u32,
+    height: u32,
+    exposure: f32,
+    // None encodes with the sRGB curve, Some(g) with a pure power law
+    gamma: Option<f32>,
+    tone_mapping: ToneMapping,
+}
+
+// keeps the white point divisions finite
+pub const MIN_WHITE: f32 = 1e-3;
+
+impl ToneMapping {
+
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.max(0f32);
+        match self {
+            ToneMapping::Clamp => x.min(1f32),
+            ToneMapping::Reinhard => x / (1f32 + x),
+            ToneMapping::ExtendedReinhard { white } => {
+                let white = white.max(MIN_WHITE);
+                x * (1f32 + x / (white * white)) / (1f32 + x)
+            }
+            ToneMapping::AcesFilmic => {
+                // Narkowicz's fit of the ACES reference rendering transform
+                let (a, b, c, d, e) = (2.51f32, 0.03f32, 2.43f32, 0.59f32, 0.14f32);
+                ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0f32, 1f32)
+            }
+            ToneMapping::Uncharted2 { white } => {
+                const EXPOSURE_BIAS: f32 = 2.0;
+                uncharted2_curve(x * EXPOSURE_BIAS) / uncharted2_curve(white.max(MIN_WHITE))
+            }
+        }
+    }
+
+}
+
+fn uncharted2_curve(x: f32) -> f32 {
+    let (a, b, c, d, e, f) = (0.15f32, 0.50f32, 0.10f32, 0.20f32, 0.02f32, 0.30f32);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+impl HdrCanvas {
+
+    pub fn new(width: u32, height: u32) -> HdrCanvas {
+        let vec: Vec<f32> = vec![1f32; (width * height * 4) as usize];
+        let buf: Vec<f32> = vec![f32::INFINITY; (width * height) as usize];
+        HdrCanvas {
+            depth_buffer: buf,
+            color_buffer: vec,
+            width,
+            height,
+            exposure: 1.0,
+            gamma: None,
+            tone_mapping: ToneMapping::Reinhard,
+        }
+    }
+
+    pub fn draw_pixel(&mut self, i: i32, j: i32, z: f32, color: &Vec4) {
+
+        let i =  i + self.get_width() / 2;
+        let j =  -j + self.get_height() / 2;
+        let index = (i + (j * self.get_width())) as usize;
+
+        // depth test fail
+        if self.depth_buffer[index] < z {
+            return;
+        } else {
+            self.depth_buffer[index] = z;
+        }
+
+        self.write(index, color);
+
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Vec4 {
+        let index = ((x + y * self.width) * 4) as usize;
+        let texel = &self.color_buffer[index..index + 4];
+        Vec4::new(texel[0], texel[1], texel[2], texel[3])
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: &Vec4) {
+        self.write((x + y * self.width) as usize, color);
+    }
+
+    fn write(&mut self, index: usize, color: &Vec4) {
+        let index = index * 4;
+        self.color_buffer[index] = color.get_x();
+        self.color_buffer[index + 1] = color.get_y();
+        self.color_buffer[index + 2] = color.get_z();
+        self.color_buffer[index + 3] = color.get_w();
+    }
+
+    pub fn clear_color(&mut self, color: &Vec4) {
+        let texel = [color.get_x(), color.get_y(), color.get_z(), color.get_w()];
+        for chunk in self.color_buffer.chunks_exact_mut(4) {
+            chunk.copy_from_slice(&texel);
+        }
+    }
+
+    pub fn clear_depth(&mut self, depth: f32) {
+        self.depth_buffer.fill_with(|| depth);
+    }
+
+    pub fn get_width(&self) -> i32 {
+        self.width as i32
+    }
+
+    pub fn get_height(&self) -> i32 {
+        self.height as i32
+    }
+
+    pub fn get_exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+    }
+
+    // exposure expressed in photographic stops, i.e. 2^ev
+    pub fn set_exposure_ev(&mut self, ev: f32) {
+        self.exposure = ev.exp2();
+    }
+
+    pub fn get_gamma(&self) -> Option<f32> {
+        self.gamma
+    }
+
+    pub fn set_gamma(&mut self, gamma: Option<f32>) {
+        self.gamma = gamma;
+    }
+
+    pub fn get_tone_mapping(&self) -> &ToneMapping {
+        &self.tone_mapping
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+
+    // exposure -> tone mapping -> transfer function -> 8-bit quantization
+    pub fn resolve(&self, canvas: &mut Canvas) {
+
+        assert!(
+            canvas.get_width() == self.get_width() && canvas.get_height() == self.get_height(),
+            "HDR canvas and target canvas must have the same dimension."
+        );
+
+        let encode = |v: f32| {
+            let v = self.tone_mapping.apply(v * self.exposure).clamp(0f32, 1f32);
+            match self.gamma {
+                Some(gamma) => (v.powf(1f32 / gamma) * 255f32 + 0.5f32) as u8,
+                None => colorspace::encode(v),
+            }
+        };
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = ((x + y * self.width) * 4) as usize;
+                let texel = &self.color_buffer[index..index + 4];
+                let alpha = (texel[3].clamp(0f32, 1f32) * 255f32 + 0.5f32) as u8;
+                canvas.set_pixel(x, y, Color::from_rgba(encode(texel[0]), encode(texel[1]), encode(texel[2]), alpha));
+            }
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn operators() -> Vec<ToneMapping> {
+        vec![
+            ToneMapping::Clamp,
+            ToneMapping::Reinhard,
+            ToneMapping::ExtendedReinhard { white: 4f32 },
+            ToneMapping::AcesFilmic,
+            ToneMapping::Uncharted2 { white: 11.2 },
+        ]
+    }
+
+    #[test]
+    fn tone_mapping_is_monotonic() {
+        for operator in operators() {
+            let mut previous = operator.apply(0f32);
+            assert!(previous.abs() < 1e-3);
+            // below every white point, where all of them stay within [0, 1]
+            for k in 1..=80 {
+                let value = operator.apply(k as f32 * 0.05);
+                assert!(value >= previous && value <= 1.0001, "{} at {}", value, k);
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    fn clamp_is_the_identity_on_the_unit_interval() {
+        for k in 0..=100 {
+            let x = k as f32 / 100f32;
+            assert_eq!(ToneMapping::Clamp.apply(x), x);
+        }
+        assert_eq!(ToneMapping::Clamp.apply(-1f32), 0f32);
+        assert_eq!(ToneMapping::Clamp.apply(5f32), 1f32);
+    }
+
+    #[test]
+    fn white_point_maps_to_one() {
+        assert!((ToneMapping::ExtendedReinhard { white: 4f32 }.apply(4f32) - 1f32).abs() < 1e-5);
+        assert!((ToneMapping::Uncharted2 { white: 11.2 }.apply(5.6) - 1f32).abs() < 1e-5);
+    }
+
+    #[test]
+    fn zero_white_stays_finite() {
+        for operator in [ToneMapping::ExtendedReinhard { white: 0f32 }, ToneMapping::Uncharted2 { white: 0f32 }] {
+            for x in [0f32, 0.5, 1f32, 100f32] {
+                assert!(operator.apply(x).is_finite());
+            }
+        }
+        let mut hdr = HdrCanvas::new(2, 2);
+        hdr.set_tone_mapping(ToneMapping::ExtendedReinhard { white: 0f32 });
+        hdr.clear_color(&Vec4::new(0.5, 0f32, 2f32, 1f32));
+        let mut canvas = Canvas::new(2, 2);
+        hdr.resolve(&mut canvas);
+        assert_eq!(canvas.get_pixel(1, 1).get_green(), 0);
+        assert_eq!(canvas.get_pixel(1, 1).get_blue(), 255);
+    }
+
+    #[test]
+    fn resolve_applies_exposure_and_transfer() {
+        let mut hdr = HdrCanvas::new(2, 2);
+        hdr.set_tone_mapping(ToneMapping::Clamp);
+        hdr.clear_color(&Vec4::new(0.25, 1f32, 4f32, 0.5));
+        hdr.set_exposure_ev(1f32);
+        let mut canvas = Canvas::new(2, 2);
+        hdr.resolve(&mut canvas);
+        let c = canvas.get_pixel(0, 0);
+        assert_eq!((c.get_red(), c.get_green(), c.get_blue(), c.get_alpha()), (colorspace::encode(0.5), 255, 255, 128));
+
+        hdr.set_exposure(1f32);
+        hdr.set_gamma(Some(2f32));
+        hdr.resolve(&mut canvas);
+        assert_eq!(canvas.get_pixel(1, 0).get_red(), 128);
+    }
+
+    #[test]
+    fn depth_test_keeps_the_nearest() {
+        let mut hdr = HdrCanvas::new(4, 4);
+        hdr.draw_pixel(0, 0, 0.5, &Vec4::new(1f32, 0f32, 0f32, 1f32));
+        hdr.draw_pixel(0, 0, 0.8, &Vec4::new(0f32, 1f32, 0f32, 1f32));
+        assert_eq!(hdr.get_pixel(2, 2), Vec4::new(1f32, 0f32, 0f32, 1f32));
+        hdr.clear_depth(f32::INFINITY);
+        hdr.draw_pixel(0, 0, 0.8, &Vec4::new(0f32, 1f32, 0f32, 1f32));
+        assert_eq!(hdr.get_pixel(2, 2), Vec4::new(0f32, 1f32, 0f32, 1f32));
+    }
+
+}
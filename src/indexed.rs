@@ -0,0 +1,273 @@
+use super::{
+    canvas::{Canvas, Color},
+    palette::Palette
+};
+
+pub const PALETTE_SIZE: usize = 256;
+
+// rotates the palette entries low..=high, `rate` steps per second
+pub struct ColorCycle {
+    low: u8,
+    high: u8,
+    rate: f64,
+    reverse: bool,
+}
+
+// 8-bit palette indices, converted to RGBA only at presentation
+pub struct IndexedCanvas {
+    depth_buffer: Vec<f32>,
+    index_buffer: Vec<u8>,
+    palette: Palette,
+    cycles: Vec<ColorCycle>,
+    width: u32,
+    height: u32,
+    time: f64,
+}
+
+impl ColorCycle {
+
+    pub fn new(low: u8, high: u8, rate: f64) -> Self {
+        ColorCycle {
+            low: low.min(high),
+            high: low.max(high),
+            rate,
+            reverse: false,
+        }
+    }
+
+    pub fn reversed(low: u8, high: u8, rate: f64) -> Self {
+        let mut cycle = Self::new(low, high, rate);
+        cycle.reverse = true;
+        cycle
+    }
+
+    pub fn get_range(&self) -> (u8, u8) {
+        (self.low, self.high)
+    }
+
+    pub fn get_rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate;
+    }
+
+    // how many entries the range is shifted by at the given time
+    pub fn offset(&self, time: f64) -> usize {
+        let len = (self.high - self.low) as usize + 1;
+        let steps = (time * self.rate).floor().rem_euclid(len as f64) as usize;
+        if self.reverse { (len - steps) % len } else { steps }
+    }
+
+}
+
+impl IndexedCanvas {
+
+    pub fn new(width: u32, height: u32) -> IndexedCanvas {
+        let vec: Vec<u8> = vec![0; (width * height) as usize];
+        let buf: Vec<f32> = vec![f32::INFINITY; (width * height) as usize];
+        IndexedCanvas {
+            depth_buffer: buf,
+            index_buffer: vec,
+            palette: Palette::grayscale(PALETTE_SIZE as u32),
+            cycles: Vec::new(),
+            width,
+            height,
+            time: 0.0,
+        }
+    }
+
+    // maps every pixel to its nearest palette entry, dither the canvas beforehand if needed
+    pub fn from_canvas(canvas: &Canvas, palette: Palette) -> IndexedCanvas {
+        let mut indexed = Self::new(canvas.get_width() as u32, canvas.get_height() as u32);
+        indexed.set_palette(palette);
+        for y in 0..indexed.height {
+            for x in 0..indexed.width {
+                let c = canvas.get_pixel(x, y);
+                let index = indexed.palette.nearest(c.get_red() as f32, c.get_green() as f32, c.get_blue() as f32);
+                indexed.set_index(x, y, index as u8);
+            }
+        }
+        indexed
+    }
+
+    pub fn draw_pixel(&mut self, i: i32, j: i32, z: f32, index: u8) {
+
+        let i =  i + self.get_width() / 2;
+        let j =  -j + self.get_height() / 2;
+        let index_position = (i + (j * self.get_width())) as usize;
+
+        // depth test fail
+        if self.depth_buffer[index_position] < z {
+            return;
+        } else {
+            self.depth_buffer[index_position] = z;
+        }
+
+        self.index_buffer[index_position] = index;
+
+    }
+
+    // buffer coordinates, origin at the top-left corner
+    pub fn get_index(&self, x: u32, y: u32) -> u8 {
+        self.index_buffer[(x + y * self.width) as usize]
+    }
+
+    pub fn set_index(&mut self, x: u32, y: u32, index: u8) {
+        self.index_buffer[(x + y * self.width) as usize] = index;
+    }
+
+    pub fn clear_index(&mut self, index: u8) {
+        self.index_buffer.fill(index);
+    }
+
+    pub fn clear_depth(&mut self, depth: f32) {
+        self.depth_buffer.fill_with(|| depth);
+    }
+
+    pub fn get_width(&self) -> i32 {
+        self.width as i32
+    }
+
+    pub fn get_height(&self) -> i32 {
+        self.height as i32
+    }
+
+    pub fn get_palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn get_palette_mut(&mut self) -> &mut Palette {
+        &mut self.palette
+    }
+
+    // palettes are always 256 entries long, missing entries are filled with black
+    pub fn set_palette(&mut self, palette: Palette) {
+        let mut colors: Vec<Color> = palette.colors().iter().take(PALETTE_SIZE).copied().collect();
+        colors.resize(PALETTE_SIZE, Color::black());
+        self.palette = Palette::new(colors);
+    }
+
+    pub fn add_cycle(&mut self, cycle: ColorCycle) {
+        self.cycles.push(cycle);
+    }
+
+    pub fn get_cycles_mut(&mut self) -> &mut Vec<ColorCycle> {
+        &mut self.cycles
+    }
+
+    pub fn clear_cycles(&mut self) {
+        self.cycles.clear();
+    }
+
+    // time as received by Logic::update_routine
+    pub fn update(&mut self, time: f64) {
+        self.time = time;
+    }
+
+    // palette with every cycle applied
+    pub fn resolve_palette(&self) -> Vec<Color> {
+        let base = self.palette.colors();
+        let mut resolved = base.to_vec();
+        for cycle in &self.cycles {
+            let (low, high) = (cycle.low as usize, cycle.high as usize);
+            let len = high - low + 1;
+            let offset = cycle.offset(self.time);
+            for k in 0..len {
+                resolved[low + (k + offset) % len] = base[low + k];
+            }
+        }
+        resolved
+    }
+
+    pub fn present(&self, canvas: &mut Canvas) {
+
+        assert!(
+            canvas.get_width() == self.get_width() && canvas.get_height() == self.get_height(),
+            "Indexed canvas and target canvas must have the same dimension."
+        );
+
+        let palette = self.resolve_palette();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                canvas.set_pixel(x, y, palette[self.get_index(x, y) as usize]);
+            }
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn ramp() -> Palette {
+        Palette::new((0..8).map(|i| Color::from_rgb(i * 10, 0, 0)).collect())
+    }
+
+    #[test]
+    fn cycle_offsets() {
+        let cycle = ColorCycle::new(6, 2, 2.0);
+        assert_eq!(cycle.get_range(), (2, 6));
+        assert_eq!(cycle.offset(0.0), 0);
+        assert_eq!(cycle.offset(0.49), 0);
+        assert_eq!(cycle.offset(0.5), 1);
+        // five entries, back to the start after 2.5 seconds
+        assert_eq!(cycle.offset(2.5), 0);
+        assert_eq!(cycle.offset(-0.5), 4);
+        let reversed = ColorCycle::reversed(2, 6, 2.0);
+        assert_eq!(reversed.offset(0.5), 4);
+        assert_eq!(reversed.offset(1.0), 3);
+    }
+
+    #[test]
+    fn palette_cycling() {
+        let mut indexed = IndexedCanvas::new(2, 2);
+        indexed.set_palette(ramp());
+        assert_eq!(indexed.get_palette().len(), PALETTE_SIZE);
+        assert_eq!(*indexed.get_palette().get(200), Color::black());
+        indexed.add_cycle(ColorCycle::new(1, 3, 1.0));
+
+        let reds = |indexed: &IndexedCanvas| -> Vec<u8> { indexed.resolve_palette()[..5].iter().map(|c| c.get_red()).collect() };
+        assert_eq!(reds(&indexed), vec![0, 10, 20, 30, 40]);
+        indexed.update(1.0);
+        assert_eq!(reds(&indexed), vec![0, 30, 10, 20, 40]);
+        indexed.update(2.5);
+        assert_eq!(reds(&indexed), vec![0, 20, 30, 10, 40]);
+        indexed.update(3.0);
+        assert_eq!(reds(&indexed), vec![0, 10, 20, 30, 40]);
+
+        // the stored palette is untouched, only the presented one moves
+        indexed.update(1.0);
+        indexed.set_index(0, 0, 1);
+        let mut canvas = Canvas::new(2, 2);
+        indexed.present(&mut canvas);
+        assert_eq!(canvas.get_pixel(0, 0).get_red(), 30);
+        assert_eq!(canvas.get_pixel(1, 1), Color::black());
+        assert_eq!(indexed.get_palette().get(1).get_red(), 10);
+        indexed.clear_cycles();
+        indexed.present(&mut canvas);
+        assert_eq!(canvas.get_pixel(0, 0).get_red(), 10);
+    }
+
+    #[test]
+    fn from_canvas_and_depth() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.clear_color(Color::from_rgb(28, 0, 0));
+        let mut indexed = IndexedCanvas::from_canvas(&canvas, ramp());
+        assert_eq!(indexed.get_index(1, 0), 3);
+
+        indexed.draw_pixel(0, 0, 0.5, 7);
+        indexed.draw_pixel(0, 0, 0.9, 5);
+        assert_eq!(indexed.get_index(1, 1), 7);
+        indexed.clear_depth(f32::INFINITY);
+        indexed.draw_pixel(0, 0, 0.9, 5);
+        assert_eq!(indexed.get_index(1, 1), 5);
+        indexed.clear_index(2);
+        assert_eq!(indexed.get_index(1, 1), 2);
+    }
+
+}
@@ -0,0 +1,61 @@
+use super::{
+    canvas::Color,
+    mathsf::{Vec2, Vec3, Vec4}
+};
+
+// values that can be blended linearly: clip vertex attributes, shader varyings, tweens
+pub trait Interpolate: Clone {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for () {
+    fn interpolate(_: &Self, _: &Self, _: f32) -> Self {}
+}
+
+impl Interpolate for f32 {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl Interpolate for Vec2 {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        Vec2::lerp(a, b, t)
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        Vec3::lerp(a, b, t)
+    }
+}
+
+impl Interpolate for Vec4 {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        Vec4::lerp(a, b, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        Color::lerp(a, b, t)
+    }
+}
+
+impl<A: Interpolate, B: Interpolate> Interpolate for (A, B) {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        (A::interpolate(&a.0, &b.0, t), B::interpolate(&a.1, &b.1, t))
+    }
+}
+
+impl<A: Interpolate, B: Interpolate, C: Interpolate> Interpolate for (A, B, C) {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        (A::interpolate(&a.0, &b.0, t), B::interpolate(&a.1, &b.1, t), C::interpolate(&a.2, &b.2, t))
+    }
+}
+
+impl<A: Interpolate, const N: usize> Interpolate for [A; N] {
+    fn interpolate(a: &Self, b: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| A::interpolate(&a[i], &b[i], t))
+    }
+}
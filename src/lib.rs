@@ -2,6 +2,27 @@ pub mod canvas;
 pub mod window;
 pub mod app;
 pub mod mathsf;
+pub mod hdr;
+pub mod colorspace;
+pub mod palette;
+pub mod dither;
+pub mod indexed;
+pub mod filter;
+pub mod resample;
+pub mod pixelart;
+pub mod paint;
+pub mod css;
+pub mod colormap;
+pub mod interpolate;
+pub mod clip;
+pub mod noise;
+pub mod random;
+pub mod tween;
+pub mod spline;
+pub mod pipeline;
+pub mod mesh;
+pub mod obj;
+pub mod gltf;
 
 mod gl;
 
@@ -1,6 +1,270 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+
+mod geometry;
+mod mat;
+mod quat;
+
+pub use geometry::{Aabb, Plane, Ray, Segment, Sphere, Triangle};
+pub use mat::{Mat2, Mat3, Mat4};
+pub use quat::Quat;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Vec2(f32, f32);
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub struct Vec3(f32, f32, f32);
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub struct Vec4(f32, f32, f32, f32);
 
+// Everything the three vector types share. Binary operators between vectors are
+// component-wise, `*` and `/` with an f32 scale every component.
+macro_rules! vector {
+    ($name:ident, $n:literal, $($field:tt),+) => {
+        impl $name {
+            pub fn addition(va: &$name, vb: &$name) -> Self {
+                Self ($(va.$field + vb.$field),+)
+            }
+            pub fn subtraction(va: &$name, vb: &$name) -> Self {
+                Self ($(va.$field - vb.$field),+)
+            }
+            pub fn lerp(v0: &$name, v1: &$name, f: f32) -> Self {
+                Self ($(v0.$field + (v1.$field - v0.$field) * f),+)
+            }
+            pub fn multiply(&mut self, f: f32) -> &mut Self {
+                $(self.$field *= f;)+
+                self
+            }
+            pub fn negate(&mut self) -> &mut Self {
+                self.multiply(-1f32)
+            }
+            pub fn dot(&self, v: &$name) -> f32 {
+                0f32 $(+ self.$field * v.$field)+
+            }
+            pub fn length_squared(&self) -> f32 {
+                self.dot(self)
+            }
+            pub fn length(&self) -> f32 {
+                self.length_squared().sqrt()
+            }
+            pub fn distance(&self, v: &$name) -> f32 {
+                (*self - *v).length()
+            }
+            pub fn distance_squared(&self, v: &$name) -> f32 {
+                (*self - *v).length_squared()
+            }
+            // zero stays zero
+            pub fn normalize(&self) -> Self {
+                let length = self.length();
+                if length > 0f32 { *self / length } else { *self }
+            }
+            // `normal` must be normalized
+            pub fn reflect(&self, normal: &$name) -> Self {
+                *self - *normal * (2f32 * self.dot(normal))
+            }
+            // GLSL refract, eta is the ratio of the indices of refraction; None on total internal reflection
+            pub fn refract(&self, normal: &$name, eta: f32) -> Option<Self> {
+                let cos = self.dot(normal);
+                let k = 1f32 - eta * eta * (1f32 - cos * cos);
+                if k < 0f32 {
+                    return None;
+                }
+                Some(*self * eta - *normal * (eta * cos + k.sqrt()))
+            }
+            pub fn min(&self, v: &$name) -> Self {
+                Self ($(self.$field.min(v.$field)),+)
+            }
+            pub fn max(&self, v: &$name) -> Self {
+                Self ($(self.$field.max(v.$field)),+)
+            }
+            pub fn clamp(&self, min: &$name, max: &$name) -> Self {
+                Self ($(self.$field.clamp(min.$field, max.$field)),+)
+            }
+            pub fn abs(&self) -> Self {
+                Self ($(self.$field.abs()),+)
+            }
+            pub fn floor(&self) -> Self {
+                Self ($(self.$field.floor()),+)
+            }
+            pub fn ceil(&self) -> Self {
+                Self ($(self.$field.ceil()),+)
+            }
+            pub fn round(&self) -> Self {
+                Self ($(self.$field.round()),+)
+            }
+            pub fn map(&self, f: impl Fn(f32) -> f32) -> Self {
+                Self ($(f(self.$field)),+)
+            }
+            pub fn min_element(&self) -> f32 {
+                f32::INFINITY $(.min(self.$field))+
+            }
+            pub fn max_element(&self) -> f32 {
+                f32::NEG_INFINITY $(.max(self.$field))+
+            }
+            pub fn to_array(&self) -> [f32; $n] {
+                [$(self.$field),+]
+            }
+        }
+        impl Add for $name {
+            type Output = $name;
+            fn add(self, rhs: $name) -> $name {
+                Self::addition(&self, &rhs)
+            }
+        }
+        impl Sub for $name {
+            type Output = $name;
+            fn sub(self, rhs: $name) -> $name {
+                Self::subtraction(&self, &rhs)
+            }
+        }
+        impl Mul for $name {
+            type Output = $name;
+            fn mul(self, rhs: $name) -> $name {
+                Self ($(self.$field * rhs.$field),+)
+            }
+        }
+        impl Mul<f32> for $name {
+            type Output = $name;
+            fn mul(self, rhs: f32) -> $name {
+                Self ($(self.$field * rhs),+)
+            }
+        }
+        impl Mul<$name> for f32 {
+            type Output = $name;
+            fn mul(self, rhs: $name) -> $name {
+                rhs * self
+            }
+        }
+        impl Div for $name {
+            type Output = $name;
+            fn div(self, rhs: $name) -> $name {
+                Self ($(self.$field / rhs.$field),+)
+            }
+        }
+        impl Div<f32> for $name {
+            type Output = $name;
+            fn div(self, rhs: f32) -> $name {
+                Self ($(self.$field / rhs),+)
+            }
+        }
+        impl Neg for $name {
+            type Output = $name;
+            fn neg(self) -> $name {
+                Self ($(-self.$field),+)
+            }
+        }
+        impl AddAssign for $name {
+            fn add_assign(&mut self, rhs: $name) {
+                *self = *self + rhs;
+            }
+        }
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, rhs: $name) {
+                *self = *self - rhs;
+            }
+        }
+        impl MulAssign for $name {
+            fn mul_assign(&mut self, rhs: $name) {
+                *self = *self * rhs;
+            }
+        }
+        impl MulAssign<f32> for $name {
+            fn mul_assign(&mut self, rhs: f32) {
+                *self = *self * rhs;
+            }
+        }
+        impl DivAssign for $name {
+            fn div_assign(&mut self, rhs: $name) {
+                *self = *self / rhs;
+            }
+        }
+        impl DivAssign<f32> for $name {
+            fn div_assign(&mut self, rhs: f32) {
+                *self = *self / rhs;
+            }
+        }
+        impl Index<usize> for $name {
+            type Output = f32;
+            fn index(&self, index: usize) -> &f32 {
+                match index {
+                    $($field => &self.$field,)+
+                    _ => panic!("Vector index {} out of range.", index),
+                }
+            }
+        }
+        impl IndexMut<usize> for $name {
+            fn index_mut(&mut self, index: usize) -> &mut f32 {
+                match index {
+                    $($field => &mut self.$field,)+
+                    _ => panic!("Vector index {} out of range.", index),
+                }
+            }
+        }
+        impl From<[f32; $n]> for $name {
+            fn from(a: [f32; $n]) -> Self {
+                Self ($(a[$field]),+)
+            }
+        }
+        impl From<$name> for [f32; $n] {
+            fn from(v: $name) -> Self {
+                v.to_array()
+            }
+        }
+    };
+}
+
+vector!(Vec2, 2, 0, 1);
+vector!(Vec3, 3, 0, 1, 2);
+vector!(Vec4, 4, 0, 1, 2, 3);
+
+// read-only swizzles, e.g. v.zyx()
+macro_rules! swizzle {
+    ($name:ident => $($method:ident: $target:ident($($field:tt),+)),+ $(,)?) => {
+        impl $name {
+            $(pub fn $method(&self) -> $target {
+                $target ($(self.$field),+)
+            })+
+        }
+    };
+}
+
+swizzle!(Vec2 => yx: Vec2(1, 0), xx: Vec2(0, 0), yy: Vec2(1, 1));
+swizzle!(Vec3 =>
+    xy: Vec2(0, 1), xz: Vec2(0, 2), yz: Vec2(1, 2), yx: Vec2(1, 0), zx: Vec2(2, 0), zy: Vec2(2, 1),
+    xzy: Vec3(0, 2, 1), yxz: Vec3(1, 0, 2), yzx: Vec3(1, 2, 0), zxy: Vec3(2, 0, 1), zyx: Vec3(2, 1, 0),
+);
+swizzle!(Vec4 =>
+    xy: Vec2(0, 1), zw: Vec2(2, 3), xyz: Vec3(0, 1, 2), yzw: Vec3(1, 2, 3), wzyx: Vec4(3, 2, 1, 0),
+);
+
+impl Vec2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self (x, y)
+    }
+    pub fn zero() -> Self {
+        Self (0f32, 0f32)
+    }
+    pub fn set(&mut self, x: f32, y: f32) {
+        self.0 = x;
+        self.1 = y;
+    }
+    pub fn get_x(&self) -> f32 {
+        self.0
+    }
+    pub fn get_y(&self) -> f32 {
+        self.1
+    }
+    // z of the 3D cross product, positive when v is counter-clockwise from self
+    pub fn cross(&self, v: &Vec2) -> f32 {
+        self.0 * v.1 - self.1 * v.0
+    }
+    // rotated a quarter turn counter-clockwise
+    pub fn perpendicular(&self) -> Self {
+        Self (-self.1, self.0)
+    }
+    pub fn extend(&self, z: f32) -> Vec3 {
+        Vec3 (self.0, self.1, z)
+    }
+}
+
 impl Vec3 {
     pub fn new(x: f32, y: f32, z: f32) -> Self {
         Self (x, y, z)
@@ -8,15 +272,6 @@ impl Vec3 {
     pub fn zero() -> Self {
         Self (0f32, 0f32, 0f32)
     }
-    pub fn addition(v0: &Vec3, v1: &Vec3) -> Self {
-        Self (v0.0 + v1.0, v0.1 + v1.1, v0.2 + v1.2)
-    }
-    pub fn subtraction(v0: &Vec3, v1: &Vec3) -> Self {
-        Self (v0.0 - v1.0, v0.1 - v1.1, v0.2 - v1.2)
-    }
-    pub fn lerp(v0: &Vec3, v1: &Vec3, f: f32) -> Self {
-        Self::addition(v0, Self::subtraction(v1, v0).multiply(f))
-    }
     pub fn set(&mut self, x: f32, y: f32, z: f32) {
         self.0 = x;
         self.1 = y;
@@ -39,24 +294,17 @@ impl Vec3 {
         self.0 = self.0.ceil();
         self.1 = self.1.ceil();
     }
-    pub fn multiply(&mut self, f: f32) -> &mut Self {
-        self.0 *= f;
-        self.1 *= f;
-        self.2 *= f;
-        self
+    pub fn cross(&self, v: &Vec3) -> Self {
+        Self (self.1 * v.2 - self.2 * v.1, self.2 * v.0 - self.0 * v.2, self.0 * v.1 - self.1 * v.0)
+    }
+    pub fn extend(&self, w: f32) -> Vec4 {
+        Vec4 (self.0, self.1, self.2, w)
     }
+    #[deprecated(note = "use `+=`")]
     pub fn add(&mut self, v: &Vec3) -> &mut Self {
-        self.0 += v.0;
-        self.1 += v.1;
-        self.2 += v.2;
+        *self += *v;
         self
     }
-    pub fn negate(&mut self) -> &mut Self {
-        self.multiply(-1f32)
-    }
-    pub fn length(&self) -> f32 {
-        (self.0*self.0 + self.1*self.1 + self.2*self.2).sqrt()
-    }
 }
 
 impl Vec4 {
@@ -66,12 +314,6 @@ impl Vec4 {
     pub fn zero() -> Self {
         Self (0f32, 0f32, 0f32, 0f32)
     }
-    pub fn addition(va: &Vec4, vb: &Vec4) -> Self {
-        Self (va.0 + vb.0, va.1 + vb.1, va.2 + vb.2, va.3 + vb.3)
-    }
-    pub fn subtraction(va: &Vec4, vb: &Vec4) -> Self {
-        Self (va.0 - vb.0, va.1 - vb.1, va.2 - vb.2, va.3 - vb.3)
-    }
     pub fn set(&mut self, x: f32, y: f32, z: f32, w: f32) {
         self.0 = x;
         self.1 = y;
@@ -90,24 +332,83 @@ impl Vec4 {
     pub fn get_w(&self) -> f32 {
         self.3
     }
-    pub fn multiply(&mut self, f: f32) -> &mut Self {
-        self.0 *= f;
-        self.1 *= f;
-        self.2 *= f;
-        self.3 *= f;
-        self
+    // divides by w, w = 0 is left as is
+    pub fn project(&self) -> Vec3 {
+        let w = if self.3 != 0f32 { self.3 } else { 1f32 };
+        Vec3 (self.0 / w, self.1 / w, self.2 / w)
     }
+    #[deprecated(note = "use `+=`")]
     pub fn add(&mut self, v: &Vec4) -> &mut Self {
-        self.0 += v.0;
-        self.1 += v.1;
-        self.2 += v.2;
-        self.3 += v.3;
+        *self += *v;
         self
     }
-    pub fn negate(&mut self) -> &mut Self {
-        self.multiply(-1f32)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // std::ops::Add is not in scope here, as for most callers, so `add` is the inherent method
+    mod deprecated {
+
+        use super::super::{Vec3, Vec4};
+
+        #[test]
+        #[allow(deprecated)]
+        fn owned_add_is_in_place() {
+            let mut pos = Vec3::new(1f32, 2f32, 3f32);
+            pos.add(&Vec3::new(1f32, 1f32, 1f32));
+            assert_eq!(pos, Vec3::new(2f32, 3f32, 4f32));
+            pos.add(&Vec3::new(1f32, 1f32, 1f32)).multiply(2f32);
+            assert_eq!(pos, Vec3::new(6f32, 8f32, 10f32));
+            let mut w = Vec4::new(1f32, 2f32, 3f32, 4f32);
+            w.add(&Vec4::new(1f32, 0f32, 0f32, -4f32));
+            assert_eq!(w, Vec4::new(2f32, 2f32, 3f32, 0f32));
+        }
+
+    }
+
+    #[test]
+    fn operators_are_component_wise() {
+        let (a, b) = (Vec3::new(1f32, 2f32, 3f32), Vec3::new(4f32, 5f32, 6f32));
+        assert_eq!(a + b, Vec3::new(5f32, 7f32, 9f32));
+        assert_eq!(b - a, Vec3::new(3f32, 3f32, 3f32));
+        assert_eq!(a * b, Vec3::new(4f32, 10f32, 18f32));
+        assert_eq!(2f32 * a, a * 2f32);
+        assert_eq!(b / 2f32, Vec3::new(2f32, 2.5, 3f32));
+        assert_eq!(-a, Vec3::new(-1f32, -2f32, -3f32));
+        let mut c = a;
+        c += b;
+        c -= a;
+        c *= 2f32;
+        c /= 4f32;
+        assert_eq!(c, Vec3::new(2f32, 2.5, 3f32));
+        assert_eq!(a[2], 3f32);
+        assert_eq!(Vec4::from([1f32, 2f32, 3f32, 4f32]).to_array(), [1f32, 2f32, 3f32, 4f32]);
+    }
+
+    #[test]
+    fn products_and_lengths() {
+        let (x, y) = (Vec3::new(1f32, 0f32, 0f32), Vec3::new(0f32, 1f32, 0f32));
+        assert_eq!(x.cross(&y), Vec3::new(0f32, 0f32, 1f32));
+        assert_eq!(x.dot(&y), 0f32);
+        assert_eq!(Vec2::new(1f32, 0f32).cross(&Vec2::new(0f32, 1f32)), 1f32);
+        assert_eq!(Vec2::new(3f32, 4f32).length(), 5f32);
+        assert_eq!(Vec3::new(0f32, 3f32, 4f32).normalize(), Vec3::new(0f32, 0.6, 0.8));
+        assert_eq!(Vec3::lerp(&x, &y, 0.25), Vec3::new(0.75, 0.25, 0f32));
+        assert_eq!(Vec4::new(2f32, 4f32, 6f32, 2f32).project(), Vec3::new(1f32, 2f32, 3f32));
     }
-    pub fn length(&self) -> f32 {
-        (self.0*self.0 + self.1*self.1 + self.2*self.2 + self.3*self.3).sqrt()
+
+    #[test]
+    fn reflect_and_refract() {
+        let n = Vec3::new(0f32, 1f32, 0f32);
+        let d = Vec3::new(1f32, -1f32, 0f32).normalize();
+        assert!(d.reflect(&n).distance(&Vec3::new(1f32, 1f32, 0f32).normalize()) < 1e-6);
+        // no bending when the indices match
+        assert!(d.refract(&n, 1f32).unwrap().distance(&d) < 1e-6);
+        // total internal reflection at a grazing angle into a thinner medium
+        assert_eq!(Vec3::new(1f32, -0.1, 0f32).normalize().refract(&n, 1.5), None);
     }
-}
\ No newline at end of file
+
+}
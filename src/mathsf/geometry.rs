@@ -0,0 +1,430 @@
+use super::Vec3;
+
+// Ray hits are reported as the parameter t along the ray, origin + t * direction,
+// only for t >= 0. The direction isn't normalized, t is in units of its length.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ray {
+    origin: Vec3,
+    direction: Vec3,
+}
+
+// every p with normal . p = distance, the normal is kept unit length
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Plane {
+    normal: Vec3,
+    distance: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sphere {
+    center: Vec3,
+    radius: f32,
+}
+
+// counter-clockwise winding is the front face
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Triangle {
+    a: Vec3,
+    b: Vec3,
+    c: Vec3,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Segment {
+    start: Vec3,
+    end: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Ray { origin, direction }
+    }
+    pub fn get_origin(&self) -> Vec3 {
+        self.origin
+    }
+    pub fn get_direction(&self) -> Vec3 {
+        self.direction
+    }
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let denominator = plane.normal.dot(&self.direction);
+        // relative to the direction's length, the normal is unit length
+        if denominator.abs() <= f32::EPSILON * self.direction.length() {
+            return None;
+        }
+        let t = (plane.distance - plane.normal.dot(&self.origin)) / denominator;
+        if t >= 0f32 { Some(t) } else { None }
+    }
+    // nearest hit, the exit point when the origin is inside the sphere
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let oc = self.origin - sphere.center;
+        let a = self.direction.length_squared();
+        let b = oc.dot(&self.direction);
+        let c = oc.length_squared() - sphere.radius * sphere.radius;
+        let discriminant = b * b - a * c;
+        if a == 0f32 || discriminant < 0f32 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let near = (-b - root) / a;
+        let far = (-b + root) / a;
+        if near >= 0f32 {
+            Some(near)
+        } else if far >= 0f32 {
+            Some(far)
+        } else {
+            None
+        }
+    }
+    // slab test, (t_enter, t_exit) with t_enter = 0 when the origin is inside the box
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<(f32, f32)> {
+        let (mut enter, mut exit) = (0f32, f32::INFINITY);
+        for axis in 0..3 {
+            // a zero component gives +-infinity, which the comparisons handle
+            let inverse = 1f32 / self.direction[axis];
+            let mut t0 = (aabb.min[axis] - self.origin[axis]) * inverse;
+            let mut t1 = (aabb.max[axis] - self.origin[axis]) * inverse;
+            if inverse < 0f32 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN (origin on a slab boundary of a parallel ray) leaves the interval untouched
+            if t0 > enter {
+                enter = t0;
+            }
+            if t1 < exit {
+                exit = t1;
+            }
+            if exit < enter {
+                return None;
+            }
+        }
+        Some((enter, exit))
+    }
+    // Moller-Trumbore, (t, u, v) where u and v are the barycentric weights of b and c.
+    // Both faces are hit, check the triangle normal against the direction to cull.
+    pub fn intersect_triangle(&self, triangle: &Triangle) -> Option<(f32, f32, f32)> {
+        let edge1 = triangle.b - triangle.a;
+        let edge2 = triangle.c - triangle.a;
+        let p = self.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        // parallel within rounding error at the scale of the edges and the direction
+        let scale = edge1.length() * edge2.length() * self.direction.length();
+        if determinant.abs() <= f32::EPSILON * scale {
+            return None;
+        }
+        let inverse = 1f32 / determinant;
+        let s = self.origin - triangle.a;
+        let u = s.dot(&p) * inverse;
+        if !(0f32..=1f32).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&edge1);
+        let v = self.direction.dot(&q) * inverse;
+        if v < 0f32 || u + v > 1f32 {
+            return None;
+        }
+        let t = edge2.dot(&q) * inverse;
+        if t >= 0f32 { Some((t, u, v)) } else { None }
+    }
+}
+
+impl Plane {
+    // the normal is normalized, distance scaled along with it
+    pub fn new(normal: Vec3, distance: f32) -> Self {
+        let length = normal.length();
+        assert!(length > 0f32, "A plane needs a non-zero normal.");
+        Plane { normal: normal / length, distance: distance / length }
+    }
+    // same normalization and zero check as new
+    pub fn from_point_normal(point: &Vec3, normal: &Vec3) -> Self {
+        Self::new(*normal, normal.dot(point))
+    }
+    // the normal faces the side a, b, c appear counter-clockwise from, they must not be collinear
+    pub fn from_points(a: &Vec3, b: &Vec3, c: &Vec3) -> Self {
+        Self::from_point_normal(a, &(*b - *a).cross(&(*c - *a)))
+    }
+    pub fn get_normal(&self) -> Vec3 {
+        self.normal
+    }
+    pub fn get_distance(&self) -> f32 {
+        self.distance
+    }
+    // positive on the side the normal points to
+    pub fn signed_distance(&self, point: &Vec3) -> f32 {
+        self.normal.dot(point) - self.distance
+    }
+    pub fn closest_point(&self, point: &Vec3) -> Vec3 {
+        *point - self.normal * self.signed_distance(point)
+    }
+}
+
+impl Aabb {
+    // corners may be given in any order
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Aabb { min: a.min(&b), max: a.max(&b) }
+    }
+    pub fn from_points(points: &[Vec3]) -> Option<Self> {
+        let (first, rest) = points.split_first()?;
+        Some(rest.iter().fold(Aabb { min: *first, max: *first }, |aabb, p| aabb.expand(p)))
+    }
+    pub fn get_min(&self) -> Vec3 {
+        self.min
+    }
+    pub fn get_max(&self) -> Vec3 {
+        self.max
+    }
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+    // half the size
+    pub fn extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+    pub fn contains(&self, point: &Vec3) -> bool {
+        point.clamp(&self.min, &self.max) == *point
+    }
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= aabb.max[axis] && aabb.min[axis] <= self.max[axis])
+    }
+    pub fn expand(&self, point: &Vec3) -> Self {
+        Aabb { min: self.min.min(point), max: self.max.max(point) }
+    }
+    pub fn union(&self, aabb: &Aabb) -> Self {
+        Aabb { min: self.min.min(&aabb.min), max: self.max.max(&aabb.max) }
+    }
+    pub fn closest_point(&self, point: &Vec3) -> Vec3 {
+        point.clamp(&self.min, &self.max)
+    }
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Sphere { center, radius: radius.abs() }
+    }
+    pub fn get_center(&self) -> Vec3 {
+        self.center
+    }
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+    pub fn contains(&self, point: &Vec3) -> bool {
+        self.center.distance_squared(point) <= self.radius * self.radius
+    }
+    pub fn intersects(&self, sphere: &Sphere) -> bool {
+        let r = self.radius + sphere.radius;
+        self.center.distance_squared(&sphere.center) <= r * r
+    }
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.contains(&aabb.closest_point(&self.center))
+    }
+    pub fn intersects_plane(&self, plane: &Plane) -> bool {
+        plane.signed_distance(&self.center).abs() <= self.radius
+    }
+    // on the surface, the center itself when the point is the center
+    pub fn closest_point(&self, point: &Vec3) -> Vec3 {
+        self.center + (*point - self.center).normalize() * self.radius
+    }
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3) -> Self {
+        Triangle { a, b, c }
+    }
+    pub fn get_vertices(&self) -> (Vec3, Vec3, Vec3) {
+        (self.a, self.b, self.c)
+    }
+    // unit normal, zero for degenerate triangles
+    pub fn normal(&self) -> Vec3 {
+        (self.b - self.a).cross(&(self.c - self.a)).normalize()
+    }
+    pub fn area(&self) -> f32 {
+        (self.b - self.a).cross(&(self.c - self.a)).length() * 0.5
+    }
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.a, self.b).expand(&self.c)
+    }
+    // weights of (a, b, c) for a point in the triangle's plane
+    pub fn barycentric(&self, point: &Vec3) -> Option<Vec3> {
+        let (v0, v1, v2) = (self.b - self.a, self.c - self.a, *point - self.a);
+        let (d00, d01, d11) = (v0.dot(&v0), v0.dot(&v1), v1.dot(&v1));
+        let (d20, d21) = (v2.dot(&v0), v2.dot(&v1));
+        // |v0 x v1|^2, degenerate when it cancels down to rounding error of d00 * d11
+        let denominator = d00 * d11 - d01 * d01;
+        if denominator.abs() <= f32::EPSILON * d00 * d11 {
+            return None;
+        }
+        let v = (d11 * d20 - d01 * d21) / denominator;
+        let w = (d00 * d21 - d01 * d20) / denominator;
+        Some(Vec3::new(1f32 - v - w, v, w))
+    }
+    // Ericson, Real-Time Collision Detection 5.1.5
+    pub fn closest_point(&self, point: &Vec3) -> Vec3 {
+        let (a, b, c) = (self.a, self.b, self.c);
+        let (ab, ac, ap) = (b - a, c - a, *point - a);
+        let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+        if d1 <= 0f32 && d2 <= 0f32 {
+            return a;
+        }
+        let bp = *point - b;
+        let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+        if d3 >= 0f32 && d4 <= d3 {
+            return b;
+        }
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0f32 && d1 >= 0f32 && d3 <= 0f32 {
+            return a + ab * (d1 / (d1 - d3));
+        }
+        let cp = *point - c;
+        let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+        if d6 >= 0f32 && d5 <= d6 {
+            return c;
+        }
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0f32 && d2 >= 0f32 && d6 <= 0f32 {
+            return a + ac * (d2 / (d2 - d6));
+        }
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0f32 && d4 - d3 >= 0f32 && d5 - d6 >= 0f32 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+        // inside the face
+        let denominator = 1f32 / (va + vb + vc);
+        a + ab * (vb * denominator) + ac * (vc * denominator)
+    }
+}
+
+impl Segment {
+    pub fn new(start: Vec3, end: Vec3) -> Self {
+        Segment { start, end }
+    }
+    pub fn get_start(&self) -> Vec3 {
+        self.start
+    }
+    pub fn get_end(&self) -> Vec3 {
+        self.end
+    }
+    pub fn length(&self) -> f32 {
+        self.start.distance(&self.end)
+    }
+    // parameter in [0, 1] of the closest point
+    pub fn closest_parameter(&self, point: &Vec3) -> f32 {
+        let d = self.end - self.start;
+        let length2 = d.length_squared();
+        if length2 == 0f32 {
+            return 0f32;
+        }
+        ((*point - self.start).dot(&d) / length2).clamp(0f32, 1f32)
+    }
+    pub fn closest_point(&self, point: &Vec3) -> Vec3 {
+        Vec3::lerp(&self.start, &self.end, self.closest_parameter(point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close(a: &Vec3, b: &Vec3) -> bool {
+        a.distance(b) < 1e-5
+    }
+
+    #[test]
+    fn plane_constructors_agree() {
+        let point = Vec3::new(0f32, 2f32, 0f32);
+        let a = Plane::new(Vec3::new(0f32, 4f32, 0f32), 8f32);
+        let b = Plane::from_point_normal(&point, &Vec3::new(0f32, 4f32, 0f32));
+        let c = Plane::from_points(&point, &Vec3::new(1f32, 2f32, 0f32), &Vec3::new(0f32, 2f32, -1f32));
+        for plane in [a, b, c] {
+            assert!(close(&plane.get_normal(), &Vec3::new(0f32, 1f32, 0f32)));
+            assert!((plane.get_distance() - 2f32).abs() < 1e-6);
+            assert!((plane.signed_distance(&Vec3::new(5f32, 5f32, 5f32)) - 3f32).abs() < 1e-6);
+        }
+        assert!(close(&a.closest_point(&Vec3::new(1f32, 7f32, 1f32)), &Vec3::new(1f32, 2f32, 1f32)));
+    }
+
+    #[test]
+    #[should_panic(expected = "non-zero normal")]
+    fn plane_from_point_and_zero_normal() {
+        Plane::from_point_normal(&Vec3::new(1f32, 2f32, 3f32), &Vec3::zero());
+    }
+
+    #[test]
+    #[should_panic(expected = "non-zero normal")]
+    fn plane_from_collinear_points() {
+        let (a, b) = (Vec3::zero(), Vec3::new(1f32, 1f32, 1f32));
+        Plane::from_points(&a, &b, &(b * 2f32));
+    }
+
+    #[test]
+    fn ray_hits() {
+        let ray = Ray::new(Vec3::new(0f32, 0f32, 5f32), Vec3::new(0f32, 0f32, -1f32));
+        let plane = Plane::from_point_normal(&Vec3::new(0f32, 0f32, 1f32), &Vec3::new(0f32, 0f32, 1f32));
+        assert_eq!(ray.intersect_plane(&plane), Some(4f32));
+        assert_eq!(ray.intersect_sphere(&Sphere::new(Vec3::zero(), 1f32)), Some(4f32));
+        assert_eq!(ray.intersect_sphere(&Sphere::new(Vec3::new(3f32, 0f32, 0f32), 1f32)), None);
+        let aabb = Aabb::new(Vec3::new(-1f32, -1f32, -1f32), Vec3::new(1f32, 1f32, 1f32));
+        assert_eq!(ray.intersect_aabb(&aabb), Some((4f32, 6f32)));
+        let triangle = Triangle::new(
+            Vec3::new(-1f32, -1f32, 0f32), Vec3::new(1f32, -1f32, 0f32), Vec3::new(-1f32, 1f32, 0f32)
+        );
+        let (t, u, v) = ray.intersect_triangle(&triangle).unwrap();
+        assert_eq!((t, u, v), (5f32, 0.5, 0.5));
+        assert!(close(&ray.at(t), &Vec3::zero()));
+    }
+
+    #[test]
+    fn closest_points() {
+        let aabb = Aabb::from_points(&[Vec3::zero(), Vec3::new(2f32, 1f32, 1f32)]).unwrap();
+        assert_eq!(aabb.closest_point(&Vec3::new(5f32, 0.5, -3f32)), Vec3::new(2f32, 0.5, 0f32));
+        assert!(Aabb::from_points(&[]).is_none());
+        let segment = Segment::new(Vec3::zero(), Vec3::new(2f32, 0f32, 0f32));
+        assert_eq!(segment.closest_parameter(&Vec3::new(3f32, 1f32, 0f32)), 1f32);
+        assert_eq!(segment.closest_point(&Vec3::new(0.5, 1f32, 0f32)), Vec3::new(0.5, 0f32, 0f32));
+        let triangle = Triangle::new(Vec3::zero(), Vec3::new(1f32, 0f32, 0f32), Vec3::new(0f32, 1f32, 0f32));
+        assert!(close(&triangle.closest_point(&Vec3::new(0.2, 0.2, 3f32)), &Vec3::new(0.2, 0.2, 0f32)));
+        assert!(close(&triangle.closest_point(&Vec3::new(2f32, 2f32, 0f32)), &Vec3::new(0.5, 0.5, 0f32)));
+        assert_eq!(triangle.area(), 0.5);
+    }
+
+    #[test]
+    fn small_geometry_is_not_degenerate() {
+        // 1e-4 edges, determinants around 1e-8 to 1e-16
+        let e = 1e-4f32;
+        let triangle = Triangle::new(Vec3::zero(), Vec3::new(e, 0f32, 0f32), Vec3::new(0f32, e, 0f32));
+        let weights = triangle.barycentric(&Vec3::new(e * 0.25, e * 0.5, 0f32)).unwrap();
+        assert!(close(&weights, &Vec3::new(0.25, 0.25, 0.5)));
+        let ray = Ray::new(Vec3::new(e * 0.25, e * 0.25, e), Vec3::new(0f32, 0f32, -e));
+        let (t, u, v) = ray.intersect_triangle(&triangle).unwrap();
+        assert!((t - 1f32).abs() < 1e-5 && (u - 0.25).abs() < 1e-5 && (v - 0.25).abs() < 1e-5);
+        let plane = Plane::from_points(&Vec3::zero(), &Vec3::new(e, 0f32, 0f32), &Vec3::new(0f32, e, 0f32));
+        assert!((ray.intersect_plane(&plane).unwrap() - 1f32).abs() < 1e-5);
+        // an oblique direction of small length
+        let ray = Ray::new(Vec3::new(0f32, 0f32, 1f32), Vec3::new(1e-4, 0f32, -1e-5));
+        assert!((ray.intersect_plane(&plane).unwrap() - 1e5).abs() < 1f32);
+    }
+
+    #[test]
+    fn degenerate_geometry_is_rejected() {
+        let collinear = Triangle::new(Vec3::zero(), Vec3::new(1f32, 1f32, 1f32), Vec3::new(3f32, 3f32, 3f32));
+        assert!(collinear.barycentric(&Vec3::new(2f32, 2f32, 2f32)).is_none());
+        let ray = Ray::new(Vec3::new(0f32, -1f32, 0f32), Vec3::new(1f32, 1f32, 1f32));
+        assert!(ray.intersect_triangle(&collinear).is_none());
+        let big = Triangle::new(Vec3::zero(), Vec3::new(1e4, 0f32, 0f32), Vec3::new(0f32, 1e4, 0f32));
+        // parallel to the face, the determinant is rounding error
+        let ray = Ray::new(Vec3::new(1f32, 1f32, 1f32), Vec3::new(1f32, 1f32, 0f32));
+        assert!(ray.intersect_triangle(&big).is_none());
+        let plane = Plane::new(Vec3::new(0f32, 0f32, 1f32), 0f32);
+        assert!(ray.intersect_plane(&plane).is_none());
+        assert!(Ray::new(Vec3::zero(), Vec3::zero()).intersect_plane(&plane).is_none());
+    }
+
+}
@@ -0,0 +1,463 @@
+use std::ops::{Mul, MulAssign};
+
+use super::{Vec2, Vec3, Vec4};
+
+// Column major, m.0[column][row], matching OpenGL. Vectors are columns, so
+// `a * b` applies b first. Angles are in radians, handedness is right-handed
+// and the projections map depth to the OpenGL clip range [-w, w].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mat2([[f32; 2]; 2]);
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mat3([[f32; 3]; 3]);
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Mat4([[f32; 4]; 4]);
+
+fn identity<const N: usize>() -> [[f32; N]; N] {
+    let mut m = [[0f32; N]; N];
+    for (i, column) in m.iter_mut().enumerate() {
+        column[i] = 1f32;
+    }
+    m
+}
+
+fn multiply<const N: usize>(a: &[[f32; N]; N], b: &[[f32; N]; N]) -> [[f32; N]; N] {
+    let mut m = [[0f32; N]; N];
+    for c in 0..N {
+        for r in 0..N {
+            m[c][r] = (0..N).map(|k| a[k][r] * b[c][k]).sum();
+        }
+    }
+    m
+}
+
+fn transpose<const N: usize>(a: &[[f32; N]; N]) -> [[f32; N]; N] {
+    let mut m = [[0f32; N]; N];
+    for c in 0..N {
+        for r in 0..N {
+            m[c][r] = a[r][c];
+        }
+    }
+    m
+}
+
+// gaussian elimination with partial pivoting
+fn determinant<const N: usize>(a: &[[f32; N]; N]) -> f32 {
+    let mut m = transpose(a);
+    let mut det = 1f32;
+    for i in 0..N {
+        let pivot = (i..N).max_by(|x, y| m[*x][i].abs().total_cmp(&m[*y][i].abs())).unwrap_or(i);
+        if m[pivot][i] == 0f32 {
+            return 0f32;
+        }
+        if pivot != i {
+            m.swap(pivot, i);
+            det = -det;
+        }
+        det *= m[i][i];
+        let (top, bottom) = m.split_at_mut(i + 1);
+        let pivot_row = &top[i];
+        for row in bottom.iter_mut() {
+            let f = row[i] / pivot_row[i];
+            for (v, p) in row.iter_mut().zip(pivot_row.iter()).skip(i) {
+                *v -= f * p;
+            }
+        }
+    }
+    det
+}
+
+// gauss-jordan with partial pivoting, None when singular
+fn inverse<const N: usize>(a: &[[f32; N]; N]) -> Option<[[f32; N]; N]> {
+    // pivots below rounding error at the scale of the matrix count as zero
+    let scale = a.iter().flatten().fold(0f32, |max, v| max.max(v.abs()));
+    let tolerance = f32::EPSILON * scale * N as f32;
+    // work on rows, m[row][column]
+    let mut m = transpose(a);
+    let mut inv = identity::<N>();
+    for i in 0..N {
+        let pivot = (i..N).max_by(|x, y| m[*x][i].abs().total_cmp(&m[*y][i].abs())).unwrap_or(i);
+        if m[pivot][i].abs() <= tolerance || !m[pivot][i].is_finite() {
+            return None;
+        }
+        m.swap(pivot, i);
+        inv.swap(pivot, i);
+        let p = m[i][i];
+        for c in 0..N {
+            m[i][c] /= p;
+            inv[i][c] /= p;
+        }
+        for r in 0..N {
+            if r == i {
+                continue;
+            }
+            let f = m[r][i];
+            for c in 0..N {
+                m[r][c] -= f * m[i][c];
+                inv[r][c] -= f * inv[i][c];
+            }
+        }
+    }
+    Some(transpose(&inv))
+}
+
+macro_rules! matrix_common {
+    ($name:ident, $n:literal) => {
+        impl $name {
+            pub fn from_cols(columns: [[f32; $n]; $n]) -> Self {
+                Self(columns)
+            }
+            pub fn from_rows(rows: [[f32; $n]; $n]) -> Self {
+                Self(transpose(&rows))
+            }
+            pub fn identity() -> Self {
+                Self(identity::<$n>())
+            }
+            pub fn zero() -> Self {
+                Self([[0f32; $n]; $n])
+            }
+            pub fn get(&self, row: usize, column: usize) -> f32 {
+                self.0[column][row]
+            }
+            pub fn set(&mut self, row: usize, column: usize, value: f32) {
+                self.0[column][row] = value;
+            }
+            pub fn cols(&self) -> &[[f32; $n]; $n] {
+                &self.0
+            }
+            pub fn multiplication(a: &$name, b: &$name) -> Self {
+                Self(multiply(&a.0, &b.0))
+            }
+            pub fn transpose(&self) -> Self {
+                Self(transpose(&self.0))
+            }
+            pub fn determinant(&self) -> f32 {
+                determinant(&self.0)
+            }
+            pub fn inverse(&self) -> Option<Self> {
+                inverse(&self.0).map(Self)
+            }
+            pub fn scalar(&self, f: f32) -> Self {
+                Self(self.0.map(|column| column.map(|v| v * f)))
+            }
+        }
+        impl Mul for $name {
+            type Output = $name;
+            fn mul(self, rhs: $name) -> $name {
+                Self::multiplication(&self, &rhs)
+            }
+        }
+        impl MulAssign for $name {
+            fn mul_assign(&mut self, rhs: $name) {
+                *self = Self::multiplication(self, &rhs);
+            }
+        }
+        impl Mul<f32> for $name {
+            type Output = $name;
+            fn mul(self, rhs: f32) -> $name {
+                self.scalar(rhs)
+            }
+        }
+        impl Default for $name {
+            fn default() -> Self {
+                Self::identity()
+            }
+        }
+    };
+}
+
+matrix_common!(Mat2, 2);
+matrix_common!(Mat3, 3);
+matrix_common!(Mat4, 4);
+
+impl Mat2 {
+    pub fn rotation(angle: f32) -> Self {
+        let (s, c) = angle.sin_cos();
+        Self([[c, s], [-s, c]])
+    }
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self([[x, 0f32], [0f32, y]])
+    }
+    pub fn transform(&self, v: &Vec2) -> Vec2 {
+        let m = &self.0;
+        Vec2(m[0][0] * v.0 + m[1][0] * v.1, m[0][1] * v.0 + m[1][1] * v.1)
+    }
+}
+
+impl Mat3 {
+    pub fn rotation_x(angle: f32) -> Self {
+        let (s, c) = angle.sin_cos();
+        Self([[1f32, 0f32, 0f32], [0f32, c, s], [0f32, -s, c]])
+    }
+    pub fn rotation_y(angle: f32) -> Self {
+        let (s, c) = angle.sin_cos();
+        Self([[c, 0f32, -s], [0f32, 1f32, 0f32], [s, 0f32, c]])
+    }
+    pub fn rotation_z(angle: f32) -> Self {
+        let (s, c) = angle.sin_cos();
+        Self([[c, s, 0f32], [-s, c, 0f32], [0f32, 0f32, 1f32]])
+    }
+    // Rodrigues, the axis doesn't need to be normalized
+    pub fn rotation_axis_angle(axis: &Vec3, angle: f32) -> Self {
+        let a = axis.normalize();
+        let (s, c) = angle.sin_cos();
+        let t = 1f32 - c;
+        Self([
+            [t * a.0 * a.0 + c, t * a.0 * a.1 + s * a.2, t * a.0 * a.2 - s * a.1],
+            [t * a.0 * a.1 - s * a.2, t * a.1 * a.1 + c, t * a.1 * a.2 + s * a.0],
+            [t * a.0 * a.2 + s * a.1, t * a.1 * a.2 - s * a.0, t * a.2 * a.2 + c],
+        ])
+    }
+    // rotates around x first, then y, then z: Rz * Ry * Rx
+    pub fn rotation_euler(x: f32, y: f32, z: f32) -> Self {
+        Self::rotation_z(z) * Self::rotation_y(y) * Self::rotation_x(x)
+    }
+    pub fn scale(v: &Vec3) -> Self {
+        Self([[v.0, 0f32, 0f32], [0f32, v.1, 0f32], [0f32, 0f32, v.2]])
+    }
+    // upper-left 3x3 block
+    pub fn from_mat4(m: &Mat4) -> Self {
+        let c = &m.0;
+        Self([
+            [c[0][0], c[0][1], c[0][2]],
+            [c[1][0], c[1][1], c[1][2]],
+            [c[2][0], c[2][1], c[2][2]],
+        ])
+    }
+    // inverse transpose, keeps normals perpendicular under non-uniform scale
+    pub fn normal_matrix(m: &Mat4) -> Option<Self> {
+        Self::from_mat4(m).inverse().map(|inv| inv.transpose())
+    }
+    pub fn transform(&self, v: &Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3(
+            m[0][0] * v.0 + m[1][0] * v.1 + m[2][0] * v.2,
+            m[0][1] * v.0 + m[1][1] * v.1 + m[2][1] * v.2,
+            m[0][2] * v.0 + m[1][2] * v.1 + m[2][2] * v.2
+        )
+    }
+}
+
+impl Mat4 {
+    pub fn from_mat3(m: &Mat3) -> Self {
+        let c = &m.0;
+        Self([
+            [c[0][0], c[0][1], c[0][2], 0f32],
+            [c[1][0], c[1][1], c[1][2], 0f32],
+            [c[2][0], c[2][1], c[2][2], 0f32],
+            [0f32, 0f32, 0f32, 1f32],
+        ])
+    }
+    pub fn translation(v: &Vec3) -> Self {
+        let mut m = Self::identity();
+        m.0[3] = [v.0, v.1, v.2, 1f32];
+        m
+    }
+    pub fn scale(v: &Vec3) -> Self {
+        Self::from_mat3(&Mat3::scale(v))
+    }
+    pub fn rotation_x(angle: f32) -> Self {
+        Self::from_mat3(&Mat3::rotation_x(angle))
+    }
+    pub fn rotation_y(angle: f32) -> Self {
+        Self::from_mat3(&Mat3::rotation_y(angle))
+    }
+    pub fn rotation_z(angle: f32) -> Self {
+        Self::from_mat3(&Mat3::rotation_z(angle))
+    }
+    pub fn rotation_axis_angle(axis: &Vec3, angle: f32) -> Self {
+        Self::from_mat3(&Mat3::rotation_axis_angle(axis, angle))
+    }
+    pub fn rotation_euler(x: f32, y: f32, z: f32) -> Self {
+        Self::from_mat3(&Mat3::rotation_euler(x, y, z))
+    }
+    // fov_y in radians, aspect is width / height
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1f32 / (fov_y / 2f32).tan();
+        let mut m = Self::zero();
+        m.0[0][0] = f / aspect;
+        m.0[1][1] = f;
+        m.0[2][2] = (far + near) / (near - far);
+        m.0[2][3] = -1f32;
+        m.0[3][2] = 2f32 * far * near / (near - far);
+        m
+    }
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        let mut m = Self::identity();
+        m.0[0][0] = 2f32 / (right - left);
+        m.0[1][1] = 2f32 / (top - bottom);
+        m.0[2][2] = -2f32 / (far - near);
+        m.0[3][0] = -(right + left) / (right - left);
+        m.0[3][1] = -(top + bottom) / (top - bottom);
+        m.0[3][2] = -(far + near) / (far - near);
+        m
+    }
+    // view matrix, the camera looks down its -z
+    pub fn look_at(eye: &Vec3, target: &Vec3, up: &Vec3) -> Self {
+        let f = (*target - *eye).normalize();
+        let s = f.cross(up).normalize();
+        let u = s.cross(&f);
+        Self([
+            [s.0, u.0, -f.0, 0f32],
+            [s.1, u.1, -f.1, 0f32],
+            [s.2, u.2, -f.2, 0f32],
+            [-s.dot(eye), -u.dot(eye), f.dot(eye), 1f32],
+        ])
+    }
+    pub fn transform(&self, v: &Vec4) -> Vec4 {
+        let m = &self.0;
+        Vec4(
+            m[0][0] * v.0 + m[1][0] * v.1 + m[2][0] * v.2 + m[3][0] * v.3,
+            m[0][1] * v.0 + m[1][1] * v.1 + m[2][1] * v.2 + m[3][1] * v.3,
+            m[0][2] * v.0 + m[1][2] * v.1 + m[2][2] * v.2 + m[3][2] * v.3,
+            m[0][3] * v.0 + m[1][3] * v.1 + m[2][3] * v.2 + m[3][3] * v.3
+        )
+    }
+    // w = 1, followed by the perspective divide
+    pub fn transform_point(&self, v: &Vec3) -> Vec3 {
+        self.transform(&v.extend(1f32)).project()
+    }
+    // w = 0, translation is ignored
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        Mat3::from_mat4(self).transform(v)
+    }
+}
+
+impl Mul<Vec2> for Mat2 {
+    type Output = Vec2;
+    fn mul(self, rhs: Vec2) -> Vec2 {
+        self.transform(&rhs)
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        self.transform(&rhs)
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+    fn mul(self, rhs: Vec4) -> Vec4 {
+        self.transform(&rhs)
+    }
+}
+
+impl Mul<&Vec2> for &Mat2 {
+    type Output = Vec2;
+    fn mul(self, rhs: &Vec2) -> Vec2 {
+        self.transform(rhs)
+    }
+}
+
+impl Mul<&Vec3> for &Mat3 {
+    type Output = Vec3;
+    fn mul(self, rhs: &Vec3) -> Vec3 {
+        self.transform(rhs)
+    }
+}
+
+impl Mul<&Vec4> for &Mat4 {
+    type Output = Vec4;
+    fn mul(self, rhs: &Vec4) -> Vec4 {
+        self.transform(rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close<const N: usize>(a: &[[f32; N]; N], b: &[[f32; N]; N], tolerance: f32) -> bool {
+        a.iter().flatten().zip(b.iter().flatten()).all(|(x, y)| (x - y).abs() <= tolerance)
+    }
+
+    fn close_vec(a: &Vec3, b: &Vec3) -> bool {
+        a.distance(b) < 1e-5
+    }
+
+    #[test]
+    fn determinant_of_known_matrices() {
+        assert_eq!(Mat3::identity().determinant(), 1f32);
+        assert_eq!(Mat2::from_rows([[3f32, 8f32], [4f32, 6f32]]).determinant(), -14f32);
+        let m = Mat3::from_rows([[6f32, 1f32, 1f32], [4f32, -2f32, 5f32], [2f32, 8f32, 7f32]]);
+        assert!((m.determinant() + 306f32).abs() < 1e-3);
+        assert!((Mat4::scale(&Vec3::new(2f32, 3f32, 4f32)).determinant() - 24f32).abs() < 1e-5);
+        assert!((Mat4::rotation_euler(0.3, -1.1, 2f32).determinant() - 1f32).abs() < 1e-5);
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let m = Mat4::translation(&Vec3::new(1f32, -2f32, 3f32))
+            * Mat4::rotation_axis_angle(&Vec3::new(1f32, 1f32, 0f32), 0.7)
+            * Mat4::scale(&Vec3::new(2f32, 0.5, 3f32));
+        let inverse = m.inverse().unwrap();
+        assert!(close(&(inverse * m).0, &Mat4::identity().0, 1e-5));
+        assert!(close(&(m * inverse).0, &Mat4::identity().0, 1e-5));
+        let m = Mat3::from_rows([[0f32, 2f32, 1f32], [1f32, 0f32, 0f32], [3f32, 1f32, 2f32]]);
+        assert!(close(&(m.inverse().unwrap() * m).0, &Mat3::identity().0, 1e-5));
+    }
+
+    #[test]
+    fn inverse_of_small_scale_matrices() {
+        // the tolerance follows the size of the entries
+        let m = Mat4::scale(&Vec3::new(1e-3, 1e-3, 1e-3));
+        let inverse = m.inverse().unwrap();
+        assert!(close(&inverse.0, &Mat4::scale(&Vec3::new(1e3, 1e3, 1e3)).0, 1e-1));
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        let m = Mat3::from_cols([[1f32, 4f32, 7f32], [2f32, 5f32, 8f32], [3f32, 6f32, 9f32]]);
+        assert_eq!(m.determinant(), 0f32);
+        assert_eq!(m.inverse(), None);
+        assert_eq!(Mat4::zero().inverse(), None);
+        assert_eq!(Mat4::scale(&Vec3::new(1f32, 0f32, 1f32)).inverse(), None);
+        assert_eq!(Mat2::from_rows([[1f32, 2f32], [2f32, 4f32]]).inverse(), None);
+        assert_eq!(Mat3::normal_matrix(&Mat4::zero()), None);
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_planes() {
+        let m = Mat4::perspective(std::f32::consts::FRAC_PI_2, 2f32, 1f32, 10f32);
+        assert!(close_vec(&m.transform_point(&Vec3::new(0f32, 0f32, -1f32)), &Vec3::new(0f32, 0f32, -1f32)));
+        assert!(close_vec(&m.transform_point(&Vec3::new(0f32, 0f32, -10f32)), &Vec3::new(0f32, 0f32, 1f32)));
+        // 90 degrees vertically: the top edge of the near plane is at y = near
+        assert!(close_vec(&m.transform_point(&Vec3::new(2f32, 1f32, -1f32)), &Vec3::new(1f32, 1f32, -1f32)));
+        let clip = m.transform(&Vec4::new(0f32, 0f32, -5f32, 1f32));
+        assert_eq!(clip.get_w(), 5f32);
+    }
+
+    #[test]
+    fn orthographic_maps_the_box_to_the_cube() {
+        let m = Mat4::orthographic(-2f32, 2f32, -1f32, 3f32, 1f32, 5f32);
+        assert!(close_vec(&m.transform_point(&Vec3::new(-2f32, -1f32, -1f32)), &Vec3::new(-1f32, -1f32, -1f32)));
+        assert!(close_vec(&m.transform_point(&Vec3::new(2f32, 3f32, -5f32)), &Vec3::new(1f32, 1f32, 1f32)));
+    }
+
+    #[test]
+    fn look_at_puts_the_target_down_negative_z() {
+        let eye = Vec3::new(3f32, 4f32, 5f32);
+        let target = Vec3::new(3f32, 4f32, -5f32);
+        let view = Mat4::look_at(&eye, &target, &Vec3::new(0f32, 1f32, 0f32));
+        assert!(close_vec(&view.transform_point(&eye), &Vec3::zero()));
+        assert!(close_vec(&view.transform_point(&target), &Vec3::new(0f32, 0f32, -10f32)));
+        assert!(close_vec(&view.transform_vector(&Vec3::new(0f32, 1f32, 0f32)), &Vec3::new(0f32, 1f32, 0f32)));
+        let view = Mat4::look_at(&Vec3::new(10f32, 0f32, 0f32), &Vec3::zero(), &Vec3::new(0f32, 1f32, 0f32));
+        assert!(close_vec(&view.transform_point(&Vec3::new(0f32, 0f32, -1f32)), &Vec3::new(1f32, 0f32, -10f32)));
+    }
+
+    #[test]
+    fn rotations_and_transforms() {
+        let v = Mat3::rotation_z(std::f32::consts::FRAC_PI_2) * Vec3::new(1f32, 0f32, 0f32);
+        assert!(close_vec(&v, &Vec3::new(0f32, 1f32, 0f32)));
+        let axis = Mat3::rotation_axis_angle(&Vec3::new(0f32, 0f32, 2f32), 0.4);
+        assert!(close(&axis.0, &Mat3::rotation_z(0.4).0, 1e-6));
+        let m = Mat4::translation(&Vec3::new(1f32, 2f32, 3f32));
+        assert_eq!(m.transform_point(&Vec3::zero()), Vec3::new(1f32, 2f32, 3f32));
+        assert_eq!(m.transform_vector(&Vec3::new(1f32, 0f32, 0f32)), Vec3::new(1f32, 0f32, 0f32));
+        assert_eq!(Mat2::from_rows([[1f32, 2f32], [3f32, 4f32]]).get(0, 1), 2f32);
+    }
+
+}
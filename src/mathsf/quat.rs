@@ -0,0 +1,267 @@
+use std::ops::{Mul, MulAssign, Neg};
+
+use super::{Mat3, Mat4, Vec3};
+
+// Rotation quaternion (x, y, z, w), w being the scalar part. Composition follows
+// the matrices: `a * b` rotates by b first, then by a.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Quat(f32, f32, f32, f32);
+
+impl Quat {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self (x, y, z, w)
+    }
+    pub fn identity() -> Self {
+        Self (0f32, 0f32, 0f32, 1f32)
+    }
+    // the axis doesn't need to be normalized, angle in radians
+    pub fn from_axis_angle(axis: &Vec3, angle: f32) -> Self {
+        let length = axis.length();
+        if length == 0f32 {
+            return Self::identity();
+        }
+        let (s, c) = (angle / 2f32).sin_cos();
+        let f = s / length;
+        Self (axis.0 * f, axis.1 * f, axis.2 * f, c)
+    }
+    // same convention as Mat3::rotation_euler: x first, then y, then z
+    pub fn from_euler(x: f32, y: f32, z: f32) -> Self {
+        let qx = Self::from_axis_angle(&Vec3::new(1f32, 0f32, 0f32), x);
+        let qy = Self::from_axis_angle(&Vec3::new(0f32, 1f32, 0f32), y);
+        let qz = Self::from_axis_angle(&Vec3::new(0f32, 0f32, 1f32), z);
+        qz * qy * qx
+    }
+    // the matrix must be a pure rotation
+    pub fn from_mat3(m: &Mat3) -> Self {
+        let e = |r: usize, c: usize| m.get(r, c);
+        let trace = e(0, 0) + e(1, 1) + e(2, 2);
+        let q = if trace > 0f32 {
+            let s = (trace + 1f32).sqrt() * 2f32;
+            Self ((e(2, 1) - e(1, 2)) / s, (e(0, 2) - e(2, 0)) / s, (e(1, 0) - e(0, 1)) / s, s / 4f32)
+        } else if e(0, 0) > e(1, 1) && e(0, 0) > e(2, 2) {
+            let s = (1f32 + e(0, 0) - e(1, 1) - e(2, 2)).sqrt() * 2f32;
+            Self (s / 4f32, (e(0, 1) + e(1, 0)) / s, (e(0, 2) + e(2, 0)) / s, (e(2, 1) - e(1, 2)) / s)
+        } else if e(1, 1) > e(2, 2) {
+            let s = (1f32 + e(1, 1) - e(0, 0) - e(2, 2)).sqrt() * 2f32;
+            Self ((e(0, 1) + e(1, 0)) / s, s / 4f32, (e(1, 2) + e(2, 1)) / s, (e(0, 2) - e(2, 0)) / s)
+        } else {
+            let s = (1f32 + e(2, 2) - e(0, 0) - e(1, 1)).sqrt() * 2f32;
+            Self ((e(0, 2) + e(2, 0)) / s, (e(1, 2) + e(2, 1)) / s, s / 4f32, (e(1, 0) - e(0, 1)) / s)
+        };
+        q.normalize()
+    }
+    pub fn from_mat4(m: &Mat4) -> Self {
+        Self::from_mat3(&Mat3::from_mat4(m))
+    }
+    pub fn get_x(&self) -> f32 {
+        self.0
+    }
+    pub fn get_y(&self) -> f32 {
+        self.1
+    }
+    pub fn get_z(&self) -> f32 {
+        self.2
+    }
+    pub fn get_w(&self) -> f32 {
+        self.3
+    }
+    pub fn multiplication(a: &Quat, b: &Quat) -> Self {
+        Self (
+            a.3 * b.0 + a.0 * b.3 + a.1 * b.2 - a.2 * b.1,
+            a.3 * b.1 - a.0 * b.2 + a.1 * b.3 + a.2 * b.0,
+            a.3 * b.2 + a.0 * b.1 - a.1 * b.0 + a.2 * b.3,
+            a.3 * b.3 - a.0 * b.0 - a.1 * b.1 - a.2 * b.2
+        )
+    }
+    pub fn dot(a: &Quat, b: &Quat) -> f32 {
+        a.0 * b.0 + a.1 * b.1 + a.2 * b.2 + a.3 * b.3
+    }
+    pub fn length(&self) -> f32 {
+        Self::dot(self, self).sqrt()
+    }
+    // identity for a zero quaternion
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        if length == 0f32 {
+            return Self::identity();
+        }
+        Self (self.0 / length, self.1 / length, self.2 / length, self.3 / length)
+    }
+    pub fn conjugate(&self) -> Self {
+        Self (-self.0, -self.1, -self.2, self.3)
+    }
+    // same as the conjugate for unit quaternions
+    pub fn inverse(&self) -> Option<Self> {
+        let length2 = Self::dot(self, self);
+        if length2 == 0f32 {
+            return None;
+        }
+        Some(Self (-self.0 / length2, -self.1 / length2, -self.2 / length2, self.3 / length2))
+    }
+    // (axis, angle in radians), the axis is x when there is no rotation
+    pub fn to_axis_angle(&self) -> (Vec3, f32) {
+        let q = self.normalize();
+        let s = (1f32 - q.3 * q.3).max(0f32).sqrt();
+        if s < 1e-6 {
+            return (Vec3::new(1f32, 0f32, 0f32), 0f32);
+        }
+        (Vec3::new(q.0 / s, q.1 / s, q.2 / s), 2f32 * q.3.clamp(-1f32, 1f32).acos())
+    }
+    // expects a unit quaternion
+    pub fn rotate(&self, v: &Vec3) -> Vec3 {
+        // v + w t + q x t with t = 2 q x v
+        let (x, y, z, w) = (self.0, self.1, self.2, self.3);
+        let t = (2f32 * (y * v.2 - z * v.1), 2f32 * (z * v.0 - x * v.2), 2f32 * (x * v.1 - y * v.0));
+        Vec3::new(
+            v.0 + w * t.0 + (y * t.2 - z * t.1),
+            v.1 + w * t.1 + (z * t.0 - x * t.2),
+            v.2 + w * t.2 + (x * t.1 - y * t.0)
+        )
+    }
+    // normalized lerp along the shorter arc, cheap but not constant speed
+    pub fn nlerp(q0: &Quat, q1: &Quat, f: f32) -> Self {
+        let q1 = if Self::dot(q0, q1) < 0f32 { -*q1 } else { *q1 };
+        Self (
+            q0.0 + (q1.0 - q0.0) * f,
+            q0.1 + (q1.1 - q0.1) * f,
+            q0.2 + (q1.2 - q0.2) * f,
+            q0.3 + (q1.3 - q0.3) * f
+        ).normalize()
+    }
+    // constant angular speed along the shorter arc
+    pub fn slerp(q0: &Quat, q1: &Quat, f: f32) -> Self {
+        let mut cos = Self::dot(q0, q1);
+        let q1 = if cos < 0f32 {
+            cos = -cos;
+            -*q1
+        } else {
+            *q1
+        };
+        // nearly parallel, sin(theta) would blow up
+        if cos > 0.9995 {
+            return Self::nlerp(q0, &q1, f);
+        }
+        let theta = cos.acos();
+        let sin = theta.sin();
+        let (a, b) = (((1f32 - f) * theta).sin() / sin, (f * theta).sin() / sin);
+        Self (a * q0.0 + b * q1.0, a * q0.1 + b * q1.1, a * q0.2 + b * q1.2, a * q0.3 + b * q1.3)
+    }
+    pub fn to_mat3(&self) -> Mat3 {
+        let q = self.normalize();
+        let (x, y, z, w) = (q.0, q.1, q.2, q.3);
+        Mat3::from_cols([
+            [1f32 - 2f32 * (y * y + z * z), 2f32 * (x * y + z * w), 2f32 * (x * z - y * w)],
+            [2f32 * (x * y - z * w), 1f32 - 2f32 * (x * x + z * z), 2f32 * (y * z + x * w)],
+            [2f32 * (x * z + y * w), 2f32 * (y * z - x * w), 1f32 - 2f32 * (x * x + y * y)],
+        ])
+    }
+    pub fn to_mat4(&self) -> Mat4 {
+        Mat4::from_mat3(&self.to_mat3())
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+    fn mul(self, rhs: Quat) -> Quat {
+        Self::multiplication(&self, &rhs)
+    }
+}
+
+impl MulAssign for Quat {
+    fn mul_assign(&mut self, rhs: Quat) {
+        *self = Self::multiplication(self, &rhs);
+    }
+}
+
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        self.rotate(&rhs)
+    }
+}
+
+impl Mul<&Vec3> for &Quat {
+    type Output = Vec3;
+    fn mul(self, rhs: &Vec3) -> Vec3 {
+        self.rotate(rhs)
+    }
+}
+
+// same rotation as the original
+impl Neg for Quat {
+    type Output = Quat;
+    fn neg(self) -> Quat {
+        Self (-self.0, -self.1, -self.2, -self.3)
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn close_vec(a: &Vec3, b: &Vec3) -> bool {
+        a.distance(b) < 1e-5
+    }
+
+    // q and -q are the same rotation
+    fn same_rotation(a: &Quat, b: &Quat) -> bool {
+        (Quat::dot(a, b).abs() - 1f32).abs() < 1e-5
+    }
+
+    #[test]
+    fn rotates_like_the_matrices() {
+        let q = Quat::from_axis_angle(&Vec3::new(0f32, 0f32, 3f32), FRAC_PI_2);
+        assert!(close_vec(&(q * Vec3::new(1f32, 0f32, 0f32)), &Vec3::new(0f32, 1f32, 0f32)));
+        let (x, y, z) = (0.3, -1.2, 2.5);
+        let q = Quat::from_euler(x, y, z);
+        let m = Mat3::rotation_euler(x, y, z);
+        let v = Vec3::new(1f32, -2f32, 0.5);
+        assert!(close_vec(&q.rotate(&v), &m.transform(&v)));
+        assert!(close_vec(&q.to_mat3().transform(&v), &m.transform(&v)));
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        for (x, y, z) in [(0.1, 0.2, 0.3), (3.0, 0.1, -0.2), (0.0, 3.1, 0.0), (-1.0, 0.0, 3.1)] {
+            let q = Quat::from_euler(x, y, z);
+            assert!(same_rotation(&Quat::from_mat3(&q.to_mat3()), &q));
+            assert!(same_rotation(&Quat::from_mat4(&q.to_mat4()), &q));
+        }
+    }
+
+    #[test]
+    fn composition_inverse_and_axis_angle() {
+        let a = Quat::from_axis_angle(&Vec3::new(1f32, 0f32, 0f32), 0.5);
+        let b = Quat::from_axis_angle(&Vec3::new(0f32, 1f32, 0f32), 1.1);
+        let v = Vec3::new(0.3, 0.7, -2f32);
+        assert!(close_vec(&(a * b).rotate(&v), &a.rotate(&b.rotate(&v))));
+        assert!(same_rotation(&(a * a.inverse().unwrap()), &Quat::identity()));
+        assert_eq!(Quat::new(0f32, 0f32, 0f32, 0f32).inverse(), None);
+        let (axis, angle) = b.to_axis_angle();
+        assert!(close_vec(&axis, &Vec3::new(0f32, 1f32, 0f32)));
+        assert!((angle - 1.1).abs() < 1e-5);
+        assert_eq!(Quat::identity().to_axis_angle(), (Vec3::new(1f32, 0f32, 0f32), 0f32));
+    }
+
+    #[test]
+    fn slerp_is_constant_speed_on_the_short_arc() {
+        let axis = Vec3::new(0f32, 0f32, 1f32);
+        let (q0, q1) = (Quat::identity(), Quat::from_axis_angle(&axis, 2f32));
+        for f in [0f32, 0.25, 0.5, 1f32] {
+            assert!(same_rotation(&Quat::slerp(&q0, &q1, f), &Quat::from_axis_angle(&axis, 2f32 * f)));
+        }
+        // -q1 is the same target, interpolation must not take the long way
+        let half = Quat::slerp(&q0, &-q1, 0.5);
+        assert!(same_rotation(&half, &Quat::from_axis_angle(&axis, 1f32)));
+        assert!(same_rotation(&Quat::nlerp(&q0, &q1, 0.5), &Quat::from_axis_angle(&axis, 1f32)));
+    }
+
+}
@@ -0,0 +1,510 @@
+use std::collections::VecDeque;
+
+use super::{
+    canvas::{Canvas, Color},
+    clip::ClipVertex,
+    interpolate::Interpolate,
+    mathsf::{Aabb, Vec2, Vec3, Vec4},
+    pipeline::{FragmentShader, Pipeline, VertexShader}
+};
+
+// shaded vertices remembered per draw call, like the post-transform cache of a GPU
+pub const VERTEX_CACHE_SIZE: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Topology {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    // every triangle shares an edge with the previous one, winding kept consistent
+    TriangleStrip,
+    // every triangle shares the first vertex
+    TriangleFan,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+// what a vertex shader receives from a Mesh, missing attributes take the defaults
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MeshVertex {
+    pub position: Vec3,
+    // (0, 0, 1) by default
+    pub normal: Vec3,
+    pub uv: Vec2,
+    // white by default
+    pub color: Color,
+    // xyz along +u, w = +-1 the handedness of the bitangent; (1, 0, 0, 1) by default
+    pub tangent: Vec4,
+}
+
+// one buffer per attribute, empty when absent, every other buffer as long as `positions`
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    colors: Vec<Color>,
+    tangents: Vec<Vec4>,
+    indices: Option<Indices>,
+    topology: Topology,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct DrawStats {
+    pub primitives: usize,
+    // vertex shader invocations
+    pub shaded: usize,
+    pub cache_hits: usize,
+}
+
+struct VertexCache<V> {
+    entries: VecDeque<(u32, ClipVertex<V>)>,
+    capacity: usize,
+}
+
+impl Topology {
+
+    // calls `primitive` with the positions (into the index stream) of every point, line or triangle
+    pub fn assemble(&self, count: usize, mut primitive: impl FnMut(&[usize])) {
+        match self {
+            Topology::PointList => (0..count).for_each(|i| primitive(&[i])),
+            Topology::LineList => (0..count / 2).for_each(|i| primitive(&[2 * i, 2 * i + 1])),
+            Topology::LineStrip => (1..count).for_each(|i| primitive(&[i - 1, i])),
+            Topology::TriangleList => (0..count / 3).for_each(|i| primitive(&[3 * i, 3 * i + 1, 3 * i + 2])),
+            Topology::TriangleStrip => (2..count).for_each(|i| {
+                if i % 2 == 0 { primitive(&[i - 2, i - 1, i]) } else { primitive(&[i - 1, i - 2, i]) }
+            }),
+            Topology::TriangleFan => (2..count).for_each(|i| primitive(&[0, i - 1, i])),
+        }
+    }
+
+    pub fn is_triangles(&self) -> bool {
+        matches!(self, Topology::TriangleList | Topology::TriangleStrip | Topology::TriangleFan)
+    }
+
+}
+
+impl Indices {
+
+    // 16-bit when every index fits
+    pub fn from_u32(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|i| *i <= u16::MAX as u32) {
+            Indices::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, i: usize) -> u32 {
+        match self {
+            Indices::U16(indices) => indices[i] as u32,
+            Indices::U32(indices) => indices[i],
+        }
+    }
+
+    pub fn max(&self) -> Option<u32> {
+        (0..self.len()).map(|i| self.get(i)).max()
+    }
+
+}
+
+impl<V: Clone> VertexCache<V> {
+
+    fn new(capacity: usize) -> Self {
+        VertexCache { entries: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    // FIFO replacement, as most hardware caches did
+    fn get_or_shade(&mut self, index: u32, stats: &mut DrawStats, shade: impl FnOnce() -> ClipVertex<V>) -> ClipVertex<V> {
+        if let Some((_, vertex)) = self.entries.iter().find(|(i, _)| *i == index) {
+            stats.cache_hits += 1;
+            return vertex.clone();
+        }
+        stats.shaded += 1;
+        let vertex = shade();
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((index, vertex.clone()));
+        vertex
+    }
+
+}
+
+impl Pipeline {
+
+    // draws `topology` primitives from `vertices` picked by `indices`
+    pub fn draw_indexed<VS, FS>(
+        &self,
+        canvas: &mut Canvas,
+        vertex_shader: &VS,
+        fragment_shader: &FS,
+        vertices: &[VS::Vertex],
+        indices: &Indices,
+        topology: Topology
+    ) -> DrawStats
+    where
+        VS: VertexShader,
+        FS: FragmentShader<VS::Varying>
+    {
+        self.draw_primitives(
+            canvas, fragment_shader, topology,
+            indices.len(), |i| indices.get(i), |index| vertex_shader.shade(&vertices[index as usize])
+        )
+    }
+
+    // `shade` runs the vertex shader on the vertex at a buffer index
+    fn draw_primitives<V, FS>(
+        &self,
+        canvas: &mut Canvas,
+        fragment_shader: &FS,
+        topology: Topology,
+        count: usize,
+        index: impl Fn(usize) -> u32,
+        shade: impl Fn(u32) -> ClipVertex<V>
+    ) -> DrawStats
+    where
+        V: Interpolate,
+        FS: FragmentShader<V>
+    {
+        let mut stats = DrawStats::default();
+        let mut cache = VertexCache::new(VERTEX_CACHE_SIZE);
+        topology.assemble(count, |primitive| {
+            stats.primitives += 1;
+            let mut shaded = primitive.iter().map(|position| {
+                let i = index(*position);
+                cache.get_or_shade(i, &mut stats, || shade(i))
+            });
+            // `assemble` hands out exactly as many positions as the primitive has vertices
+            match primitive.len() {
+                1 => self.draw_point(canvas, fragment_shader, shaded.next().unwrap()),
+                2 => self.draw_line(canvas, fragment_shader, [shaded.next().unwrap(), shaded.next().unwrap()]),
+                _ => self.draw_triangle(
+                    canvas, fragment_shader,
+                    [shaded.next().unwrap(), shaded.next().unwrap(), shaded.next().unwrap()]
+                ),
+            }
+        });
+        stats
+    }
+
+}
+
+impl Mesh {
+
+    pub fn new(positions: Vec<Vec3>) -> Self {
+        Mesh {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            tangents: Vec::new(),
+            indices: None,
+            topology: Topology::TriangleList,
+        }
+    }
+
+    fn check_length(&self, len: usize, attribute: &str) {
+        assert!(
+            len == 0 || len == self.positions.len(),
+            "Mesh {} count does not match the position count.", attribute
+        );
+    }
+
+    pub fn set_normals(&mut self, normals: Vec<Vec3>) -> &mut Self {
+        self.check_length(normals.len(), "normal");
+        self.normals = normals;
+        self
+    }
+
+    pub fn set_uvs(&mut self, uvs: Vec<Vec2>) -> &mut Self {
+        self.check_length(uvs.len(), "uv");
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn set_colors(&mut self, colors: Vec<Color>) -> &mut Self {
+        self.check_length(colors.len(), "color");
+        self.colors = colors;
+        self
+    }
+
+    pub fn set_tangents(&mut self, tangents: Vec<Vec4>) -> &mut Self {
+        self.check_length(tangents.len(), "tangent");
+        self.tangents = tangents;
+        self
+    }
+
+    // None draws the vertices in order
+    pub fn set_indices(&mut self, indices: Option<Indices>) -> &mut Self {
+        if let Some(max) = indices.as_ref().and_then(|indices| indices.max()) {
+            assert!((max as usize) < self.positions.len(), "Mesh index {} out of range.", max);
+        }
+        self.indices = indices;
+        self
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) -> &mut Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn get_positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    pub fn get_normals(&self) -> &[Vec3] {
+        &self.normals
+    }
+
+    pub fn get_uvs(&self) -> &[Vec2] {
+        &self.uvs
+    }
+
+    pub fn get_colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn get_tangents(&self) -> &[Vec4] {
+        &self.tangents
+    }
+
+    pub fn get_indices(&self) -> Option<&Indices> {
+        self.indices.as_ref()
+    }
+
+    pub fn get_topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    // length of the index stream, or of the vertex buffers when not indexed
+    pub fn element_count(&self) -> usize {
+        self.indices.as_ref().map_or(self.positions.len(), |indices| indices.len())
+    }
+
+    pub fn element(&self, i: usize) -> u32 {
+        self.indices.as_ref().map_or(i as u32, |indices| indices.get(i))
+    }
+
+    pub fn vertex(&self, index: u32) -> MeshVertex {
+        let i = index as usize;
+        MeshVertex {
+            position: self.positions[i],
+            normal: self.normals.get(i).copied().unwrap_or(Vec3::new(0f32, 0f32, 1f32)),
+            uv: self.uvs.get(i).copied().unwrap_or_default(),
+            color: self.colors.get(i).copied().unwrap_or(Color::white()),
+            tangent: self.tangents.get(i).copied().unwrap_or(Vec4::new(1f32, 0f32, 0f32, 1f32)),
+        }
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.positions)
+    }
+
+    // smooth normals, area weighted over the triangles sharing each vertex
+    pub fn compute_normals(&mut self) -> &mut Self {
+        assert!(self.topology.is_triangles(), "Normals can only be computed for triangles.");
+        let mut normals = vec![Vec3::zero(); self.positions.len()];
+        self.topology.assemble(self.element_count(), |triangle| {
+            let [a, b, c] = [0, 1, 2].map(|k| self.element(triangle[k]) as usize);
+            let (pa, pb, pc) = (self.positions[a], self.positions[b], self.positions[c]);
+            // the cross product length is twice the area
+            let n = (pb - pa).cross(&(pc - pa));
+            for i in [a, b, c] {
+                normals[i] += n;
+            }
+        });
+        self.normals = normals.iter().map(|n| n.normalize()).collect();
+        self
+    }
+
+    pub fn draw<VS, FS>(&self, pipeline: &Pipeline, canvas: &mut Canvas, vertex_shader: &VS, fragment_shader: &FS) -> DrawStats
+    where
+        VS: VertexShader<Vertex = MeshVertex>,
+        FS: FragmentShader<VS::Varying>
+    {
+        pipeline.draw_primitives(
+            canvas, fragment_shader, self.topology,
+            self.element_count(), |i| self.element(i), |index| vertex_shader.shade(&self.vertex(index))
+        )
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    struct Flat;
+
+    impl VertexShader for Flat {
+        type Vertex = MeshVertex;
+        type Varying = ();
+        fn shade(&self, vertex: &MeshVertex) -> (Vec4, ()) {
+            let p = vertex.position;
+            (Vec4::new(p.get_x(), p.get_y(), p.get_z(), 1f32), ())
+        }
+    }
+
+    impl FragmentShader<()> for Flat {
+        fn shade(&self, _: &(), _: &crate::pipeline::Fragment) -> Option<Color> {
+            Some(Color::black())
+        }
+    }
+
+    fn primitives(topology: Topology, count: usize) -> Vec<Vec<usize>> {
+        let mut primitives = Vec::new();
+        topology.assemble(count, |p| primitives.push(p.to_vec()));
+        primitives
+    }
+
+    #[test]
+    fn assembly() {
+        assert_eq!(primitives(Topology::PointList, 3), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(primitives(Topology::LineList, 5), vec![vec![0, 1], vec![2, 3]]);
+        assert_eq!(primitives(Topology::LineStrip, 3), vec![vec![0, 1], vec![1, 2]]);
+        assert_eq!(primitives(Topology::TriangleList, 7), vec![vec![0, 1, 2], vec![3, 4, 5]]);
+        assert_eq!(primitives(Topology::TriangleFan, 5), vec![vec![0, 1, 2], vec![0, 2, 3], vec![0, 3, 4]]);
+        assert_eq!(primitives(Topology::TriangleStrip, 5), vec![vec![0, 1, 2], vec![2, 1, 3], vec![2, 3, 4]]);
+        for topology in [Topology::LineStrip, Topology::TriangleList, Topology::TriangleStrip, Topology::TriangleFan] {
+            assert!(primitives(topology, 1).is_empty());
+            assert!(primitives(topology, 0).is_empty());
+        }
+        assert!(Topology::TriangleFan.is_triangles() && !Topology::LineStrip.is_triangles());
+    }
+
+    #[test]
+    fn strip_keeps_the_winding() {
+        // a zigzag, every triangle counter-clockwise in the xy plane once assembled
+        let positions: Vec<Vec3> = (0..8).map(|i| Vec3::new((i / 2) as f32, (i % 2) as f32, 0f32)).collect();
+        let mut mesh = Mesh::new(positions.clone());
+        mesh.set_topology(Topology::TriangleStrip);
+        for triangle in primitives(Topology::TriangleStrip, positions.len()) {
+            let [a, b, c] = [0, 1, 2].map(|k| positions[triangle[k]]);
+            assert!((b - a).cross(&(c - a)).get_z() < 0f32);
+        }
+        // so the computed normals agree everywhere
+        mesh.compute_normals();
+        assert!(mesh.get_normals().iter().all(|n| *n == Vec3::new(0f32, 0f32, -1f32)));
+    }
+
+    #[test]
+    fn narrowing_indices() {
+        let small = Indices::from_u32(vec![0, 7, 65535]);
+        assert_eq!(small, Indices::U16(vec![0, 7, 65535]));
+        assert_eq!((small.len(), small.get(1), small.max()), (3, 7, Some(65535)));
+        let large = Indices::from_u32(vec![3, 65536]);
+        assert_eq!(large, Indices::U32(vec![3, 65536]));
+        assert_eq!(large.max(), Some(65536));
+        let empty = Indices::from_u32(Vec::new());
+        assert!(empty.is_empty() && empty.max().is_none());
+    }
+
+    #[test]
+    fn fifo_vertex_cache() {
+        let mut cache = VertexCache::new(2);
+        let mut stats = DrawStats::default();
+        let shade = |i: u32| (Vec4::new(i as f32, 0f32, 0f32, 1f32), i);
+        for index in [0, 1, 0, 2, 0, 0] {
+            assert_eq!(cache.get_or_shade(index, &mut stats, || shade(index)).1, index);
+        }
+        // the hit on 0 doesn't refresh it, 2 evicts it anyway
+        assert_eq!((stats.shaded, stats.cache_hits), (4, 2));
+    }
+
+    #[test]
+    fn draw_stats() {
+        let positions = vec![
+            Vec3::new(-0.5, -0.5, 0f32), Vec3::new(0.5, -0.5, 0f32), Vec3::new(-0.5, 0.5, 0f32), Vec3::new(0.5, 0.5, 0f32)
+        ];
+        let mut mesh = Mesh::new(positions.clone());
+        mesh.set_indices(Some(Indices::from_u32(vec![0, 1, 2, 2, 1, 3])));
+        let mut canvas = Canvas::new(8, 8);
+        let stats = mesh.draw(&Pipeline::new(), &mut canvas, &Flat, &Flat);
+        assert_eq!(stats, DrawStats { primitives: 2, shaded: 4, cache_hits: 2 });
+        assert_eq!(canvas.get_pixel(4, 4), Color::black());
+
+        // index 0 again after more distinct vertices than the cache holds
+        let mut indices: Vec<u32> = (0..VERTEX_CACHE_SIZE as u32 + 1).collect();
+        indices.push(0);
+        indices.truncate(indices.len() / 3 * 3);
+        let mut mesh = Mesh::new(vec![Vec3::zero(); VERTEX_CACHE_SIZE + 1]);
+        mesh.set_indices(Some(Indices::from_u32(indices.clone())));
+        let stats = mesh.draw(&Pipeline::new(), &mut canvas, &Flat, &Flat);
+        assert_eq!((stats.shaded, stats.cache_hits), (indices.len(), 0));
+
+        // the same through draw_indexed, as lines
+        let vertices: Vec<MeshVertex> = (0..4).map(|i| mesh.vertex(i)).collect();
+        let stats = Pipeline::new().draw_indexed(&mut canvas, &Flat, &Flat, &vertices, &Indices::U16(vec![0, 1, 1, 2, 2, 0]), Topology::LineList);
+        assert_eq!(stats, DrawStats { primitives: 3, shaded: 3, cache_hits: 3 });
+    }
+
+    #[test]
+    fn area_weighted_normals() {
+        // a hinge: the larger triangle faces +z, the smaller one +x
+        let mut mesh = Mesh::new(vec![
+            Vec3::zero(), Vec3::new(0f32, 1f32, 0f32), Vec3::new(-2f32, 0f32, 0f32), Vec3::new(0f32, 0f32, 1f32)
+        ]);
+        mesh.set_indices(Some(Indices::from_u32(vec![0, 1, 2, 0, 1, 3])));
+        mesh.compute_normals();
+        let normals = mesh.get_normals();
+        assert_eq!(normals[2], Vec3::new(0f32, 0f32, 1f32));
+        assert_eq!(normals[3], Vec3::new(1f32, 0f32, 0f32));
+        // the shared edge weighs +z twice as much as +x
+        let expected = Vec3::new(1f32, 0f32, 2f32).normalize();
+        assert!((normals[0] - expected).length() < 1e-6 && (normals[1] - expected).length() < 1e-6);
+    }
+
+    #[test]
+    fn vertex_defaults_and_accessors() {
+        let mut mesh = Mesh::new(vec![Vec3::zero(), Vec3::new(1f32, 2f32, 3f32)]);
+        let vertex = mesh.vertex(1);
+        assert_eq!(vertex.position, Vec3::new(1f32, 2f32, 3f32));
+        assert_eq!(vertex.normal, Vec3::new(0f32, 0f32, 1f32));
+        assert_eq!(vertex.uv, Vec2::default());
+        assert_eq!(vertex.color, Color::white());
+        assert_eq!(vertex.tangent, Vec4::new(1f32, 0f32, 0f32, 1f32));
+        mesh.set_uvs(vec![Vec2::new(0.5, 0.5); 2]).set_colors(vec![Color::red(); 2]).set_topology(Topology::LineList);
+        assert_eq!(mesh.get_uvs().len(), 2);
+        assert_eq!(mesh.get_colors()[1], Color::red());
+        assert!(mesh.get_tangents().is_empty());
+        assert_eq!(mesh.get_positions().len(), mesh.vertex_count());
+        assert_eq!((mesh.element_count(), mesh.element(1)), (2, 1));
+        assert_eq!(mesh.get_topology(), Topology::LineList);
+        assert_eq!(mesh.bounds().unwrap().get_max(), Vec3::new(1f32, 2f32, 3f32));
+    }
+
+    #[test]
+    #[should_panic(expected = "Mesh index 2 out of range")]
+    fn index_out_of_range() {
+        Mesh::new(vec![Vec3::zero(); 2]).set_indices(Some(Indices::from_u32(vec![0, 1, 2])));
+    }
+
+    #[test]
+    #[should_panic(expected = "normal count does not match")]
+    fn attribute_length() {
+        Mesh::new(vec![Vec3::zero(); 3]).set_normals(vec![Vec3::zero(); 2]);
+    }
+
+}
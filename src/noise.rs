@@ -0,0 +1,515 @@
+// Seeded coherent noise. The same seed always gives the same field, lattice
+// features are one unit apart, so scale the input to change the frequency.
+// Perlin, simplex and value noise are in [-1, 1], Worley returns distances in
+// lattice units (mostly [0, 1] for F1).
+
+pub trait Noise {
+
+    // any dimension from 1 to 4
+    fn sample<const N: usize>(&self, p: [f32; N]) -> f32;
+
+    fn noise1(&self, x: f32) -> f32 {
+        self.sample([x])
+    }
+
+    fn noise2(&self, x: f32, y: f32) -> f32 {
+        self.sample([x, y])
+    }
+
+    fn noise3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.sample([x, y, z])
+    }
+
+    fn noise4(&self, x: f32, y: f32, z: f32, w: f32) -> f32 {
+        self.sample([x, y, z, w])
+    }
+
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Perlin {
+    seed: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Simplex {
+    seed: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Value {
+    seed: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Distance {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WorleyOutput {
+    // distance to the nearest feature point
+    F1,
+    // distance to the second nearest
+    F2,
+    // cell borders
+    F2MinusF1,
+}
+
+// cellular noise, one feature point per lattice cell
+#[derive(Clone, Copy, Debug)]
+pub struct Worley {
+    seed: u32,
+    // how far feature points may wander from the cell corner, 1 is fully random
+    jitter: f32,
+    distance: Distance,
+    output: WorleyOutput,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FractalKind {
+    // fractional Brownian motion, in [-1, 1]
+    Fbm,
+    // sharp creases where the noise crosses zero, in [0, 1]
+    Ridged,
+    // sum of absolute octaves, in [0, 1]
+    Turbulence,
+}
+
+// several octaves of a noise, each `lacunarity` times the frequency and `gain` times the amplitude
+#[derive(Clone, Debug)]
+pub struct Fractal<T: Noise> {
+    noise: T,
+    kind: FractalKind,
+    octaves: u32,
+    lacunarity: f32,
+    gain: f32,
+}
+
+// samples `noise` at p + amplitude * warp(p), every axis warped by a differently offset sample
+#[derive(Clone, Debug)]
+pub struct DomainWarp<T: Noise, W: Noise> {
+    noise: T,
+    warp: W,
+    amplitude: f32,
+}
+
+const PRIMES: [u32; 4] = [0x8da6b343, 0xd8163841, 0xcb1ab31f, 0x165667b1];
+
+// murmur3-style hash of a lattice cell
+fn hash<const N: usize>(seed: u32, cell: &[i32; N]) -> u32 {
+    let mut h = seed.wrapping_mul(0x9e3779b9) ^ 0x85ebca6b;
+    for (c, prime) in cell.iter().zip(PRIMES.iter()) {
+        h ^= (*c as u32).wrapping_mul(*prime);
+        h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+    }
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^ (h >> 16)
+}
+
+fn unit(h: u32) -> f32 {
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6f32 - 15f32) + 10f32)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// dot product of a hashed gradient with the offset from its lattice point
+fn gradient<const N: usize>(h: u32, d: &[f32; N]) -> f32 {
+    match N {
+        1 => {
+            let g = 1f32 + (h & 7) as f32;
+            if h & 8 != 0 { -g * d[0] } else { g * d[0] }
+        }
+        2 => {
+            let (s, c) = ((h & 7) as f32 * std::f32::consts::FRAC_PI_4).sin_cos();
+            c * d[0] + s * d[1]
+        }
+        // the edge midpoints of a hypercube: one axis zero, the others +-1
+        _ => {
+            let skip = (h % N as u32) as usize;
+            d.iter().enumerate().filter(|(i, _)| *i != skip).fold(0f32, |acc, (i, v)| {
+                if (h >> (8 + i)) & 1 != 0 { acc - v } else { acc + v }
+            })
+        }
+    }
+}
+
+// collapses 2^N corner values along every axis, axis 0 being the lowest corner bit
+fn multilinear<const N: usize>(mut corners: [f32; 16], t: &[f32; N]) -> f32 {
+    let mut count = 1 << N;
+    for f in t.iter() {
+        count /= 2;
+        for k in 0..count {
+            corners[k] = lerp(corners[2 * k], corners[2 * k + 1], *f);
+        }
+    }
+    corners[0]
+}
+
+fn check_dimension<const N: usize>() {
+    assert!((1..=4).contains(&N), "Noise is only defined for 1 to 4 dimensions.");
+}
+
+impl Perlin {
+    pub fn new(seed: u32) -> Self {
+        Perlin { seed }
+    }
+}
+
+impl Noise for Perlin {
+    fn sample<const N: usize>(&self, p: [f32; N]) -> f32 {
+        check_dimension::<N>();
+        let cell = p.map(|v| v.floor() as i32);
+        let frac: [f32; N] = std::array::from_fn(|i| p[i] - p[i].floor());
+        let mut corners = [0f32; 16];
+        for (corner, value) in corners.iter_mut().enumerate().take(1 << N) {
+            let c: [i32; N] = std::array::from_fn(|i| cell[i] + ((corner >> i) & 1) as i32);
+            let d: [f32; N] = std::array::from_fn(|i| frac[i] - ((corner >> i) & 1) as f32);
+            *value = gradient(hash(self.seed, &c), &d);
+        }
+        // empirical factors bringing the extremes close to +-1
+        let scale = match N {
+            1 => 0.25,
+            2 => 1.4,
+            3 => 1.0,
+            _ => 0.95,
+        };
+        (multilinear(corners, &frac.map(fade)) * scale).clamp(-1f32, 1f32)
+    }
+}
+
+impl Simplex {
+    pub fn new(seed: u32) -> Self {
+        Simplex { seed }
+    }
+}
+
+impl Noise for Simplex {
+    fn sample<const N: usize>(&self, p: [f32; N]) -> f32 {
+        check_dimension::<N>();
+        let n = N as f32;
+        // skew to the hypercube lattice and back
+        let skew = ((n + 1f32).sqrt() - 1f32) / n;
+        let unskew = (1f32 - 1f32 / (n + 1f32).sqrt()) / n;
+        let s = p.iter().sum::<f32>() * skew;
+        let cell = p.map(|v| (v + s).floor() as i32);
+        let t = cell.iter().map(|c| *c as f32).sum::<f32>() * unskew;
+        let d0: [f32; N] = std::array::from_fn(|i| p[i] - (cell[i] as f32 - t));
+        // the simplex is walked along the axes in decreasing order of d0
+        let mut order: [usize; N] = std::array::from_fn(|i| i);
+        order.sort_by(|a, b| d0[*b].total_cmp(&d0[*a]));
+        let radius2 = if N <= 2 { 0.5 } else { 0.6 };
+        let mut offset = [0i32; N];
+        let mut total = 0f32;
+        for k in 0..=N {
+            if k > 0 {
+                offset[order[k - 1]] = 1;
+            }
+            let d: [f32; N] = std::array::from_fn(|i| d0[i] - offset[i] as f32 + k as f32 * unskew);
+            let falloff = radius2 - d.iter().map(|v| v * v).sum::<f32>();
+            if falloff > 0f32 {
+                let c: [i32; N] = std::array::from_fn(|i| cell[i] + offset[i]);
+                let falloff2 = falloff * falloff;
+                total += falloff2 * falloff2 * gradient(hash(self.seed, &c), &d);
+            }
+        }
+        // empirical factors bringing the extremes close to +-1
+        let scale = match N {
+            1 => 8.9,
+            2 => 99.0,
+            3 => 32.0,
+            _ => 27.0,
+        };
+        (total * scale).clamp(-1f32, 1f32)
+    }
+}
+
+impl Value {
+    pub fn new(seed: u32) -> Self {
+        Value { seed }
+    }
+}
+
+impl Noise for Value {
+    fn sample<const N: usize>(&self, p: [f32; N]) -> f32 {
+        check_dimension::<N>();
+        let cell = p.map(|v| v.floor() as i32);
+        let frac: [f32; N] = std::array::from_fn(|i| p[i] - p[i].floor());
+        let mut corners = [0f32; 16];
+        for (corner, value) in corners.iter_mut().enumerate().take(1 << N) {
+            let c: [i32; N] = std::array::from_fn(|i| cell[i] + ((corner >> i) & 1) as i32);
+            *value = unit(hash(self.seed, &c)) * 2f32 - 1f32;
+        }
+        multilinear(corners, &frac.map(fade))
+    }
+}
+
+impl Worley {
+
+    pub fn new(seed: u32) -> Self {
+        Worley {
+            seed,
+            jitter: 1f32,
+            distance: Distance::Euclidean,
+            output: WorleyOutput::F1,
+        }
+    }
+
+    pub fn set_jitter(&mut self, jitter: f32) -> &mut Self {
+        self.jitter = jitter.clamp(0f32, 1f32);
+        self
+    }
+
+    pub fn set_distance(&mut self, distance: Distance) -> &mut Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn set_output(&mut self, output: WorleyOutput) -> &mut Self {
+        self.output = output;
+        self
+    }
+
+    // (F1, F2), searching the 3^N neighboring cells
+    pub fn distances<const N: usize>(&self, p: [f32; N]) -> (f32, f32) {
+        check_dimension::<N>();
+        let cell = p.map(|v| v.floor() as i32);
+        let (mut f1, mut f2) = (f32::INFINITY, f32::INFINITY);
+        for neighbor in 0..3usize.pow(N as u32) {
+            let c: [i32; N] = std::array::from_fn(|i| cell[i] + (neighbor / 3usize.pow(i as u32) % 3) as i32 - 1);
+            let h = hash(self.seed, &c);
+            let d: [f32; N] = std::array::from_fn(|i| {
+                // a different hash per axis for the feature point
+                let jitter = unit(hash(h ^ PRIMES[i], &[i as i32])) - 0.5;
+                c[i] as f32 + 0.5 + jitter * self.jitter - p[i]
+            });
+            let distance = match self.distance {
+                Distance::Euclidean => d.iter().map(|v| v * v).sum::<f32>().sqrt(),
+                Distance::Manhattan => d.iter().map(|v| v.abs()).sum(),
+                Distance::Chebyshev => d.iter().fold(0f32, |acc, v| acc.max(v.abs())),
+            };
+            if distance < f1 {
+                f2 = f1;
+                f1 = distance;
+            } else if distance < f2 {
+                f2 = distance;
+            }
+        }
+        (f1, f2)
+    }
+
+}
+
+impl Noise for Worley {
+    fn sample<const N: usize>(&self, p: [f32; N]) -> f32 {
+        let (f1, f2) = self.distances(p);
+        match self.output {
+            WorleyOutput::F1 => f1,
+            WorleyOutput::F2 => f2,
+            WorleyOutput::F2MinusF1 => f2 - f1,
+        }
+    }
+}
+
+impl<T: Noise> Fractal<T> {
+
+    pub fn new(noise: T, kind: FractalKind, octaves: u32) -> Self {
+        assert!(octaves > 0, "A fractal needs at least one octave.");
+        Fractal { noise, kind, octaves, lacunarity: 2f32, gain: 0.5 }
+    }
+
+    pub fn fbm(noise: T, octaves: u32) -> Self {
+        Self::new(noise, FractalKind::Fbm, octaves)
+    }
+
+    pub fn ridged(noise: T, octaves: u32) -> Self {
+        Self::new(noise, FractalKind::Ridged, octaves)
+    }
+
+    pub fn turbulence(noise: T, octaves: u32) -> Self {
+        Self::new(noise, FractalKind::Turbulence, octaves)
+    }
+
+    pub fn set_lacunarity(&mut self, lacunarity: f32) -> &mut Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn set_gain(&mut self, gain: f32) -> &mut Self {
+        self.gain = gain;
+        self
+    }
+
+    pub fn get_noise(&self) -> &T {
+        &self.noise
+    }
+
+}
+
+impl<T: Noise> Noise for Fractal<T> {
+    fn sample<const N: usize>(&self, p: [f32; N]) -> f32 {
+        let (mut frequency, mut amplitude) = (1f32, 1f32);
+        let (mut total, mut weight) = (0f32, 0f32);
+        for octave in 0..self.octaves {
+            // shifts every octave so their lattices don't line up at the origin
+            let shift = octave as f32 * 17.31;
+            let v = self.noise.sample(p.map(|x| x * frequency + shift));
+            total += amplitude * match self.kind {
+                FractalKind::Fbm => v,
+                FractalKind::Ridged => (1f32 - v.abs()).powi(2),
+                FractalKind::Turbulence => v.abs(),
+            };
+            weight += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        total / weight
+    }
+}
+
+impl<T: Noise, W: Noise> DomainWarp<T, W> {
+
+    pub fn new(noise: T, warp: W, amplitude: f32) -> Self {
+        DomainWarp { noise, warp, amplitude }
+    }
+
+    pub fn set_amplitude(&mut self, amplitude: f32) -> &mut Self {
+        self.amplitude = amplitude;
+        self
+    }
+
+}
+
+impl<T: Noise, W: Noise> Noise for DomainWarp<T, W> {
+    fn sample<const N: usize>(&self, p: [f32; N]) -> f32 {
+        let warped: [f32; N] = std::array::from_fn(|i| {
+            let offset = 31.7 * (i + 1) as f32;
+            p[i] + self.amplitude * self.warp.sample(p.map(|x| x + offset))
+        });
+        self.noise.sample(warped)
+    }
+}
+
+// row major samples at (x, y) * frequency, ready for Colormap::plot
+pub fn grid<T: Noise>(noise: &T, width: u32, height: u32, frequency: f32) -> Vec<f32> {
+    let mut values = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            values.push(noise.noise2(x as f32 * frequency, y as f32 * frequency));
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // scattered points, not on the lattice
+    fn points() -> impl Iterator<Item = [f32; 4]> {
+        (0..4000).map(|i| {
+            let i = i as f32;
+            [(i * 0.618034).fract() * 37f32 - 18f32, (i * 0.754878).fract() * 29f32, (i * 0.569840).fract() * 11f32 - 5f32, i * 0.0131]
+        })
+    }
+
+    fn check_range<T: Noise>(noise: &T) {
+        let (mut low, mut high) = (0f32, 0f32);
+        for p in points() {
+            for v in [noise.noise1(p[0]), noise.noise2(p[0], p[1]), noise.noise3(p[0], p[1], p[2]), noise.sample(p)] {
+                assert!((-1f32..=1f32).contains(&v), "{}", v);
+                low = low.min(v);
+                high = high.max(v);
+            }
+        }
+        // uses most of the range
+        assert!(low < -0.5 && high > 0.5, "{} {}", low, high);
+    }
+
+    #[test]
+    fn noise_is_in_range() {
+        check_range(&Perlin::new(1));
+        check_range(&Simplex::new(2));
+        check_range(&Value::new(3));
+        check_range(&Fractal::fbm(Simplex::new(4), 5));
+        check_range(&DomainWarp::new(Perlin::new(5), Simplex::new(6), 0.5));
+        for v in points().map(|p| Fractal::ridged(Perlin::new(7), 4).sample(p)) {
+            assert!((0f32..=1f32).contains(&v));
+        }
+        for v in points().map(|p| Fractal::turbulence(Value::new(8), 4).noise2(p[0], p[1])) {
+            assert!((0f32..=1f32).contains(&v));
+        }
+    }
+
+    #[test]
+    fn seeds_are_deterministic() {
+        let field = |noise: &Simplex| points().map(|p| noise.noise3(p[0], p[1], p[2])).collect::<Vec<f32>>();
+        assert_eq!(field(&Simplex::new(9)), field(&Simplex::new(9)));
+        assert_ne!(field(&Simplex::new(9)), field(&Simplex::new(10)));
+        assert_eq!(grid(&Perlin::new(1), 8, 8, 0.3), grid(&Perlin::new(1), 8, 8, 0.3));
+        assert_ne!(grid(&Perlin::new(1), 8, 8, 0.3), grid(&Perlin::new(2), 8, 8, 0.3));
+        assert_ne!(grid(&Worley::new(1), 8, 8, 0.3), grid(&Worley::new(2), 8, 8, 0.3));
+    }
+
+    #[test]
+    fn perlin_vanishes_on_the_lattice() {
+        let noise = Perlin::new(12);
+        for x in -5..5 {
+            for y in -5..5 {
+                assert_eq!(noise.noise2(x as f32, y as f32), 0f32);
+            }
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let noise = Simplex::new(13);
+        let value = Value::new(13);
+        for p in points().take(500) {
+            let step = 1e-3;
+            assert!((noise.noise2(p[0], p[1]) - noise.noise2(p[0] + step, p[1])).abs() < 0.05);
+            assert!((value.noise3(p[0], p[1], p[2]) - value.noise3(p[0], p[1], p[2] + step)).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn worley_distances() {
+        let mut worley = Worley::new(14);
+        for p in points().take(500) {
+            let (f1, f2) = worley.distances([p[0], p[1]]);
+            assert!(0f32 <= f1 && f1 <= f2);
+        }
+        // without jitter the feature points sit at the cell centres
+        worley.set_jitter(0f32);
+        assert!((worley.noise2(3.5, -1.5)).abs() < 1e-6);
+        assert!((worley.noise2(3.75, -1.5) - 0.25).abs() < 1e-6);
+        worley.set_output(WorleyOutput::F2);
+        assert!((worley.noise2(3.75, -1.5) - 0.75).abs() < 1e-6);
+        worley.set_output(WorleyOutput::F2MinusF1);
+        assert!((worley.noise2(4f32, -1.5)).abs() < 1e-6);
+        worley.set_output(WorleyOutput::F1);
+        worley.set_distance(Distance::Manhattan);
+        assert!((worley.noise2(3.75, -1.25) - 0.5).abs() < 1e-6);
+        worley.set_distance(Distance::Chebyshev);
+        assert!((worley.noise2(3.75, -1.25) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "1 to 4 dimensions")]
+    fn five_dimensions() {
+        Perlin::new(0).sample([0f32; 5]);
+    }
+
+}
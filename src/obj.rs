@@ -0,0 +1,395 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path
+};
+
+use super::{
+    canvas::Color,
+    mathsf::{Vec2, Vec3},
+    mesh::{Indices, Mesh}
+};
+
+// Wavefront OBJ and MTL. Faces with more than three vertices are fan triangulated
+// (so they should be convex), unsupported statements are skipped and every
+// malformed one is an error carrying its line number (1-based).
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(String, std::io::Error),
+    InvalidNumber { line: usize, text: String },
+    MissingValues { line: usize, keyword: String },
+    InvalidFace { line: usize, text: String },
+    IndexOutOfRange { line: usize, index: i64 },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Material {
+    pub name: String,
+    // Ka, Kd, Ks as written in the file, usually linear RGB in [0, 1]
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    // Ns, the Phong exponent
+    pub shininess: f32,
+    // d, or 1 - Tr
+    pub opacity: f32,
+    // map_Kd, relative to the MTL file
+    pub diffuse_map: Option<String>,
+}
+
+// faces sharing a group name and material
+#[derive(Clone, Debug)]
+pub struct ObjMesh {
+    pub name: String,
+    pub material: Option<String>,
+    pub mesh: Mesh,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Obj {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<Material>,
+    // mtllib statements, only resolved by Obj::load
+    pub material_libraries: Vec<String>,
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(path, e) => write!(f, "cannot read '{}': {}", path, e),
+            ObjError::InvalidNumber { line, text } => write!(f, "line {}: invalid number '{}'", line, text),
+            ObjError::MissingValues { line, keyword } => write!(f, "line {}: too few values for '{}'", line, keyword),
+            ObjError::InvalidFace { line, text } => write!(f, "line {}: invalid face '{}'", line, text),
+            ObjError::IndexOutOfRange { line, index } => write!(f, "line {}: index {} out of range", line, index),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::new(),
+            ambient: Vec3::zero(),
+            diffuse: Vec3::new(0.8, 0.8, 0.8),
+            specular: Vec3::zero(),
+            shininess: 0f32,
+            opacity: 1f32,
+            diffuse_map: None,
+        }
+    }
+}
+
+impl Material {
+
+    pub fn diffuse_color(&self) -> Color {
+        Color::from_linear(&self.diffuse.extend(self.opacity))
+    }
+
+    pub fn specular_color(&self) -> Color {
+        Color::from_linear(&self.specular.extend(1f32))
+    }
+
+    pub fn parse_mtl(source: &str) -> Result<Vec<Material>, ObjError> {
+        let mut materials: Vec<Material> = Vec::new();
+        for (number, line) in statements(source) {
+            let mut tokens = line.split_whitespace();
+            let keyword = tokens.next().unwrap_or_default();
+            let rest: Vec<&str> = tokens.collect();
+            if keyword == "newmtl" {
+                materials.push(Material { name: rest.join(" "), ..Material::default() });
+                continue;
+            }
+            // statements before the first newmtl have nothing to apply to
+            let Some(material) = materials.last_mut() else {
+                continue;
+            };
+            match keyword {
+                "Ka" => material.ambient = parse_color(number, keyword, &rest)?,
+                "Kd" => material.diffuse = parse_color(number, keyword, &rest)?,
+                "Ks" => material.specular = parse_color(number, keyword, &rest)?,
+                "Ns" => material.shininess = parse_floats::<1>(number, keyword, &rest)?[0],
+                "d" => material.opacity = parse_floats::<1>(number, keyword, &rest)?[0],
+                "Tr" => material.opacity = 1f32 - parse_floats::<1>(number, keyword, &rest)?[0],
+                // options like -s 1 1 1 come before the file name, which is last
+                "map_Kd" => {
+                    let file = rest.last().ok_or_else(|| missing(number, keyword))?;
+                    material.diffuse_map = Some(file.to_string());
+                }
+                _ => {}
+            }
+        }
+        Ok(materials)
+    }
+
+}
+
+impl Obj {
+
+    // mtllib statements are recorded but not loaded, see Obj::load
+    pub fn parse(source: &str) -> Result<Obj, ObjError> {
+        let mut parser = Parser::default();
+        for (number, line) in statements(source) {
+            parser.statement(number, line)?;
+        }
+        Ok(parser.finish())
+    }
+
+    // reads the file and the material libraries next to it
+    pub fn load(path: impl AsRef<Path>) -> Result<Obj, ObjError> {
+        let path = path.as_ref();
+        let mut obj = Self::parse(&read(path)?)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for library in &obj.material_libraries {
+            let materials = Material::parse_mtl(&read(&directory.join(library))?)?;
+            obj.materials.extend(materials);
+        }
+        Ok(obj)
+    }
+
+    pub fn material(&self, name: &str) -> Option<&Material> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|e| ObjError::Io(path.display().to_string(), e))
+}
+
+// (line number, statement) without comments and blank lines, joining `\` continuations
+fn statements(source: &str) -> Vec<(usize, String)> {
+    let mut statements = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (i, raw) in source.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or_default().trim_end();
+        let (number, mut text) = pending.take().unwrap_or((i + 1, String::new()));
+        if let Some(continued) = line.strip_suffix('\\') {
+            text.push_str(continued);
+            text.push(' ');
+            pending = Some((number, text));
+            continue;
+        }
+        text.push_str(line);
+        if !text.trim().is_empty() {
+            statements.push((number, text.trim().to_string()));
+        }
+    }
+    if let Some((number, text)) = pending {
+        statements.push((number, text.trim().to_string()));
+    }
+    statements
+}
+
+fn missing(line: usize, keyword: &str) -> ObjError {
+    ObjError::MissingValues { line, keyword: keyword.to_string() }
+}
+
+fn parse_float(line: usize, text: &str) -> Result<f32, ObjError> {
+    text.parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| ObjError::InvalidNumber { line, text: text.to_string() })
+}
+
+// the first N values, extra ones (like the optional w) are ignored
+fn parse_floats<const N: usize>(line: usize, keyword: &str, values: &[&str]) -> Result<[f32; N], ObjError> {
+    if values.len() < N {
+        return Err(missing(line, keyword));
+    }
+    let mut parsed = [0f32; N];
+    for (v, text) in parsed.iter_mut().zip(values) {
+        *v = parse_float(line, text)?;
+    }
+    Ok(parsed)
+}
+
+// a single value means gray, spectral and XYZ forms aren't supported
+fn parse_color(line: usize, keyword: &str, values: &[&str]) -> Result<Vec3, ObjError> {
+    match values {
+        [] => Err(missing(line, keyword)),
+        [v] => {
+            let v = parse_float(line, v)?;
+            Ok(Vec3::new(v, v, v))
+        }
+        _ => parse_floats::<3>(line, keyword, values).map(Vec3::from),
+    }
+}
+
+// positions into the file-wide attribute lists, per face corner
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+// vertices are shared between faces of the same smoothing group only
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct VertexKey {
+    corner: Corner,
+    smoothing: u64,
+}
+
+#[derive(Default)]
+struct Builder {
+    name: String,
+    material: Option<String>,
+    keys: Vec<VertexKey>,
+    lookup: HashMap<VertexKey, u32>,
+    indices: Vec<u32>,
+}
+
+#[derive(Default)]
+struct Parser {
+    positions: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    group: String,
+    material: Option<String>,
+    // 0 is off: flat faces
+    smoothing: u64,
+    faces: usize,
+    current: Builder,
+    meshes: Vec<ObjMesh>,
+    material_libraries: Vec<String>,
+}
+
+impl Parser {
+
+    fn statement(&mut self, number: usize, line: String) -> Result<(), ObjError> {
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap_or_default();
+        let rest: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => self.positions.push(Vec3::from(parse_floats::<3>(number, keyword, &rest)?)),
+            "vn" => self.normals.push(Vec3::from(parse_floats::<3>(number, keyword, &rest)?)),
+            // v is optional and defaults to 0
+            "vt" => {
+                let u = parse_floats::<1>(number, keyword, &rest)?[0];
+                let v = if rest.len() > 1 { parse_float(number, rest[1])? } else { 0f32 };
+                self.uvs.push(Vec2::new(u, v));
+            }
+            "f" => self.face(number, &line, &rest)?,
+            "g" | "o" => {
+                self.group = rest.join(" ");
+                self.flush();
+            }
+            "usemtl" => {
+                self.material = Some(rest.join(" "));
+                self.flush();
+            }
+            "s" => {
+                let value = rest.first().ok_or_else(|| missing(number, keyword))?;
+                self.smoothing = match *value {
+                    "off" => 0,
+                    _ => value.parse().map_err(|_| ObjError::InvalidNumber { line: number, text: value.to_string() })?,
+                };
+            }
+            "mtllib" => self.material_libraries.extend(rest.iter().map(|s| s.to_string())),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // an index is 1-based, or relative to the end of the list when negative
+    fn resolve(&self, number: usize, text: &str, len: usize) -> Result<usize, ObjError> {
+        let index: i64 = text.parse().map_err(|_| ObjError::InvalidNumber { line: number, text: text.to_string() })?;
+        let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+        if index == 0 || resolved < 0 || resolved >= len as i64 {
+            return Err(ObjError::IndexOutOfRange { line: number, index });
+        }
+        Ok(resolved as usize)
+    }
+
+    fn face(&mut self, number: usize, line: &str, corners: &[&str]) -> Result<(), ObjError> {
+        let invalid = || ObjError::InvalidFace { line: number, text: line.to_string() };
+        if corners.len() < 3 {
+            return Err(invalid());
+        }
+        let mut parsed = Vec::with_capacity(corners.len());
+        for corner in corners {
+            let mut parts = corner.split('/');
+            let position = parts.next().filter(|p| !p.is_empty()).ok_or_else(invalid)?;
+            let uv = parts.next().filter(|p| !p.is_empty());
+            let normal = parts.next().filter(|p| !p.is_empty());
+            if parts.next().is_some() {
+                return Err(invalid());
+            }
+            parsed.push(Corner {
+                position: self.resolve(number, position, self.positions.len())?,
+                uv: uv.map(|uv| self.resolve(number, uv, self.uvs.len())).transpose()?,
+                normal: normal.map(|n| self.resolve(number, n, self.normals.len())).transpose()?,
+            });
+        }
+        // flat faces never share vertices, every face gets its own group
+        self.faces += 1;
+        let smoothing = if self.smoothing == 0 { u64::MAX - self.faces as u64 } else { self.smoothing };
+        let indices: Vec<u32> = parsed.iter().map(|corner| {
+            // normals from the file make the smoothing group irrelevant
+            let key = VertexKey { corner: *corner, smoothing: if corner.normal.is_some() { 0 } else { smoothing } };
+            let builder = &mut self.current;
+            *builder.lookup.entry(key).or_insert_with(|| {
+                builder.keys.push(key);
+                builder.keys.len() as u32 - 1
+            })
+        }).collect();
+        for i in 1..indices.len() - 1 {
+            self.current.indices.extend([indices[0], indices[i], indices[i + 1]]);
+        }
+        Ok(())
+    }
+
+    // closes the mesh being built, the next faces start a new one
+    fn flush(&mut self) {
+        let builder = std::mem::take(&mut self.current);
+        self.current.name = self.group.clone();
+        self.current.material = self.material.clone();
+        if builder.indices.is_empty() {
+            return;
+        }
+        let positions: Vec<Vec3> = builder.keys.iter().map(|k| self.positions[k.corner.position]).collect();
+
+        // normals missing from the file are computed, area weighted across shared vertices
+        let mut computed = vec![Vec3::zero(); positions.len()];
+        for triangle in builder.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| triangle[k] as usize);
+            let n = (positions[b] - positions[a]).cross(&(positions[c] - positions[a]));
+            for i in [a, b, c] {
+                computed[i] += n;
+            }
+        }
+        let normals = builder.keys.iter().zip(computed.iter())
+            .map(|(k, n)| k.corner.normal.map_or(n.normalize(), |i| self.normals[i]))
+            .collect();
+
+        let mut mesh = Mesh::new(positions);
+        mesh.set_normals(normals);
+        if builder.keys.iter().any(|k| k.corner.uv.is_some()) {
+            mesh.set_uvs(builder.keys.iter().map(|k| k.corner.uv.map_or(Vec2::zero(), |i| self.uvs[i])).collect());
+        }
+        mesh.set_indices(Some(Indices::from_u32(builder.indices)));
+        self.meshes.push(ObjMesh { name: builder.name, material: builder.material, mesh });
+    }
+
+    fn finish(mut self) -> Obj {
+        self.flush();
+        Obj {
+            meshes: self.meshes,
+            materials: Vec::new(),
+            material_libraries: self.material_libraries,
+        }
+    }
+
+}
@@ -0,0 +1,411 @@
+use std::f32::consts::TAU;
+
+use super::canvas::Color;
+
+// Paints are evaluated in canvas coordinates, the same ones draw_pixel takes:
+// origin at the center, y pointing up.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Spread {
+    // the end colors extend forever
+    Pad,
+    Repeat,
+    // mirrored every other period
+    Reflect,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Interpolation {
+    // straight mix of the encoded values, what most 2D APIs do
+    Srgb,
+    LinearRgb,
+    // perceptually even
+    Oklab,
+}
+
+#[derive(Clone, Debug)]
+pub struct ColorRamp {
+    stops: Vec<(f32, Color)>,
+    spread: Spread,
+    interpolation: Interpolation,
+}
+
+#[derive(Clone, Debug)]
+pub struct LinearGradient {
+    start: (f32, f32),
+    end: (f32, f32),
+    ramp: ColorRamp,
+}
+
+// t = 0 at the focal point, t = 1 on the circle
+#[derive(Clone, Debug)]
+pub struct RadialGradient {
+    center: (f32, f32),
+    radius: f32,
+    focal: (f32, f32),
+    ramp: ColorRamp,
+}
+
+// sweeps counter-clockwise from `angle` (radians), one turn covers t in [0, 1]
+#[derive(Clone, Debug)]
+pub struct ConicGradient {
+    center: (f32, f32),
+    angle: f32,
+    ramp: ColorRamp,
+}
+
+// grid of colors over a rectangle, bilinearly blended inside every cell
+#[derive(Clone, Debug)]
+pub struct MeshGradient {
+    origin: (f32, f32),
+    size: (f32, f32),
+    columns: usize,
+    rows: usize,
+    colors: Vec<Color>,
+    interpolation: Interpolation,
+}
+
+#[derive(Clone, Debug)]
+pub enum Paint {
+    Solid(Color),
+    Linear(LinearGradient),
+    Radial(RadialGradient),
+    Conic(ConicGradient),
+    Mesh(MeshGradient),
+}
+
+impl Interpolation {
+    pub fn mix(&self, c0: &Color, c1: &Color, f: f32) -> Color {
+        match self {
+            Interpolation::Srgb => Color::lerp(c0, c1, f),
+            Interpolation::LinearRgb => Color::lerp_linear(c0, c1, f),
+            Interpolation::Oklab => Color::lerp_oklab(c0, c1, f),
+        }
+    }
+}
+
+impl Spread {
+    // maps any t into [0, 1]
+    pub fn apply(&self, t: f32) -> f32 {
+        match self {
+            Spread::Pad => t.clamp(0f32, 1f32),
+            Spread::Repeat => t.rem_euclid(1f32),
+            Spread::Reflect => {
+                let t = t.rem_euclid(2f32);
+                if t > 1f32 { 2f32 - t } else { t }
+            }
+        }
+    }
+}
+
+impl ColorRamp {
+
+    // stops are (offset, color), sorted by offset on construction
+    pub fn new(mut stops: Vec<(f32, Color)>) -> Self {
+        assert!(!stops.is_empty(), "A color ramp needs at least one stop.");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ColorRamp {
+            stops,
+            spread: Spread::Pad,
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    // evenly spaced stops
+    pub fn from_colors(colors: &[Color]) -> Self {
+        let last = (colors.len().max(2) - 1) as f32;
+        Self::new(colors.iter().enumerate().map(|(i, c)| (i as f32 / last, *c)).collect())
+    }
+
+    pub fn two(c0: Color, c1: Color) -> Self {
+        Self::new(vec![(0f32, c0), (1f32, c1)])
+    }
+
+    pub fn set_spread(&mut self, spread: Spread) -> &mut Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) -> &mut Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn get_spread(&self) -> Spread {
+        self.spread
+    }
+
+    pub fn get_interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn stops(&self) -> &[(f32, Color)] {
+        &self.stops
+    }
+
+    pub fn sample(&self, t: f32) -> Color {
+        let t = self.spread.apply(t);
+        let first = self.stops[0];
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+            if t <= t1 {
+                let span = t1 - t0;
+                let f = if span > 0f32 { (t - t0) / span } else { 1f32 };
+                return self.interpolation.mix(&c0, &c1, f);
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+
+}
+
+impl LinearGradient {
+
+    pub fn new(x0: f32, y0: f32, x1: f32, y1: f32, ramp: ColorRamp) -> Self {
+        LinearGradient { start: (x0, y0), end: (x1, y1), ramp }
+    }
+
+    pub fn sample(&self, x: f32, y: f32) -> Color {
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let length2 = dx * dx + dy * dy;
+        let t = if length2 > 0f32 {
+            ((x - self.start.0) * dx + (y - self.start.1) * dy) / length2
+        } else {
+            0f32
+        };
+        self.ramp.sample(t)
+    }
+
+}
+
+impl RadialGradient {
+
+    pub fn new(cx: f32, cy: f32, radius: f32, ramp: ColorRamp) -> Self {
+        RadialGradient { center: (cx, cy), radius, focal: (cx, cy), ramp }
+    }
+
+    // the focal point is pulled slightly inside the circle when it lies outside
+    pub fn with_focal(cx: f32, cy: f32, radius: f32, fx: f32, fy: f32, ramp: ColorRamp) -> Self {
+        let (dx, dy) = (fx - cx, fy - cy);
+        let distance = (dx * dx + dy * dy).sqrt();
+        let limit = radius * 0.999;
+        let focal = if distance > limit {
+            (cx + dx * limit / distance, cy + dy * limit / distance)
+        } else {
+            (fx, fy)
+        };
+        RadialGradient { center: (cx, cy), radius, focal, ramp }
+    }
+
+    pub fn sample(&self, x: f32, y: f32) -> Color {
+        // t = |p - f| / |e - f| where e is where the ray from f through p leaves the circle
+        let (px, py) = (x - self.focal.0, y - self.focal.1);
+        let distance = (px * px + py * py).sqrt();
+        if distance <= f32::EPSILON || self.radius <= 0f32 {
+            return self.ramp.sample(0f32);
+        }
+        let (ux, uy) = (px / distance, py / distance);
+        let (fx, fy) = (self.focal.0 - self.center.0, self.focal.1 - self.center.1);
+        let b = fx * ux + fy * uy;
+        let c = fx * fx + fy * fy - self.radius * self.radius;
+        let s = -b + (b * b - c).max(0f32).sqrt();
+        self.ramp.sample(if s > 0f32 { distance / s } else { 1f32 })
+    }
+
+}
+
+impl ConicGradient {
+
+    pub fn new(cx: f32, cy: f32, angle: f32, ramp: ColorRamp) -> Self {
+        ConicGradient { center: (cx, cy), angle, ramp }
+    }
+
+    pub fn sample(&self, x: f32, y: f32) -> Color {
+        let theta = (y - self.center.1).atan2(x - self.center.0) - self.angle;
+        let t = theta.rem_euclid(TAU) / TAU;
+        // the seam is always a hard edge, only Pad makes sense past one turn
+        self.ramp.sample(t)
+    }
+
+}
+
+impl MeshGradient {
+
+    // `colors` is (columns + 1) x (rows + 1) row major, first row at `origin` (the minimum corner)
+    pub fn new(x: f32, y: f32, width: f32, height: f32, columns: usize, rows: usize, colors: Vec<Color>) -> Self {
+        assert!(columns > 0 && rows > 0, "A mesh gradient needs at least one cell.");
+        assert_eq!(colors.len(), (columns + 1) * (rows + 1), "Mesh gradient color count does not match its grid.");
+        MeshGradient {
+            origin: (x, y),
+            size: (width, height),
+            columns,
+            rows,
+            colors,
+            interpolation: Interpolation::Srgb,
+        }
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) -> &mut Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn sample(&self, x: f32, y: f32) -> Color {
+        let u = ((x - self.origin.0) / self.size.0).clamp(0f32, 1f32) * self.columns as f32;
+        let v = ((y - self.origin.1) / self.size.1).clamp(0f32, 1f32) * self.rows as f32;
+        let (column, row) = ((u as usize).min(self.columns - 1), (v as usize).min(self.rows - 1));
+        let (fu, fv) = (u - column as f32, v - row as f32);
+        let at = |c: usize, r: usize| &self.colors[c + r * (self.columns + 1)];
+        let bottom = self.interpolation.mix(at(column, row), at(column + 1, row), fu);
+        let top = self.interpolation.mix(at(column, row + 1), at(column + 1, row + 1), fu);
+        self.interpolation.mix(&bottom, &top, fv)
+    }
+
+}
+
+impl Paint {
+    pub fn sample(&self, x: f32, y: f32) -> Color {
+        match self {
+            Paint::Solid(color) => *color,
+            Paint::Linear(gradient) => gradient.sample(x, y),
+            Paint::Radial(gradient) => gradient.sample(x, y),
+            Paint::Conic(gradient) => gradient.sample(x, y),
+            Paint::Mesh(gradient) => gradient.sample(x, y),
+        }
+    }
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Self {
+        Paint::Solid(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn gray(v: u8) -> Color {
+        Color::from_rgb(v, v, v)
+    }
+
+    fn ramp() -> ColorRamp {
+        ColorRamp::two(gray(0), gray(200))
+    }
+
+    #[test]
+    fn spread_modes() {
+        for (t, pad, repeat, reflect) in [(-0.25, 0f32, 0.75, 0.25), (0.5, 0.5, 0.5, 0.5), (1.25, 1f32, 0.25, 0.75), (2.5, 1f32, 0.5, 0.5)] {
+            assert_eq!(Spread::Pad.apply(t), pad);
+            assert_eq!(Spread::Repeat.apply(t), repeat);
+            assert_eq!(Spread::Reflect.apply(t), reflect);
+        }
+        let mut ramp = ramp();
+        ramp.set_spread(Spread::Reflect);
+        assert_eq!(ramp.sample(1.5), gray(100));
+        ramp.set_spread(Spread::Repeat);
+        assert_eq!(ramp.sample(1.25), gray(50));
+    }
+
+    #[test]
+    fn stops() {
+        // unsorted on purpose
+        let ramp = ColorRamp::new(vec![(0.75, gray(100)), (0.25, gray(0)), (0.5, gray(40))]);
+        assert_eq!(ramp.stops()[0].0, 0.25);
+        assert_eq!(ramp.sample(0f32), gray(0));
+        assert_eq!(ramp.sample(0.375), gray(20));
+        assert_eq!(ramp.sample(0.5), gray(40));
+        assert_eq!(ramp.sample(0.625), gray(70));
+        assert_eq!(ramp.sample(1f32), gray(100));
+        // two stops at the same offset make a hard edge
+        let edge = ColorRamp::new(vec![(0f32, gray(0)), (0.5, gray(0)), (0.5, gray(255)), (1f32, gray(255))]);
+        assert_eq!(edge.sample(0.49), gray(0));
+        assert_eq!(edge.sample(0.51), gray(255));
+        assert_eq!(ColorRamp::from_colors(&[gray(9)]).sample(0.7), gray(9));
+        assert_eq!(ColorRamp::from_colors(&[gray(0), gray(10), gray(100)]).stops()[1].0, 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one stop")]
+    fn ramps_need_a_stop() {
+        ColorRamp::new(vec![]);
+    }
+
+    #[test]
+    fn interpolation_spaces() {
+        let (black, white) = (Color::black(), Color::white());
+        assert_eq!(Interpolation::Srgb.mix(&black, &white, 0.5), gray(128));
+        assert_eq!(Interpolation::LinearRgb.mix(&black, &white, 0.5), gray(188));
+        let oklab = Interpolation::Oklab.mix(&black, &white, 0.5);
+        assert!((99..=100).contains(&oklab.get_red()));
+        let mut ramp = ramp();
+        ramp.set_interpolation(Interpolation::LinearRgb);
+        assert_eq!(ramp.get_interpolation(), Interpolation::LinearRgb);
+        assert!(ramp.sample(0.5).get_red() > 100);
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let gradient = LinearGradient::new(-10f32, 0f32, 10f32, 0f32, ramp());
+        assert_eq!(gradient.sample(-10f32, 5f32), gray(0));
+        assert_eq!(gradient.sample(0f32, -7f32), gray(100));
+        assert_eq!(gradient.sample(10f32, 0f32), gray(200));
+        assert_eq!(gradient.sample(50f32, 0f32), gray(200));
+        // a zero length gradient shows its first stop
+        assert_eq!(LinearGradient::new(1f32, 1f32, 1f32, 1f32, ramp()).sample(5f32, 5f32), gray(0));
+    }
+
+    #[test]
+    fn radial_gradient() {
+        let gradient = RadialGradient::new(0f32, 0f32, 10f32, ramp());
+        assert_eq!(gradient.sample(0f32, 0f32), gray(0));
+        assert_eq!(gradient.sample(0f32, 5f32), gray(100));
+        assert_eq!(gradient.sample(-10f32, 0f32), gray(200));
+        // off-center focal point: t runs from the focus to the circle along every ray
+        let focal = RadialGradient::with_focal(0f32, 0f32, 10f32, 5f32, 0f32, ramp());
+        assert_eq!(focal.sample(5f32, 0f32), gray(0));
+        assert_eq!(focal.sample(7.5, 0f32), gray(100));
+        assert_eq!(focal.sample(-2.5, 0f32), gray(100));
+        // a focus outside the circle is pulled back in
+        let outside = RadialGradient::with_focal(0f32, 0f32, 10f32, 20f32, 0f32, ramp());
+        assert_eq!(outside.sample(9.99, 0f32), gray(0));
+    }
+
+    #[test]
+    fn conic_gradient() {
+        let mut ramp = ramp();
+        ramp.set_spread(Spread::Pad);
+        let gradient = ConicGradient::new(0f32, 0f32, 0f32, ramp.clone());
+        assert_eq!(gradient.sample(1f32, 0f32), gray(0));
+        assert_eq!(gradient.sample(0f32, 1f32), gray(50));
+        assert_eq!(gradient.sample(-1f32, 0f32), gray(100));
+        assert_eq!(gradient.sample(0f32, -1f32), gray(150));
+        let turned = ConicGradient::new(0f32, 0f32, std::f32::consts::FRAC_PI_2, ramp);
+        assert_eq!(turned.sample(0f32, 1f32), gray(0));
+    }
+
+    #[test]
+    fn mesh_gradient() {
+        // 2 x 1 cells over [0, 20] x [0, 10]
+        let colors = vec![gray(0), gray(100), gray(200), gray(40), gray(140), gray(240)];
+        let mesh = MeshGradient::new(0f32, 0f32, 20f32, 10f32, 2, 1, colors);
+        assert_eq!(mesh.sample(0f32, 0f32), gray(0));
+        assert_eq!(mesh.sample(20f32, 10f32), gray(240));
+        assert_eq!(mesh.sample(10f32, 0f32), gray(100));
+        assert_eq!(mesh.sample(15f32, 5f32), gray(170));
+        // clamped outside the rectangle
+        assert_eq!(mesh.sample(-5f32, 30f32), gray(40));
+    }
+
+    #[test]
+    fn paints() {
+        assert_eq!(Paint::from(Color::red()).sample(3f32, 4f32), Color::red());
+        let paint = Paint::Linear(LinearGradient::new(0f32, 0f32, 0f32, 10f32, ramp()));
+        assert_eq!(paint.sample(99f32, 5f32), gray(100));
+    }
+
+}
@@ -0,0 +1,118 @@
+use super::canvas::Color;
+
+pub struct Palette {
+    colors: Vec<Color>,
+}
+
+impl Palette {
+
+    pub fn new(colors: Vec<Color>) -> Self {
+        Palette { colors }
+    }
+
+    pub fn from_hex(values: &[u32]) -> Self {
+        let colors = values.iter()
+            .map(|v| Color::from_rgb((v >> 16) as u8, (v >> 8) as u8, *v as u8))
+            .collect();
+        Palette { colors }
+    }
+
+    pub fn monochrome() -> Self {
+        Self::new(vec![Color::black(), Color::white()])
+    }
+
+    pub fn grayscale(levels: u32) -> Self {
+        let levels = levels.clamp(2, 256);
+        let colors = (0..levels)
+            .map(|i| Color::gray_shade(i as f32 / (levels - 1) as f32))
+            .collect();
+        Palette { colors }
+    }
+
+    pub fn game_boy() -> Self {
+        Self::from_hex(&[0x0f380f, 0x306230, 0x8bac0f, 0x9bbc0f])
+    }
+
+    pub fn cga() -> Self {
+        Self::from_hex(&[0x000000, 0x55ffff, 0xff55ff, 0xffffff])
+    }
+
+    pub fn pico8() -> Self {
+        Self::from_hex(&[
+            0x000000, 0x1d2b53, 0x7e2553, 0x008751, 0xab5236, 0x5f574f, 0xc2c3c7, 0xfff1e8,
+            0xff004d, 0xffa300, 0xffec27, 0x00e436, 0x29adff, 0x83769c, 0xff77a8, 0xffccaa,
+        ])
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &Color {
+        &self.colors[index]
+    }
+
+    pub fn set(&mut self, index: usize, color: Color) {
+        self.colors[index] = color;
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn colors_mut(&mut self) -> &mut [Color] {
+        &mut self.colors
+    }
+
+    // index of the closest entry, euclidean distance over sRGB channels
+    pub fn nearest(&self, red: f32, green: f32, blue: f32) -> usize {
+        let mut best = 0;
+        let mut best_distance = f32::INFINITY;
+        for (i, c) in self.colors.iter().enumerate() {
+            let dr = red - c.get_red() as f32;
+            let dg = green - c.get_green() as f32;
+            let db = blue - c.get_blue() as f32;
+            let distance = dr * dr + dg * dg + db * db;
+            if distance < best_distance {
+                best = i;
+                best_distance = distance;
+            }
+        }
+        best
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn constructors() {
+        let palette = Palette::from_hex(&[0x123456, 0xff0080]);
+        assert_eq!(palette.colors(), &[Color::from_rgb(0x12, 0x34, 0x56), Color::from_rgb(0xff, 0x00, 0x80)]);
+        let gray = Palette::grayscale(5);
+        assert_eq!(gray.len(), 5);
+        assert_eq!(*gray.get(0), Color::black());
+        assert_eq!(*gray.get(4), Color::white());
+        assert_eq!(Palette::grayscale(1).len(), 2);
+        assert_eq!(Palette::pico8().len(), 16);
+    }
+
+    #[test]
+    fn nearest_entry() {
+        let mut palette = Palette::cga();
+        assert_eq!(palette.nearest(10f32, 0f32, 0f32), 0);
+        assert_eq!(palette.nearest(90f32, 240f32, 250f32), 1);
+        assert_eq!(palette.nearest(300f32, 300f32, 300f32), 3);
+        palette.set(3, Color::red());
+        assert_eq!(palette.nearest(250f32, 10f32, 10f32), 3);
+        assert_eq!(Palette::new(vec![]).nearest(1f32, 2f32, 3f32), 0);
+    }
+
+}
@@ -0,0 +1,542 @@
+use super::{
+    canvas::{Canvas, Color},
+    clip::{ClipVertex, Clipper},
+    interpolate::Interpolate,
+    mathsf::Vec4
+};
+
+// OpenGL-like software pipeline: vertex shading, clipping in homogeneous space,
+// perspective divide, viewport mapping, rasterization with perspective-correct
+// varyings, depth test and blending (the canvas blending mode) into a Canvas.
+// NDC depth [-1, 1] is stored as [0, 1] in the depth buffer, smaller is nearer.
+
+pub trait VertexShader {
+    type Vertex;
+    type Varying: Interpolate;
+    // clip-space position and the varyings handed to the fragment shader
+    fn shade(&self, vertex: &Self::Vertex) -> (Vec4, Self::Varying);
+}
+
+pub trait FragmentShader<V> {
+    // None discards the fragment
+    fn shade(&self, varying: &V, fragment: &Fragment) -> Option<Color>;
+}
+
+// what a fragment shader knows about the pixel besides the varyings
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fragment {
+    // buffer coordinates, origin at the top-left corner
+    pub x: u32,
+    pub y: u32,
+    pub depth: f32,
+    pub front_facing: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Pipeline {
+    clipper: Clipper,
+    cull_mode: CullMode,
+    front_face: FrontFace,
+    depth_test: bool,
+    depth_write: bool,
+}
+
+// a vertex after the perspective divide and viewport mapping
+struct ScreenVertex {
+    x: f32,
+    y: f32,
+    depth: f32,
+    // 1 / w, for perspective-correct interpolation
+    inverse_w: f32,
+}
+
+impl Pipeline {
+
+    pub fn new() -> Self {
+        Pipeline {
+            // the rasterizer scissors to the canvas anyway
+            clipper: Clipper::with_guard_band(2f32),
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            depth_test: true,
+            depth_write: true,
+        }
+    }
+
+    pub fn set_cull_mode(&mut self, cull_mode: CullMode) -> &mut Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn set_front_face(&mut self, front_face: FrontFace) -> &mut Self {
+        self.front_face = front_face;
+        self
+    }
+
+    pub fn set_depth_test(&mut self, depth_test: bool) -> &mut Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn set_depth_write(&mut self, depth_write: bool) -> &mut Self {
+        self.depth_write = depth_write;
+        self
+    }
+
+    pub fn get_clipper_mut(&mut self) -> &mut Clipper {
+        &mut self.clipper
+    }
+
+    // triangle list, every three vertices make a triangle and leftovers are ignored
+    pub fn draw<VS, FS>(&self, canvas: &mut Canvas, vertex_shader: &VS, fragment_shader: &FS, vertices: &[VS::Vertex])
+    where
+        VS: VertexShader,
+        FS: FragmentShader<VS::Varying>
+    {
+        for triangle in vertices.chunks_exact(3) {
+            let shaded = [
+                vertex_shader.shade(&triangle[0]),
+                vertex_shader.shade(&triangle[1]),
+                vertex_shader.shade(&triangle[2]),
+            ];
+            self.draw_triangle(canvas, fragment_shader, shaded);
+        }
+    }
+
+    // everything after vertex shading, for callers that shade (or cache) vertices themselves
+    pub fn draw_triangle<V, FS>(&self, canvas: &mut Canvas, fragment_shader: &FS, triangle: [ClipVertex<V>; 3])
+    where
+        V: Interpolate,
+        FS: FragmentShader<V>
+    {
+        for clipped in self.clipper.clip_triangle(triangle) {
+            self.rasterize(canvas, fragment_shader, &clipped);
+        }
+    }
+
+    fn rasterize<V, FS>(&self, canvas: &mut Canvas, fragment_shader: &FS, triangle: &[ClipVertex<V>; 3])
+    where
+        V: Interpolate,
+        FS: FragmentShader<V>
+    {
+        let (width, height) = (canvas.get_width() as f32, canvas.get_height() as f32);
+        let screen = triangle.each_ref().map(|(p, _)| to_screen(p, width, height));
+        let [s0, s1, s2] = &screen;
+
+        // y points down in buffer coordinates, so counter-clockwise on screen is negative here
+        let area = edge(s0, s1, s2.x, s2.y);
+        if area == 0f32 {
+            return;
+        }
+        let counter_clockwise = area < 0f32;
+        let front_facing = counter_clockwise == (self.front_face == FrontFace::CounterClockwise);
+        match self.cull_mode {
+            CullMode::Back if !front_facing => return,
+            CullMode::Front if front_facing => return,
+            _ => {}
+        }
+
+        let min_x = s0.x.min(s1.x).min(s2.x).floor().max(0f32) as u32;
+        let max_x = s0.x.max(s1.x).max(s2.x).ceil().min(width) as u32;
+        let min_y = s0.y.min(s1.y).min(s2.y).floor().max(0f32) as u32;
+        let max_y = s0.y.max(s1.y).max(s2.y).ceil().min(height) as u32;
+
+        // top-left rule: pixels exactly on a shared edge belong to one triangle only
+        let sign = area.signum();
+        let owns = |a: &ScreenVertex, b: &ScreenVertex| {
+            let (dx, dy) = ((b.x - a.x) * sign, (b.y - a.y) * sign);
+            dy < 0f32 || (dy == 0f32 && dx > 0f32)
+        };
+        let edges = [(s1, s2, owns(s1, s2)), (s2, s0, owns(s2, s0)), (s0, s1, owns(s0, s1))];
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let mut weights = [0f32; 3];
+                let mut inside = true;
+                for (k, (a, b, top_left)) in edges.iter().enumerate() {
+                    let w = edge(a, b, px, py) / area;
+                    if w < 0f32 || (w == 0f32 && !top_left) {
+                        inside = false;
+                        break;
+                    }
+                    weights[k] = w;
+                }
+                if !inside {
+                    continue;
+                }
+
+                let depth = weights[0] * s0.depth + weights[1] * s1.depth + weights[2] * s2.depth;
+                if self.depth_test && canvas.get_depth(x, y) < depth {
+                    continue;
+                }
+                let varying = perspective_interpolate(triangle, &screen, &weights);
+                self.shade_fragment(canvas, fragment_shader, &varying, Fragment { x, y, depth, front_facing });
+            }
+        }
+    }
+
+    // line segment in clip space, one fragment per step along the major axis
+    pub fn draw_line<V, FS>(&self, canvas: &mut Canvas, fragment_shader: &FS, line: [ClipVertex<V>; 2])
+    where
+        V: Interpolate,
+        FS: FragmentShader<V>
+    {
+        if canvas.get_width() == 0 || canvas.get_height() == 0 {
+            return;
+        }
+        let [a, b] = line;
+        let Some((a, b)) = self.clipper.clip_line(a, b) else {
+            return;
+        };
+        let (width, height) = (canvas.get_width() as f32, canvas.get_height() as f32);
+        let (s0, s1) = (to_screen(&a.0, width, height), to_screen(&b.0, width, height));
+        let steps = (s1.x - s0.x).abs().max((s1.y - s0.y).abs()).ceil().max(1f32) as u32;
+        for k in 0..=steps {
+            let t = k as f32 / steps as f32;
+            let (x, y) = (s0.x + (s1.x - s0.x) * t, s0.y + (s1.y - s0.y) * t);
+            // the guard band lets lines run past the canvas, its far edges still belong to the last pixel
+            if x < 0f32 || y < 0f32 || x > width || y > height {
+                continue;
+            }
+            let (x, y) = ((x as u32).min(width as u32 - 1), (y as u32).min(height as u32 - 1));
+            let depth = s0.depth + (s1.depth - s0.depth) * t;
+            if self.depth_test && canvas.get_depth(x, y) < depth {
+                continue;
+            }
+            let (p0, p1) = ((1f32 - t) * s0.inverse_w, t * s1.inverse_w);
+            let varying = V::interpolate(&a.1, &b.1, p1 / (p0 + p1));
+            self.shade_fragment(canvas, fragment_shader, &varying, Fragment { x, y, depth, front_facing: true });
+        }
+    }
+
+    // single pixel, dropped when outside the clip volume
+    pub fn draw_point<V, FS>(&self, canvas: &mut Canvas, fragment_shader: &FS, point: ClipVertex<V>)
+    where
+        V: Interpolate,
+        FS: FragmentShader<V>
+    {
+        if canvas.get_width() == 0 || canvas.get_height() == 0 || Clipper::new().outcode(&point.0) != 0 {
+            return;
+        }
+        let (width, height) = (canvas.get_width() as f32, canvas.get_height() as f32);
+        let s = to_screen(&point.0, width, height);
+        let (x, y) = ((s.x as u32).min(width as u32 - 1), (s.y as u32).min(height as u32 - 1));
+        if self.depth_test && canvas.get_depth(x, y) < s.depth {
+            return;
+        }
+        self.shade_fragment(canvas, fragment_shader, &point.1, Fragment { x, y, depth: s.depth, front_facing: true });
+    }
+
+    // the depth test has passed already
+    fn shade_fragment<V, FS>(&self, canvas: &mut Canvas, fragment_shader: &FS, varying: &V, fragment: Fragment)
+    where
+        FS: FragmentShader<V>
+    {
+        let Some(color) = fragment_shader.shade(varying, &fragment) else {
+            return;
+        };
+        let (x, y) = (fragment.x, fragment.y);
+        if self.depth_write {
+            canvas.set_depth(x, y, fragment.depth);
+        }
+        let blended = Canvas::blend(canvas.get_blending(), &color, &canvas.get_pixel(x, y));
+        canvas.set_pixel(x, y, blended);
+    }
+
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// perspective divide and viewport mapping
+fn to_screen(p: &Vec4, width: f32, height: f32) -> ScreenVertex {
+    let inverse_w = 1f32 / p.get_w();
+    ScreenVertex {
+        x: (p.get_x() * inverse_w + 1f32) * 0.5 * width,
+        y: (1f32 - p.get_y() * inverse_w) * 0.5 * height,
+        depth: p.get_z() * inverse_w * 0.5 + 0.5,
+        inverse_w,
+    }
+}
+
+fn edge(a: &ScreenVertex, b: &ScreenVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+// screen-space weights divided by w and renormalized, then blended pairwise
+fn perspective_interpolate<V: Interpolate>(triangle: &[ClipVertex<V>; 3], screen: &[ScreenVertex; 3], weights: &[f32; 3]) -> V {
+    let p = [
+        weights[0] * screen[0].inverse_w,
+        weights[1] * screen[1].inverse_w,
+        weights[2] * screen[2].inverse_w,
+    ];
+    let total = p[0] + p[1] + p[2];
+    let (p0, p1, p2) = (p[0] / total, p[1] / total, p[2] / total);
+    let first = p0 + p1;
+    let v01 = if first > 0f32 {
+        V::interpolate(&triangle[0].1, &triangle[1].1, p1 / first)
+    } else {
+        triangle[0].1.clone()
+    };
+    V::interpolate(&v01, &triangle[2].1, p2)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::cell::RefCell;
+
+    use super::*;
+
+    const SIZE: u32 = 8;
+
+    // records every fragment, None for the ones left of `discard_below`
+    struct Recorder<V> {
+        fragments: RefCell<Vec<(Fragment, V)>>,
+        color: Color,
+        discard_below: u32,
+    }
+
+    impl<V> Recorder<V> {
+        fn new(color: Color) -> Self {
+            Recorder { fragments: RefCell::new(Vec::new()), color, discard_below: 0 }
+        }
+        fn counts(&self) -> Vec<u32> {
+            let mut counts = vec![0; (SIZE * SIZE) as usize];
+            for (f, _) in self.fragments.borrow().iter() {
+                counts[(f.x + f.y * SIZE) as usize] += 1;
+            }
+            counts
+        }
+    }
+
+    impl<V: Clone> FragmentShader<V> for Recorder<V> {
+        fn shade(&self, varying: &V, fragment: &Fragment) -> Option<Color> {
+            self.fragments.borrow_mut().push((*fragment, varying.clone()));
+            if fragment.x < self.discard_below { None } else { Some(self.color) }
+        }
+    }
+
+    struct PassThrough;
+
+    impl VertexShader for PassThrough {
+        type Vertex = ClipVertex<f32>;
+        type Varying = f32;
+        fn shade(&self, vertex: &Self::Vertex) -> (Vec4, f32) {
+            *vertex
+        }
+    }
+
+    // screen position in buffer pixels to an NDC vertex
+    fn at(x: f32, y: f32, z: f32) -> ClipVertex<()> {
+        (Vec4::new(x / SIZE as f32 * 2f32 - 1f32, 1f32 - y / SIZE as f32 * 2f32, z, 1f32), ())
+    }
+
+    // counter-clockwise on screen, y up
+    fn triangle(z: f32) -> [ClipVertex<()>; 3] {
+        [at(1f32, 7f32, z), at(7f32, 7f32, z), at(4f32, 1f32, z)]
+    }
+
+    fn covered(canvas: &Canvas) -> usize {
+        (0..SIZE * SIZE).filter(|i| canvas.get_pixel(i % SIZE, i / SIZE) != Color::white()).count()
+    }
+
+    #[test]
+    fn culling_follows_the_front_face() {
+        let [a, b, c] = triangle(0f32);
+        let clockwise = [a, c, b];
+        let red = Recorder::new(Color::red());
+        let cases = [
+            (CullMode::Back, FrontFace::CounterClockwise, true, false),
+            (CullMode::Back, FrontFace::Clockwise, false, true),
+            (CullMode::Front, FrontFace::CounterClockwise, false, true),
+            (CullMode::Front, FrontFace::Clockwise, true, false),
+            (CullMode::None, FrontFace::CounterClockwise, true, true),
+        ];
+        for (cull_mode, front_face, draws_ccw, draws_cw) in cases {
+            let mut pipeline = Pipeline::new();
+            pipeline.set_cull_mode(cull_mode).set_front_face(front_face);
+            for (triangle, drawn) in [(triangle(0f32), draws_ccw), (clockwise, draws_cw)] {
+                let mut canvas = Canvas::new(SIZE, SIZE);
+                red.fragments.borrow_mut().clear();
+                pipeline.draw_triangle(&mut canvas, &red, triangle);
+                assert_eq!(covered(&canvas) > 0, drawn, "{:?} {:?}", cull_mode, front_face);
+                let ccw = triangle[1] == b;
+                let front = ccw == (front_face == FrontFace::CounterClockwise);
+                assert!(red.fragments.borrow().iter().all(|(f, _)| f.front_facing == front));
+            }
+        }
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        let pipeline = *Pipeline::new().set_cull_mode(CullMode::None).set_depth_test(false);
+        let recorder = Recorder::new(Color::red());
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        // a square through pixel centres split along its diagonal, and a fan around a centre
+        let (a, b, c, d) = (at(0.5, 0.5, 0f32), at(6.5, 0.5, 0f32), at(6.5, 6.5, 0f32), at(0.5, 6.5, 0f32));
+        pipeline.draw_triangle(&mut canvas, &recorder, [a, b, c]);
+        pipeline.draw_triangle(&mut canvas, &recorder, [a, c, d]);
+        let counts = recorder.counts();
+        assert!(counts.iter().all(|c| *c <= 1));
+        // the top and left edges of the square are inside, its bottom and right ones outside
+        assert_eq!(counts.iter().sum::<u32>(), 36);
+        assert!((0..6).all(|i| counts[(i + i * SIZE) as usize] == 1));
+
+        let recorder = Recorder::new(Color::red());
+        let center = at(3.5, 3.5, 0f32);
+        let ring = [
+            at(0f32, 0f32, 0f32), at(3.5, 0f32, 0f32), at(8f32, 0f32, 0f32), at(8f32, 5.5, 0f32),
+            at(8f32, 8f32, 0f32), at(2f32, 8f32, 0f32), at(0f32, 8f32, 0f32), at(0f32, 3.5, 0f32),
+        ];
+        for k in 0..ring.len() {
+            pipeline.draw_triangle(&mut canvas, &recorder, [center, ring[k], ring[(k + 1) % ring.len()]]);
+        }
+        // the fan covers the whole canvas
+        assert!(recorder.counts().iter().all(|c| *c == 1));
+    }
+
+    #[test]
+    fn depth_test_and_write() {
+        let (near, far) = (Recorder::new(Color::red()), Recorder::new(Color::green()));
+        let mut pipeline = Pipeline::new();
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        pipeline.draw_triangle(&mut canvas, &near, triangle(-0.5));
+        pipeline.draw_triangle(&mut canvas, &far, triangle(0.5));
+        assert_eq!(canvas.get_pixel(4, 5), Color::red());
+        assert_eq!(canvas.get_depth(4, 5), 0.25);
+        // rejected before shading
+        assert!(far.fragments.borrow().is_empty());
+
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        pipeline.draw_triangle(&mut canvas, &far, triangle(0.5));
+        pipeline.draw_triangle(&mut canvas, &near, triangle(-0.5));
+        assert_eq!(canvas.get_pixel(4, 5), Color::red());
+
+        // the near triangle leaves the depth buffer alone
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        pipeline.set_depth_write(false);
+        pipeline.draw_triangle(&mut canvas, &near, triangle(-0.5));
+        assert_eq!(canvas.get_depth(4, 5), f32::INFINITY);
+        pipeline.set_depth_write(true);
+        pipeline.draw_triangle(&mut canvas, &far, triangle(0.5));
+        assert_eq!(canvas.get_pixel(4, 5), Color::green());
+        assert_eq!(canvas.get_depth(4, 5), 0.75);
+
+        pipeline.set_depth_test(false);
+        pipeline.draw_triangle(&mut canvas, &near, triangle(-0.5));
+        pipeline.draw_triangle(&mut canvas, &far, triangle(0.9));
+        assert_eq!(canvas.get_pixel(4, 5), Color::green());
+    }
+
+    #[test]
+    fn varyings_are_perspective_correct() {
+        // the clip position as a varying, its projection must land on the pixel centre
+        let vertex = |x: f32, y: f32, w: f32| {
+            let p = Vec4::new(x * w, y * w, 0f32, w);
+            (p, p)
+        };
+        let triangle = [vertex(-0.9, -0.9, 1f32), vertex(0.9, -0.9, 4f32), vertex(0f32, 0.9, 2f32)];
+        let recorder = Recorder::new(Color::red());
+        Pipeline::new().draw_triangle(&mut Canvas::new(SIZE, SIZE), &recorder, triangle);
+        let fragments = recorder.fragments.borrow();
+        assert!(fragments.len() > 10);
+        let (mut low, mut high) = (f32::INFINITY, 0f32);
+        for (f, p) in fragments.iter() {
+            let x = (f.x as f32 + 0.5) / SIZE as f32 * 2f32 - 1f32;
+            let y = 1f32 - (f.y as f32 + 0.5) / SIZE as f32 * 2f32;
+            assert!((p.get_x() / p.get_w() - x).abs() < 1e-4, "{:?} {:?}", f, p);
+            assert!((p.get_y() / p.get_w() - y).abs() < 1e-4, "{:?} {:?}", f, p);
+            low = low.min(p.get_w());
+            high = high.max(p.get_w());
+        }
+        assert!(high - low > 1f32);
+    }
+
+    #[test]
+    fn none_discards_the_fragment() {
+        let mut recorder = Recorder::new(Color::red());
+        recorder.discard_below = SIZE / 2;
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        Pipeline::new().draw_triangle(&mut canvas, &recorder, triangle(0f32));
+        assert!(recorder.fragments.borrow().iter().any(|(f, _)| f.x < SIZE / 2));
+        for y in 0..SIZE {
+            for x in 0..SIZE / 2 {
+                assert_eq!(canvas.get_pixel(x, y), Color::white());
+                assert_eq!(canvas.get_depth(x, y), f32::INFINITY);
+            }
+        }
+        assert_eq!(canvas.get_pixel(5, 5), Color::red());
+    }
+
+    #[test]
+    fn triangle_crossing_the_near_plane() {
+        let recorder = Recorder::new(Color::red());
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        let mut behind = triangle(0f32);
+        behind[2].0 = Vec4::new(0f32, 0.75, -3f32, 1f32);
+        Pipeline::new().draw_triangle(&mut canvas, &recorder, behind);
+        let fragments = recorder.fragments.borrow();
+        assert!(!fragments.is_empty());
+        assert!(fragments.iter().all(|(f, _)| (0f32..=0.5).contains(&f.depth)));
+        // only the part in front of the near plane, not reaching up to the apex
+        assert!(fragments.iter().all(|(f, _)| f.y >= 3));
+        let mut gone = triangle(-3f32);
+        gone[0].0 = Vec4::new(-0.75, -0.75, -2f32, 1f32);
+        let recorder = Recorder::new(Color::red());
+        Pipeline::new().draw_triangle(&mut canvas, &recorder, gone);
+        assert!(recorder.fragments.borrow().is_empty());
+    }
+
+    #[test]
+    fn draw_uses_the_vertex_shader() {
+        let vertices: Vec<ClipVertex<f32>> = triangle(0f32).iter().zip([0f32, 1f32, 2f32]).map(|((p, _), v)| (*p, v)).collect();
+        let recorder = Recorder::new(Color::red());
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        Pipeline::new().draw(&mut canvas, &PassThrough, &recorder, &vertices);
+        assert!(recorder.fragments.borrow().iter().all(|(_, v)| (0f32..=2f32).contains(v)));
+        assert_eq!(recorder.counts().iter().sum::<u32>() as usize, covered(&canvas));
+    }
+
+    #[test]
+    fn lines_and_points() {
+        let recorder = Recorder::new(Color::red());
+        let mut canvas = Canvas::new(SIZE, SIZE);
+        let pipeline = Pipeline::new();
+        pipeline.draw_line(&mut canvas, &recorder, [(Vec4::new(-2f32, 0f32, 0f32, 1f32), 0f32), (Vec4::new(2f32, 0f32, 0f32, 1f32), 1f32)]);
+        assert!((0..SIZE).all(|x| canvas.get_pixel(x, SIZE / 2) == Color::red()));
+        pipeline.draw_point(&mut canvas, &recorder, (Vec4::new(-0.5, 0.5, 0f32, 1f32), 0f32));
+        assert_eq!(canvas.get_pixel(2, 2), Color::red());
+        pipeline.draw_point(&mut canvas, &recorder, (Vec4::new(0f32, 0f32, 2f32, 1f32), 0f32));
+        assert_eq!(covered(&canvas), SIZE as usize + 1);
+    }
+
+    #[test]
+    fn empty_canvas() {
+        let recorder = Recorder::new(Color::red());
+        let mut canvas = Canvas::new(0, 0);
+        let pipeline = Pipeline::new();
+        pipeline.draw_triangle(&mut canvas, &recorder, triangle(0f32));
+        pipeline.draw_line(&mut canvas, &recorder, [at(0f32, 0f32, 0f32), at(4f32, 4f32, 0f32)]);
+        pipeline.draw_point(&mut canvas, &recorder, at(0f32, 0f32, 0f32));
+        assert!(recorder.fragments.borrow().is_empty());
+    }
+
+}
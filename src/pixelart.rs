@@ -0,0 +1,378 @@
+use super::canvas::{Canvas, Color};
+
+// integer upscalers for pixel art, they only ever look at exact (or YUV-close) color matches
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelScaler {
+    // AdvanceMAME Scale2x
+    Scale2x,
+    // AdvanceMAME Scale3x
+    Scale3x,
+    // Johnston's EPX, Scale2x plus the three-equal-neighbors rule
+    Epx,
+    // Hyllian's xBR level 1, 2x
+    Xbr,
+    // Stepin's hq2x
+    Hq2x,
+}
+
+// RGBA packed as 0xAABBGGRR for cheap equality tests
+struct Pixels {
+    data: Vec<u32>,
+    width: i32,
+    height: i32,
+}
+
+impl Pixels {
+
+    fn load(canvas: &Canvas) -> Self {
+        let (width, height) = (canvas.get_width(), canvas.get_height());
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as u32 {
+            for x in 0..width as u32 {
+                let c = canvas.get_pixel(x, y);
+                data.push(pack(c.get_red(), c.get_green(), c.get_blue(), c.get_alpha()));
+            }
+        }
+        Pixels { data, width, height }
+    }
+
+    // clamp-to-edge addressing
+    fn at(&self, x: i32, y: i32) -> u32 {
+        let x = x.clamp(0, self.width - 1);
+        let y = y.clamp(0, self.height - 1);
+        self.data[(x + y * self.width) as usize]
+    }
+
+}
+
+fn pack(red: u8, green: u8, blue: u8, alpha: u8) -> u32 {
+    red as u32 | (green as u32) << 8 | (blue as u32) << 16 | (alpha as u32) << 24
+}
+
+fn channels(p: u32) -> [u32; 4] {
+    [p & 0xff, (p >> 8) & 0xff, (p >> 16) & 0xff, p >> 24]
+}
+
+fn unpack(p: u32) -> Color {
+    let [r, g, b, a] = channels(p);
+    Color::from_rgba(r as u8, g as u8, b as u8, a as u8)
+}
+
+// weighted average of packed colors
+fn mix(colors: &[(u32, u32)]) -> u32 {
+    let total: u32 = colors.iter().map(|(_, w)| w).sum();
+    let mut acc = [0u32; 4];
+    for (p, w) in colors {
+        for (a, c) in acc.iter_mut().zip(channels(*p)) {
+            *a += c * w;
+        }
+    }
+    let [r, g, b, a] = acc.map(|v| (v + total / 2) / total);
+    pack(r as u8, g as u8, b as u8, a as u8)
+}
+
+// the conversion used by hqx (and most xBR ports), alpha is ignored
+fn yuv(p: u32) -> (f32, f32, f32) {
+    let [r, g, b, _] = channels(p).map(|c| c as f32);
+    (0.299 * r + 0.587 * g + 0.114 * b, -0.169 * r - 0.331 * g + 0.5 * b, 0.5 * r - 0.419 * g - 0.081 * b)
+}
+
+// xBR weighted distance
+fn distance(a: u32, b: u32) -> f32 {
+    let (ya, ua, va) = yuv(a);
+    let (yb, ub, vb) = yuv(b);
+    48.0 * (ya - yb).abs() + 7.0 * (ua - ub).abs() + 6.0 * (va - vb).abs()
+}
+
+// hqx similarity thresholds
+fn different(a: u32, b: u32) -> bool {
+    if a == b {
+        return false;
+    }
+    let (ya, ua, va) = yuv(a);
+    let (yb, ub, vb) = yuv(b);
+    (ya - yb).abs() > 48.0 || (ua - ub).abs() > 7.0 || (va - vb).abs() > 6.0
+}
+
+impl PixelScaler {
+
+    pub fn factor(&self) -> u32 {
+        match self {
+            PixelScaler::Scale3x => 3,
+            _ => 2,
+        }
+    }
+
+    pub fn scale(&self, canvas: &Canvas) -> Canvas {
+        let factor = self.factor();
+        let mut target = Canvas::new(canvas.get_width() as u32 * factor, canvas.get_height() as u32 * factor);
+        self.scale_into(canvas, &mut target);
+        target
+    }
+
+    // target must be exactly `factor` times larger than source
+    pub fn scale_into(&self, source: &Canvas, target: &mut Canvas) {
+
+        let factor = self.factor() as i32;
+        assert!(
+            target.get_width() == source.get_width() * factor && target.get_height() == source.get_height() * factor,
+            "Target canvas must be exactly {}x the source canvas.", factor
+        );
+
+        let pixels = Pixels::load(source);
+        let mut block = [0u32; 9];
+
+        for y in 0..pixels.height {
+            for x in 0..pixels.width {
+                let p = |dx: i32, dy: i32| pixels.at(x + dx, y + dy);
+                match self {
+                    PixelScaler::Scale2x => scale2x(&p, &mut block),
+                    PixelScaler::Scale3x => scale3x(&p, &mut block),
+                    PixelScaler::Epx => epx(&p, &mut block),
+                    PixelScaler::Xbr => xbr(&p, &mut block),
+                    PixelScaler::Hq2x => hq2x(&p, &mut block),
+                }
+                for by in 0..factor {
+                    for bx in 0..factor {
+                        let color = unpack(block[(bx + by * factor) as usize]);
+                        target.set_pixel((x * factor + bx) as u32, (y * factor + by) as u32, color);
+                    }
+                }
+            }
+        }
+
+    }
+
+}
+
+//  B
+// DEF
+//  H
+fn scale2x<P: Fn(i32, i32) -> u32>(p: &P, out: &mut [u32; 9]) {
+    let (b, d, e, f, h) = (p(0, -1), p(-1, 0), p(0, 0), p(1, 0), p(0, 1));
+    if b != h && d != f {
+        out[0] = if d == b { d } else { e };
+        out[1] = if b == f { f } else { e };
+        out[2] = if d == h { d } else { e };
+        out[3] = if h == f { f } else { e };
+    } else {
+        out[..4].fill(e);
+    }
+}
+
+// ABC
+// DEF
+// GHI
+fn scale3x<P: Fn(i32, i32) -> u32>(p: &P, out: &mut [u32; 9]) {
+    let (a, b, c) = (p(-1, -1), p(0, -1), p(1, -1));
+    let (d, e, f) = (p(-1, 0), p(0, 0), p(1, 0));
+    let (g, h, i) = (p(-1, 1), p(0, 1), p(1, 1));
+    if b != h && d != f {
+        out[0] = if d == b { d } else { e };
+        out[1] = if (d == b && e != c) || (b == f && e != a) { b } else { e };
+        out[2] = if b == f { f } else { e };
+        out[3] = if (d == b && e != g) || (d == h && e != a) { d } else { e };
+        out[4] = e;
+        out[5] = if (b == f && e != i) || (h == f && e != c) { f } else { e };
+        out[6] = if d == h { d } else { e };
+        out[7] = if (d == h && e != i) || (h == f && e != g) { h } else { e };
+        out[8] = if h == f { f } else { e };
+    } else {
+        out.fill(e);
+    }
+}
+
+//  A
+// CPB
+//  D
+fn epx<P: Fn(i32, i32) -> u32>(p: &P, out: &mut [u32; 9]) {
+    let (a, b, c, d, center) = (p(0, -1), p(1, 0), p(-1, 0), p(0, 1), p(0, 0));
+    out[..4].fill(center);
+    let neighbors = [a, b, c, d];
+    let three_equal = neighbors.iter().any(|n| neighbors.iter().filter(|m| *m == n).count() >= 3);
+    if three_equal {
+        return;
+    }
+    if c == a { out[0] = a; }
+    if a == b { out[1] = b; }
+    if d == c { out[2] = c; }
+    if b == d { out[3] = d; }
+}
+
+fn xbr<P: Fn(i32, i32) -> u32>(p: &P, out: &mut [u32; 9]) {
+    let e = p(0, 0);
+    // each output corner is the bottom-right case mirrored by (sx, sy)
+    for (slot, (sx, sy)) in [(0usize, (-1, -1)), (1, (1, -1)), (2, (-1, 1)), (3, (1, 1))] {
+        let q = |dx: i32, dy: i32| p(dx * sx, dy * sy);
+        let (b, c, d, f, g, h, i) = (q(0, -1), q(1, -1), q(-1, 0), q(1, 0), q(-1, 1), q(0, 1), q(1, 1));
+        let (f4, i4, h5, i5) = (q(2, 0), q(2, 1), q(0, 2), q(1, 2));
+        let wd1 = distance(e, c) + distance(e, g) + distance(i, f4) + distance(i, h5) + 4.0 * distance(h, f);
+        let wd2 = distance(h, d) + distance(h, i5) + distance(f, i4) + distance(f, b) + 4.0 * distance(e, i);
+        out[slot] = if wd1 < wd2 {
+            let edge = if distance(e, f) <= distance(e, h) { f } else { h };
+            mix(&[(e, 1), (edge, 1)])
+        } else {
+            e
+        };
+    }
+}
+
+// hq2x rule of the top-left output pixel for every pattern of neighbors that differ
+// from the center (bit 0 A, 1 B, 2 C, 3 D, 4 F, 5 G, 6 H, 7 I), the other three pixels
+// use the same table in a mirrored frame. Codes are decoded in hq2x below.
+const HQ2X: [u8; 256] = [
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 10,  7,  5,  3, 12,  8,
+     4,  4,  6, 13,  4,  4,  6, 13,  5,  3,  7,  7,  5,  3,  1,  7,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 12,  8,  5,  3, 11,  9,
+     4,  4,  6, 13,  4,  4,  6, 13,  5,  3, 11,  7,  5,  3,  1,  9,
+     4,  4,  6,  2,  4,  4,  6,  2,  5, 14,  7,  7,  5, 14, 11,  7,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 11,  7,  5,  3, 11,  7,
+     4,  4,  6,  2,  4,  4,  6,  2,  5, 14,  1,  7,  5, 14,  1,  9,
+     4,  4,  6,  2,  4,  4,  6, 13,  5,  3, 11,  7,  5, 14,  1,  9,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 10,  7,  5,  3, 12,  8,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 11,  7,  5,  3, 11,  7,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 12,  8,  5,  3, 11,  9,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 11,  8,  5,  3,  1,  9,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 11,  7,  5,  3, 11,  8,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 11,  7,  5,  3,  1,  7,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3, 11,  7,  5,  3,  1,  9,
+     4,  4,  6,  2,  4,  4,  6,  2,  5,  3,  1,  7,  5,  3,  1,  9,
+];
+
+// A B C
+// D E F
+// G H I
+fn hq2x<P: Fn(i32, i32) -> u32>(p: &P, out: &mut [u32; 9]) {
+    let e = p(0, 0);
+    for (slot, (sx, sy)) in [(0usize, (-1, -1)), (1, (1, -1)), (2, (-1, 1)), (3, (1, 1))] {
+        // mirrored so that A is always the neighbor at this output pixel's corner
+        let q = |dx: i32, dy: i32| p(-dx * sx, -dy * sy);
+        let (a, b, c) = (q(-1, -1), q(0, -1), q(1, -1));
+        let (d, f) = (q(-1, 0), q(1, 0));
+        let (g, h, i) = (q(-1, 1), q(0, 1), q(1, 1));
+        let pattern = [a, b, c, d, f, g, h, i].iter()
+            .enumerate()
+            .fold(0, |bits, (k, n)| if different(e, *n) { bits | 1 << k } else { bits });
+        let same = |x: u32, y: u32| !different(x, y);
+        out[slot] = match HQ2X[pattern] {
+            1 => mix(&[(e, 3), (a, 1)]),
+            2 => mix(&[(e, 3), (d, 1)]),
+            3 => mix(&[(e, 3), (b, 1)]),
+            4 => mix(&[(e, 2), (d, 1), (b, 1)]),
+            5 => mix(&[(e, 2), (a, 1), (b, 1)]),
+            6 => mix(&[(e, 2), (a, 1), (d, 1)]),
+            // the diagonal through the corner is an edge when B and D match
+            7 => if same(b, d) { mix(&[(e, 2), (d, 1), (b, 1)]) } else { e },
+            8 => if same(b, d) { mix(&[(e, 2), (d, 3), (b, 3)]) } else { e },
+            9 => if same(b, d) { mix(&[(e, 14), (d, 1), (b, 1)]) } else { e },
+            10 => if same(b, d) { mix(&[(e, 2), (d, 1), (b, 1)]) } else { mix(&[(e, 3), (a, 1)]) },
+            11 => if same(b, d) { mix(&[(e, 6), (d, 1), (b, 1)]) } else { mix(&[(e, 3), (a, 1)]) },
+            12 => if same(b, d) { mix(&[(e, 2), (d, 3), (b, 3)]) } else { mix(&[(e, 3), (a, 1)]) },
+            // shallow edges continuing through the neighboring corner
+            13 => if same(b, f) { mix(&[(e, 5), (b, 2), (d, 1)]) } else { mix(&[(e, 3), (d, 1)]) },
+            _ => if same(d, h) { mix(&[(e, 5), (d, 2), (b, 1)]) } else { mix(&[(e, 3), (b, 1)]) },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const SCALERS: [PixelScaler; 5] = [
+        PixelScaler::Scale2x, PixelScaler::Scale3x, PixelScaler::Epx, PixelScaler::Xbr, PixelScaler::Hq2x,
+    ];
+
+    fn image(width: u32, height: u32, color: impl Fn(u32, u32) -> Color) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                canvas.set_pixel(x, y, color(x, y));
+            }
+        }
+        canvas
+    }
+
+    fn block(canvas: &Canvas, x: u32, y: u32) -> [Color; 4] {
+        [canvas.get_pixel(2 * x, 2 * y), canvas.get_pixel(2 * x + 1, 2 * y), canvas.get_pixel(2 * x, 2 * y + 1), canvas.get_pixel(2 * x + 1, 2 * y + 1)]
+    }
+
+    #[test]
+    fn flat_images_stay_flat() {
+        let flat = image(3, 3, |_, _| Color::from_rgba(10, 200, 30, 128));
+        for scaler in SCALERS {
+            let scaled = scaler.scale(&flat);
+            let size = 3 * scaler.factor();
+            assert_eq!(scaled.get_width() as u32, size);
+            for y in 0..size {
+                for x in 0..size {
+                    assert_eq!(scaled.get_pixel(x, y), Color::from_rgba(10, 200, 30, 128), "{:?}", scaler);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scale2x_rounds_a_staircase() {
+        // white above the diagonal
+        let stairs = image(3, 3, |x, y| if x > y { Color::white() } else { Color::black() });
+        let scaled = PixelScaler::Scale2x.scale(&stairs);
+        // the center pixel takes the white of its top and right neighbors in its top-right corner
+        assert_eq!(block(&scaled, 1, 1), [Color::black(), Color::white(), Color::black(), Color::black()]);
+    }
+
+    #[test]
+    fn hq2x_isolated_dot() {
+        let dot = image(3, 3, |x, y| if (x, y) == (1, 1) { Color::red() } else { Color::black() });
+        let scaled = PixelScaler::Hq2x.scale(&dot);
+        // every neighbor differs, the matching edge neighbors pull each corner by 2/16
+        assert_eq!(block(&scaled, 1, 1), [Color::from_rgb(223, 0, 0); 4]);
+        for (x, y) in [(0, 0), (1, 0), (2, 1), (2, 2)] {
+            assert_eq!(block(&scaled, x, y), [Color::black(); 4]);
+        }
+    }
+
+    #[test]
+    fn hq2x_checkerboard() {
+        let board = image(6, 6, |x, y| if (x + y) % 2 == 0 { Color::white() } else { Color::black() });
+        let scaled = PixelScaler::Hq2x.scale(&board);
+        // four differing edge neighbors that match each other: (6 E + B + D) / 8
+        assert_eq!(block(&scaled, 2, 2), [Color::from_rgb(191, 191, 191); 4]);
+        assert_eq!(block(&scaled, 3, 2), [Color::from_rgb(64, 64, 64); 4]);
+    }
+
+    #[test]
+    fn hq2x_is_symmetric() {
+        // a few colors scattered by a xorshift, enough to reach most patterns
+        let mut state = 0x9e3779b9u32;
+        let colors = [Color::black(), Color::white(), Color::red(), Color::from_rgb(250, 10, 10), Color::blue()];
+        let cells: Vec<Color> = (0..144).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            colors[(state % 5) as usize]
+        }).collect();
+        let source = image(12, 12, |x, y| cells[(x + y * 12) as usize]);
+        let transposed = image(12, 12, |x, y| cells[(y + x * 12) as usize]);
+        let mirrored = image(12, 12, |x, y| cells[(11 - x + y * 12) as usize]);
+        let (scaled, scaled_transposed, scaled_mirrored) = (
+            PixelScaler::Hq2x.scale(&source),
+            PixelScaler::Hq2x.scale(&transposed),
+            PixelScaler::Hq2x.scale(&mirrored),
+        );
+        for y in 0..24 {
+            for x in 0..24 {
+                assert_eq!(scaled.get_pixel(x, y), scaled_transposed.get_pixel(y, x));
+                assert_eq!(scaled.get_pixel(x, y), scaled_mirrored.get_pixel(23 - x, y));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "exactly 3x")]
+    fn target_size_is_checked() {
+        PixelScaler::Scale3x.scale_into(&Canvas::new(2, 2), &mut Canvas::new(4, 4));
+    }
+
+}
@@ -0,0 +1,363 @@
+use std::f32::consts::TAU;
+
+use super::mathsf::{Vec2, Vec3};
+
+// PCG32 (XSH-RR), O'Neill 2014. Small, fast and fully deterministic across platforms,
+// the same seed and stream always give the same sequence.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+    increment: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+impl Rng {
+
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0xda3e39cb94b95bdb)
+    }
+
+    // different streams with the same seed are independent sequences
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Rng { state: 0, increment: (stream << 1) | 1 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u32() & 1 != 0
+    }
+
+    // true with probability p
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.next_f32() < p
+    }
+
+    // uniform in [low, high), unbiased (Lemire)
+    pub fn range_u32(&mut self, low: u32, high: u32) -> u32 {
+        assert!(low < high, "Empty random range.");
+        let span = high - low;
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let m = self.next_u32() as u64 * span as u64;
+            if (m as u32) >= threshold {
+                return low + (m >> 32) as u32;
+            }
+        }
+    }
+
+    pub fn range_i32(&mut self, low: i32, high: i32) -> i32 {
+        assert!(low < high, "Empty random range.");
+        let span = (high as i64 - low as i64) as u32;
+        (low as i64 + self.range_u32(0, span) as i64) as i32
+    }
+
+    pub fn range_usize(&mut self, low: usize, high: usize) -> usize {
+        assert!(low < high && high - low <= u32::MAX as usize, "Invalid random range.");
+        low + self.range_u32(0, (high - low) as u32) as usize
+    }
+
+    // uniform in [low, high)
+    pub fn range_f32(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next_f32()
+    }
+
+    // standard normal distribution scaled by `deviation` (Box-Muller)
+    pub fn gaussian(&mut self, mean: f32, deviation: f32) -> f32 {
+        let u = 1f32 - self.next_f32();
+        let v = self.next_f32();
+        mean + deviation * (-2f32 * u.ln()).sqrt() * (TAU * v).cos()
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        // Fisher-Yates
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range_usize(0, i + 1));
+        }
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(&items[self.range_usize(0, items.len())])
+    }
+
+    // uniform on the surface of the unit sphere
+    pub fn unit_sphere(&mut self) -> Vec3 {
+        let z = 1f32 - 2f32 * self.next_f32();
+        let r = (1f32 - z * z).max(0f32).sqrt();
+        let phi = TAU * self.next_f32();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    // uniform inside the unit ball
+    pub fn unit_ball(&mut self) -> Vec3 {
+        self.unit_sphere() * self.next_f32().cbrt()
+    }
+
+    // uniform on the hemisphere around `normal`
+    pub fn hemisphere(&mut self, normal: &Vec3) -> Vec3 {
+        let v = self.unit_sphere();
+        if v.dot(normal) < 0f32 { -v } else { v }
+    }
+
+    // density proportional to the cosine with `normal` (Malley), for diffuse bounces
+    pub fn cosine_hemisphere(&mut self, normal: &Vec3) -> Vec3 {
+        let d = self.unit_disk();
+        let z = (1f32 - d.length_squared()).max(0f32).sqrt();
+        let (tangent, bitangent) = basis(&normal.normalize());
+        tangent * d.get_x() + bitangent * d.get_y() + normal.normalize() * z
+    }
+
+    // uniform inside the unit disk
+    pub fn unit_disk(&mut self) -> Vec2 {
+        let r = self.next_f32().sqrt();
+        let phi = TAU * self.next_f32();
+        Vec2::new(r * phi.cos(), r * phi.sin())
+    }
+
+    // uniform inside the triangle
+    pub fn triangle(&mut self, a: &Vec3, b: &Vec3, c: &Vec3) -> Vec3 {
+        let (mut u, mut v) = (self.next_f32(), self.next_f32());
+        // folds the other half of the parallelogram back
+        if u + v > 1f32 {
+            u = 1f32 - u;
+            v = 1f32 - v;
+        }
+        *a + (*b - *a) * u + (*c - *a) * v
+    }
+
+    // Bridson's Poisson-disk sampling in [0, width) x [0, height): no two points closer
+    // than `radius`, `attempts` candidates per active point (30 is typical)
+    pub fn poisson_disk(&mut self, width: f32, height: f32, radius: f32, attempts: u32) -> Vec<Vec2> {
+        assert!(radius > 0f32, "Poisson-disk radius must be positive.");
+        if width <= 0f32 || height <= 0f32 {
+            return Vec::new();
+        }
+        // a grid cell fits at most one point
+        let cell = radius / std::f32::consts::SQRT_2;
+        let (columns, rows) = ((width / cell).ceil() as usize, (height / cell).ceil() as usize);
+        let mut grid: Vec<Option<usize>> = vec![None; columns * rows];
+        let cell_of = |p: &Vec2| {
+            ((p.get_x() / cell) as usize).min(columns - 1) + ((p.get_y() / cell) as usize).min(rows - 1) * columns
+        };
+        let mut points = Vec::new();
+        let mut active = Vec::new();
+
+        let first = Vec2::new(self.range_f32(0f32, width), self.range_f32(0f32, height));
+        grid[cell_of(&first)] = Some(0);
+        points.push(first);
+        active.push(0);
+
+        while !active.is_empty() {
+            let slot = self.range_usize(0, active.len());
+            let center = points[active[slot]];
+            let mut found = false;
+            for _ in 0..attempts {
+                // uniform in the annulus [radius, 2 radius]
+                let r = (radius * radius * (1f32 + 3f32 * self.next_f32())).sqrt();
+                let phi = TAU * self.next_f32();
+                let candidate = center + Vec2::new(r * phi.cos(), r * phi.sin());
+                let (x, y) = (candidate.get_x(), candidate.get_y());
+                if x < 0f32 || y < 0f32 || x >= width || y >= height {
+                    continue;
+                }
+                let (column, row) = ((x / cell) as usize, (y / cell) as usize);
+                let far_enough = (row.saturating_sub(2)..(row + 3).min(rows)).all(|r| {
+                    (column.saturating_sub(2)..(column + 3).min(columns)).all(|c| {
+                        grid[c + r * columns].is_none_or(|i| points[i].distance_squared(&candidate) >= radius * radius)
+                    })
+                });
+                if far_enough {
+                    grid[cell_of(&candidate)] = Some(points.len());
+                    active.push(points.len());
+                    points.push(candidate);
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                active.swap_remove(slot);
+            }
+        }
+        points
+    }
+
+}
+
+// orthonormal tangent and bitangent for a unit normal (Duff et al. 2017)
+fn basis(n: &Vec3) -> (Vec3, Vec3) {
+    let sign = 1f32.copysign(n.get_z());
+    let a = -1f32 / (sign + n.get_z());
+    let b = n.get_x() * n.get_y() * a;
+    (
+        Vec3::new(1f32 + sign * n.get_x() * n.get_x() * a, sign * b, -sign * n.get_x()),
+        Vec3::new(b, sign + n.get_y() * n.get_y() * a, -n.get_y())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn matches_the_pcg32_reference() {
+        // pcg32-demo, pcg32_srandom_r(&rng, 42, 54)
+        let mut rng = Rng::with_stream(42, 54);
+        let expected = [0xa15c02b7u32, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e];
+        for e in expected {
+            assert_eq!(rng.next_u32(), e);
+        }
+    }
+
+    #[test]
+    fn seeds_and_streams() {
+        let sequence = |mut rng: Rng| (0..64).map(|_| rng.next_u32()).collect::<Vec<u32>>();
+        assert_eq!(sequence(Rng::new(7)), sequence(Rng::new(7)));
+        assert_ne!(sequence(Rng::new(7)), sequence(Rng::new(8)));
+        assert_ne!(sequence(Rng::with_stream(7, 1)), sequence(Rng::with_stream(7, 2)));
+        let mut a = Rng::new(3);
+        let mut b = a.clone();
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    #[test]
+    fn ranges_stay_in_bounds() {
+        let mut rng = Rng::new(1);
+        let mut seen = [false; 7];
+        for _ in 0..1000 {
+            let v = rng.range_u32(10, 17);
+            assert!((10..17).contains(&v));
+            seen[(v - 10) as usize] = true;
+            let v = rng.range_i32(-3, 2);
+            assert!((-3..2).contains(&v));
+            let v = rng.range_f32(-1f32, 1f32);
+            assert!((-1f32..1f32).contains(&v));
+            assert!((0f32..1f32).contains(&rng.next_f32()));
+            assert!((0f64..1f64).contains(&rng.next_f64()));
+        }
+        assert!(seen.iter().all(|s| *s));
+        assert_eq!(rng.range_u32(5, 6), 5);
+        // the full span doesn't overflow
+        let (mut negative, mut positive) = (false, false);
+        for _ in 0..100 {
+            let v = rng.range_i32(i32::MIN, i32::MAX);
+            assert!(v < i32::MAX);
+            negative |= v < 0;
+            positive |= v > 0;
+        }
+        assert!(negative && positive);
+        assert!(rng.range_u32(0, u32::MAX) < u32::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty random range")]
+    fn empty_range() {
+        Rng::new(0).range_i32(4, 4);
+    }
+
+    #[test]
+    fn uniform_and_gaussian_moments() {
+        let mut rng = Rng::new(11);
+        let n = 20000;
+        let mean = (0..n).map(|_| rng.next_f32()).sum::<f32>() / n as f32;
+        assert!((mean - 0.5).abs() < 0.01);
+        let samples: Vec<f32> = (0..n).map(|_| rng.gaussian(2f32, 3f32)).collect();
+        let mean = samples.iter().sum::<f32>() / n as f32;
+        let variance = samples.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / n as f32;
+        assert!((mean - 2f32).abs() < 0.1);
+        assert!((variance.sqrt() - 3f32).abs() < 0.1);
+        let hits = (0..n).filter(|_| rng.chance(0.25)).count();
+        assert!((hits as f32 / n as f32 - 0.25).abs() < 0.02);
+    }
+
+    #[test]
+    fn shuffle_and_choose() {
+        let mut rng = Rng::new(5);
+        let mut items: Vec<u32> = (0..50).collect();
+        rng.shuffle(&mut items);
+        assert_ne!(items, (0..50).collect::<Vec<u32>>());
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<u32>>());
+        assert!(rng.choose::<u32>(&[]).is_none());
+        assert!(items.contains(rng.choose(&items).unwrap()));
+    }
+
+    #[test]
+    fn directions() {
+        let mut rng = Rng::new(9);
+        let normal = Vec3::new(1f32, 2f32, -2f32).normalize();
+        let mut sum = Vec3::zero();
+        for _ in 0..2000 {
+            let v = rng.unit_sphere();
+            assert!((v.length() - 1f32).abs() < 1e-5);
+            sum += v;
+            let v = rng.hemisphere(&normal);
+            assert!((v.length() - 1f32).abs() < 1e-5 && v.dot(&normal) >= 0f32);
+            let v = rng.cosine_hemisphere(&normal);
+            assert!((v.length() - 1f32).abs() < 1e-4 && v.dot(&normal) >= -1e-6);
+            assert!(rng.unit_ball().length() <= 1f32 + 1e-6);
+            assert!(rng.unit_disk().length() <= 1f32 + 1e-6);
+        }
+        // no preferred direction
+        assert!(sum.length() / 2000f32 < 0.05);
+        // the basis is orthonormal for normals down the z axis too
+        let down = Vec3::new(0f32, 0f32, -1f32);
+        assert!((0..100).all(|_| rng.cosine_hemisphere(&down).dot(&down) >= -1e-6));
+    }
+
+    #[test]
+    fn triangle_points_are_inside() {
+        let mut rng = Rng::new(2);
+        let (a, b, c) = (Vec3::zero(), Vec3::new(2f32, 0f32, 0f32), Vec3::new(0f32, 1f32, 0f32));
+        for _ in 0..1000 {
+            let p = rng.triangle(&a, &b, &c);
+            assert!(p.get_x() >= 0f32 && p.get_y() >= 0f32 && p.get_x() / 2f32 + p.get_y() <= 1f32 + 1e-6);
+            assert_eq!(p.get_z(), 0f32);
+        }
+    }
+
+    #[test]
+    fn poisson_disk_keeps_the_minimum_distance() {
+        let mut rng = Rng::new(4);
+        let radius = 0.7;
+        let points = rng.poisson_disk(10f32, 6f32, radius, 30);
+        // dense packing, more than half the hexagonal limit
+        assert!(points.len() > 60, "{}", points.len());
+        for (i, p) in points.iter().enumerate() {
+            assert!(p.get_x() >= 0f32 && p.get_x() < 10f32 && p.get_y() >= 0f32 && p.get_y() < 6f32);
+            for q in &points[i + 1..] {
+                assert!(p.distance(q) >= radius);
+            }
+        }
+        assert!(rng.poisson_disk(0f32, 5f32, 1f32, 30).is_empty());
+        let mut again = Rng::new(4);
+        assert_eq!(again.poisson_disk(10f32, 6f32, radius, 30), points);
+    }
+
+}
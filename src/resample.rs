@@ -0,0 +1,265 @@
+use std::f32::consts::PI;
+
+use super::canvas::{Canvas, Color};
+
+// reconstruction filters, widened automatically when minifying
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResampleFilter {
+    Box,
+    Bilinear,
+    // Catmull-Rom (B = 0, C = 1/2)
+    Bicubic,
+    // Mitchell-Netravali (B = 1/3, C = 1/3)
+    Mitchell,
+    // windowed sinc with `a` lobes, usually 2 or 3
+    Lanczos(u32),
+}
+
+impl ResampleFilter {
+
+    pub fn support(&self) -> f32 {
+        match self {
+            ResampleFilter::Box => 0.5,
+            ResampleFilter::Bilinear => 1.0,
+            ResampleFilter::Bicubic | ResampleFilter::Mitchell => 2.0,
+            ResampleFilter::Lanczos(a) => (*a).max(1) as f32,
+        }
+    }
+
+    pub fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ResampleFilter::Box => if x <= 0.5 { 1.0 } else { 0.0 },
+            ResampleFilter::Bilinear => (1.0 - x).max(0.0),
+            ResampleFilter::Bicubic => cubic(x, 0.0, 0.5),
+            ResampleFilter::Mitchell => cubic(x, 1.0 / 3.0, 1.0 / 3.0),
+            ResampleFilter::Lanczos(a) => {
+                let a = (*a).max(1) as f32;
+                if x < a { sinc(x) * sinc(x / a) } else { 0.0 }
+            }
+        }
+    }
+
+}
+
+fn cubic(x: f32, b: f32, c: f32) -> f32 {
+    if x < 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+    } else if x < 2.0 {
+        ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+    } else {
+        0.0
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        let x = PI * x;
+        x.sin() / x
+    }
+}
+
+// source taps and normalized weights for every destination pixel of one axis
+struct Contribution {
+    first: i32,
+    weights: Vec<f32>,
+}
+
+fn contributions(src: u32, dst: u32, filter: ResampleFilter) -> Vec<Contribution> {
+    let scale = dst as f32 / src as f32;
+    // when minifying the filter is stretched so it integrates over the whole footprint
+    let stretch = if scale < 1.0 { 1.0 / scale } else { 1.0 };
+    let support = filter.support() * stretch;
+    (0..dst)
+        .map(|i| {
+            let center = (i as f32 + 0.5) / scale - 0.5;
+            let first = (center - support).floor() as i32;
+            let last = (center + support).ceil() as i32;
+            let mut weights: Vec<f32> = (first..=last)
+                .map(|j| filter.weight((j as f32 - center) / stretch))
+                .collect();
+            let sum: f32 = weights.iter().sum();
+            if sum.abs() > f32::EPSILON {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            } else {
+                // degenerate footprint, fall back to the nearest texel
+                weights.iter_mut().for_each(|w| *w = 0.0);
+                let nearest = (center.round() as i32 - first).clamp(0, weights.len() as i32 - 1);
+                weights[nearest as usize] = 1.0;
+            }
+            Contribution { first, weights }
+        })
+        .collect()
+}
+
+pub fn resample(canvas: &Canvas, width: u32, height: u32, filter: ResampleFilter) -> Canvas {
+    let mut target = Canvas::new(width, height);
+    resample_into(canvas, &mut target, filter);
+    target
+}
+
+// resizes `source` to fill `target`, filtering premultiplied RGBA in sRGB space
+pub fn resample_into(source: &Canvas, target: &mut Canvas, filter: ResampleFilter) {
+
+    let (sw, sh) = (source.get_width() as u32, source.get_height() as u32);
+    let (dw, dh) = (target.get_width() as u32, target.get_height() as u32);
+    if sw == 0 || sh == 0 || dw == 0 || dh == 0 {
+        return;
+    }
+
+    let mut pixels: Vec<[f32; 4]> = Vec::with_capacity((sw * sh) as usize);
+    for y in 0..sh {
+        for x in 0..sw {
+            let c = source.get_pixel(x, y);
+            let a = c.get_alpha() as f32 / 255.0;
+            pixels.push([c.get_red() as f32 * a, c.get_green() as f32 * a, c.get_blue() as f32 * a, c.get_alpha() as f32]);
+        }
+    }
+
+    // horizontal pass: sw x sh -> dw x sh
+    let columns = contributions(sw, dw, filter);
+    let mut horizontal: Vec<[f32; 4]> = Vec::with_capacity((dw * sh) as usize);
+    for y in 0..sh as i32 {
+        for contribution in &columns {
+            let mut acc = [0f32; 4];
+            for (k, w) in contribution.weights.iter().enumerate() {
+                let x = (contribution.first + k as i32).clamp(0, sw as i32 - 1);
+                let p = &pixels[(x + y * sw as i32) as usize];
+                for c in 0..4 {
+                    acc[c] += w * p[c];
+                }
+            }
+            horizontal.push(acc);
+        }
+    }
+
+    // vertical pass: dw x sh -> dw x dh
+    let rows = contributions(sh, dh, filter);
+    for (y, contribution) in rows.iter().enumerate() {
+        for x in 0..dw as i32 {
+            let mut acc = [0f32; 4];
+            for (k, w) in contribution.weights.iter().enumerate() {
+                let sy = (contribution.first + k as i32).clamp(0, sh as i32 - 1);
+                let p = &horizontal[(x + sy * dw as i32) as usize];
+                for c in 0..4 {
+                    acc[c] += w * p[c];
+                }
+            }
+            let alpha = acc[3].clamp(0.0, 255.0);
+            let unpremultiply = if alpha > 0.0 { 255.0 / alpha } else { 0.0 };
+            let channel = |v: f32| (v * unpremultiply).clamp(0.0, 255.0).round() as u8;
+            let color = Color::from_rgba(channel(acc[0]), channel(acc[1]), channel(acc[2]), alpha.round() as u8);
+            target.set_pixel(x as u32, y as u32, color);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const FILTERS: [ResampleFilter; 6] = [
+        ResampleFilter::Box,
+        ResampleFilter::Bilinear,
+        ResampleFilter::Bicubic,
+        ResampleFilter::Mitchell,
+        ResampleFilter::Lanczos(2),
+        ResampleFilter::Lanczos(3),
+    ];
+
+    fn row(values: &[u8]) -> Canvas {
+        let mut canvas = Canvas::new(values.len() as u32, 1);
+        for (x, v) in values.iter().enumerate() {
+            canvas.set_pixel(x as u32, 0, Color::from_rgb(*v, *v, *v));
+        }
+        canvas
+    }
+
+    fn reds(canvas: &Canvas) -> Vec<u8> {
+        (0..canvas.get_width() as u32).map(|x| canvas.get_pixel(x, 0).get_red()).collect()
+    }
+
+    #[test]
+    fn weights_partition_unity() {
+        for filter in FILTERS {
+            for k in 0..10 {
+                let offset = k as f32 / 10f32;
+                let support = filter.support().ceil() as i32;
+                let sum: f32 = (-support..=support + 1).map(|j| filter.weight(j as f32 - offset)).sum();
+                // the box has two taps at exactly half a texel
+                let expected = if filter == ResampleFilter::Box && k == 5 { 2f32 } else { 1f32 };
+                assert!((sum - expected).abs() < 0.02, "{:?} at {}: {}", filter, offset, sum);
+            }
+            assert_eq!(filter.weight(filter.support() + 0.01), 0f32);
+        }
+        assert_eq!(ResampleFilter::Bicubic.weight(1f32), 0f32);
+        assert!((ResampleFilter::Mitchell.weight(0f32) - 8f32 / 9f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn same_size_is_a_copy() {
+        let source = row(&[0, 30, 200, 255, 7]);
+        // Mitchell is the only one that does not interpolate its samples
+        for filter in FILTERS.into_iter().filter(|f| *f != ResampleFilter::Mitchell) {
+            assert_eq!(reds(&resample(&source, 5, 1, filter)), vec![0, 30, 200, 255, 7], "{:?}", filter);
+        }
+    }
+
+    #[test]
+    fn magnification() {
+        let source = row(&[0, 255]);
+        assert_eq!(reds(&resample(&source, 4, 1, ResampleFilter::Box)), vec![0, 0, 255, 255]);
+        assert_eq!(reds(&resample(&source, 4, 1, ResampleFilter::Bilinear)), vec![0, 64, 191, 255]);
+        // Catmull-Rom overshoots, the clamp keeps it in range
+        let steps = reds(&resample(&row(&[0, 0, 255, 255]), 8, 1, ResampleFilter::Bicubic));
+        assert_eq!((steps[0], steps[7]), (0, 255));
+        assert!(steps.windows(2).all(|w| w[0] <= w[1]));
+
+        let mut square = Canvas::new(2, 2);
+        square.set_pixel(0, 0, Color::black());
+        square.set_pixel(1, 1, Color::black());
+        let big = resample(&square, 4, 4, ResampleFilter::Box);
+        for y in 0..4 {
+            for x in 0..4 {
+                let black = (x < 2) == (y < 2);
+                assert_eq!(big.get_pixel(x, y).get_red(), if black { 0 } else { 255 });
+            }
+        }
+    }
+
+    #[test]
+    fn minification_averages() {
+        let source = row(&[0, 100, 200, 40]);
+        assert_eq!(reds(&resample(&source, 2, 1, ResampleFilter::Box)), vec![50, 120]);
+        assert_eq!(reds(&resample(&source, 1, 1, ResampleFilter::Box)), vec![85]);
+        // a one pixel checkerboard turns gray instead of aliasing
+        let stripes = row(&[0, 255].repeat(8));
+        for filter in [ResampleFilter::Bilinear, ResampleFilter::Mitchell, ResampleFilter::Lanczos(2)] {
+            // away from the clamped borders
+            let inner = reds(&resample(&stripes, 4, 1, filter))[1..3].to_vec();
+            assert!(inner.iter().all(|v| (120..=135).contains(v)), "{:?}: {:?}", filter, inner);
+        }
+    }
+
+    #[test]
+    fn transparent_texels_do_not_bleed() {
+        let mut source = Canvas::new(2, 1);
+        source.set_pixel(0, 0, Color::from_rgba(255, 0, 0, 0));
+        source.set_pixel(1, 0, Color::from_rgba(0, 0, 255, 255));
+        let target = resample(&source, 4, 1, ResampleFilter::Bilinear);
+        let mid = target.get_pixel(1, 0);
+        assert_eq!((mid.get_red(), mid.get_blue(), mid.get_alpha()), (0, 255, 64));
+        assert_eq!(target.get_pixel(0, 0).get_alpha(), 0);
+    }
+
+    #[test]
+    fn empty_sizes() {
+        assert_eq!(resample(&row(&[1, 2]), 0, 3, ResampleFilter::Bilinear).get_width(), 0);
+        assert_eq!(resample(&Canvas::new(0, 0), 2, 2, ResampleFilter::Lanczos(3)).get_width(), 2);
+    }
+
+}
@@ -0,0 +1,444 @@
+use std::ops::{Add, Mul, Sub};
+
+use super::{
+    canvas::Canvas,
+    mathsf::{Vec2, Vec3},
+    paint::Paint
+};
+
+// Piecewise cubic curves. Every spline is a chain of segments, each stored as the
+// polynomial a + b u + c u^2 + d u^3 with u in [0, 1]. The curve parameter t runs
+// over [0, 1] across all segments; equal steps in t are not equal distances, use an
+// ArcLengthTable for constant speed.
+
+pub trait SplineVector: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self> {
+    fn length(&self) -> f32;
+}
+
+impl SplineVector for Vec2 {
+    fn length(&self) -> f32 {
+        Vec2::length(self)
+    }
+}
+
+impl SplineVector for Vec3 {
+    fn length(&self) -> f32 {
+        Vec3::length(self)
+    }
+}
+
+pub trait Curve<V: SplineVector> {
+
+    fn segment_count(&self) -> usize;
+
+    // polynomial coefficients [a, b, c, d] of one segment
+    fn coefficients(&self, segment: usize) -> [V; 4];
+
+    // segment and local parameter u for the curve parameter t, clamped to [0, 1]
+    fn locate(&self, t: f32) -> (usize, f32) {
+        let count = self.segment_count();
+        assert!(count > 0, "The curve has no segment.");
+        let x = t.clamp(0f32, 1f32) * count as f32;
+        let segment = (x as usize).min(count - 1);
+        (segment, x - segment as f32)
+    }
+
+    fn position(&self, t: f32) -> V {
+        let (segment, u) = self.locate(t);
+        let [a, b, c, d] = self.coefficients(segment);
+        a + (b + (c + d * u) * u) * u
+    }
+
+    // with respect to t, so scaled by the segment count
+    fn derivative(&self, t: f32) -> V {
+        let (segment, u) = self.locate(t);
+        let [_, b, c, d] = self.coefficients(segment);
+        (b + (c * 2f32 + d * (3f32 * u)) * u) * self.segment_count() as f32
+    }
+
+    fn second_derivative(&self, t: f32) -> V {
+        let (segment, u) = self.locate(t);
+        let [_, _, c, d] = self.coefficients(segment);
+        let n = self.segment_count() as f32;
+        (c * 2f32 + d * (6f32 * u)) * (n * n)
+    }
+
+}
+
+fn hermite_coefficients<V: SplineVector>(p0: V, p1: V, m0: V, m1: V) -> [V; 4] {
+    [
+        p0,
+        m0,
+        (p1 - p0) * 3f32 - m0 * 2f32 - m1,
+        (p0 - p1) * 2f32 + m0 + m1,
+    ]
+}
+
+// cubic Hermite, one tangent per point
+#[derive(Clone, Debug)]
+pub struct Hermite<V: SplineVector> {
+    points: Vec<V>,
+    tangents: Vec<V>,
+}
+
+impl<V: SplineVector> Hermite<V> {
+    pub fn new(points: Vec<V>, tangents: Vec<V>) -> Self {
+        assert!(points.len() >= 2, "A spline needs at least two points.");
+        assert_eq!(points.len(), tangents.len(), "Hermite splines need one tangent per point.");
+        Hermite { points, tangents }
+    }
+    pub fn get_points(&self) -> &[V] {
+        &self.points
+    }
+    pub fn get_tangents(&self) -> &[V] {
+        &self.tangents
+    }
+}
+
+impl<V: SplineVector> Curve<V> for Hermite<V> {
+    fn segment_count(&self) -> usize {
+        self.points.len() - 1
+    }
+    fn coefficients(&self, segment: usize) -> [V; 4] {
+        hermite_coefficients(
+            self.points[segment],
+            self.points[segment + 1],
+            self.tangents[segment],
+            self.tangents[segment + 1]
+        )
+    }
+}
+
+// Interpolates every point. `alpha` picks the knot spacing: 0 uniform, 0.5 centripetal
+// (no cusps or self-intersections within a segment), 1 chordal.
+#[derive(Clone, Debug)]
+pub struct CatmullRom<V: SplineVector> {
+    points: Vec<V>,
+    alpha: f32,
+    closed: bool,
+}
+
+impl<V: SplineVector> CatmullRom<V> {
+
+    pub fn new(points: Vec<V>) -> Self {
+        assert!(points.len() >= 2, "A spline needs at least two points.");
+        CatmullRom { points, alpha: 0.5, closed: false }
+    }
+
+    pub fn set_alpha(&mut self, alpha: f32) -> &mut Self {
+        self.alpha = alpha.clamp(0f32, 1f32);
+        self
+    }
+
+    // joins the last point back to the first
+    pub fn set_closed(&mut self, closed: bool) -> &mut Self {
+        self.closed = closed;
+        self
+    }
+
+    pub fn get_points(&self) -> &[V] {
+        &self.points
+    }
+
+    // open curves extend their ends by mirroring the neighbor
+    fn point(&self, i: isize) -> V {
+        let n = self.points.len() as isize;
+        if self.closed {
+            return self.points[i.rem_euclid(n) as usize];
+        }
+        if i < 0 {
+            self.points[0] * 2f32 - self.points[1]
+        } else if i >= n {
+            self.points[n as usize - 1] * 2f32 - self.points[n as usize - 2]
+        } else {
+            self.points[i as usize]
+        }
+    }
+
+}
+
+impl<V: SplineVector> Curve<V> for CatmullRom<V> {
+    fn segment_count(&self) -> usize {
+        if self.closed { self.points.len() } else { self.points.len() - 1 }
+    }
+    fn coefficients(&self, segment: usize) -> [V; 4] {
+        let i = segment as isize;
+        let (p0, p1, p2, p3) = (self.point(i - 1), self.point(i), self.point(i + 1), self.point(i + 2));
+        // knot intervals, kept away from zero for repeated points
+        let interval = |a: V, b: V| (b - a).length().powf(self.alpha).max(1e-4);
+        let (d0, d1, d2) = (interval(p0, p1), interval(p1, p2), interval(p2, p3));
+        // non-uniform tangents rescaled to the unit segment
+        let m1 = ((p1 - p0) * (1f32 / d0) - (p2 - p0) * (1f32 / (d0 + d1)) + (p2 - p1) * (1f32 / d1)) * d1;
+        let m2 = ((p2 - p1) * (1f32 / d1) - (p3 - p1) * (1f32 / (d1 + d2)) + (p3 - p2) * (1f32 / d2)) * d1;
+        hermite_coefficients(p1, p2, m1, m2)
+    }
+}
+
+// Uniform cubic B-spline, C2 continuous but only approximating its control points.
+// Open curves repeat their end points so they start and end on them.
+#[derive(Clone, Debug)]
+pub struct BSpline<V: SplineVector> {
+    points: Vec<V>,
+    closed: bool,
+}
+
+impl<V: SplineVector> BSpline<V> {
+
+    pub fn new(points: Vec<V>) -> Self {
+        assert!(points.len() >= 2, "A spline needs at least two points.");
+        BSpline { points, closed: false }
+    }
+
+    pub fn set_closed(&mut self, closed: bool) -> &mut Self {
+        self.closed = closed;
+        self
+    }
+
+    pub fn get_points(&self) -> &[V] {
+        &self.points
+    }
+
+    fn point(&self, i: isize) -> V {
+        let n = self.points.len() as isize;
+        if self.closed {
+            self.points[i.rem_euclid(n) as usize]
+        } else {
+            self.points[i.clamp(0, n - 1) as usize]
+        }
+    }
+
+}
+
+impl<V: SplineVector> Curve<V> for BSpline<V> {
+    fn segment_count(&self) -> usize {
+        if self.closed { self.points.len() } else { self.points.len() + 1 }
+    }
+    fn coefficients(&self, segment: usize) -> [V; 4] {
+        // open curves start two segments early so the tripled end point is reached
+        let i = if self.closed { segment as isize } else { segment as isize - 2 };
+        let (p0, p1, p2, p3) = (self.point(i), self.point(i + 1), self.point(i + 2), self.point(i + 3));
+        let sixth = 1f32 / 6f32;
+        [
+            (p0 + p1 * 4f32 + p2) * sixth,
+            (p2 - p0) * 0.5,
+            (p0 - p1 * 2f32 + p2) * 0.5,
+            (p3 - p0 + (p1 - p2) * 3f32) * sixth,
+        ]
+    }
+}
+
+// cumulative chord lengths of a curve sampled at equal steps of t
+#[derive(Clone, Debug)]
+pub struct ArcLengthTable {
+    lengths: Vec<f32>,
+}
+
+impl ArcLengthTable {
+
+    // `samples` steps per segment, 16 to 32 is plenty for rendering and animation
+    pub fn new<V: SplineVector, C: Curve<V>>(curve: &C, samples: usize) -> Self {
+        let steps = samples.max(1) * curve.segment_count();
+        let mut lengths = Vec::with_capacity(steps + 1);
+        lengths.push(0f32);
+        let mut previous = curve.position(0f32);
+        let mut total = 0f32;
+        for k in 1..=steps {
+            let p = curve.position(k as f32 / steps as f32);
+            total += (p - previous).length();
+            lengths.push(total);
+            previous = p;
+        }
+        ArcLengthTable { lengths }
+    }
+
+    pub fn total_length(&self) -> f32 {
+        self.lengths[self.lengths.len() - 1]
+    }
+
+    // curve parameter at `distance` along the curve
+    pub fn parameter(&self, distance: f32) -> f32 {
+        let steps = self.lengths.len() - 1;
+        let distance = distance.clamp(0f32, self.total_length());
+        let k = self.lengths.partition_point(|l| *l < distance).clamp(1, steps);
+        let (l0, l1) = (self.lengths[k - 1], self.lengths[k]);
+        let f = if l1 > l0 { (distance - l0) / (l1 - l0) } else { 0f32 };
+        (k as f32 - 1f32 + f) / steps as f32
+    }
+
+    // curve parameter at the fraction s in [0, 1] of the total length, constant speed in s
+    pub fn parameter_normalized(&self, s: f32) -> f32 {
+        self.parameter(s * self.total_length())
+    }
+
+    // `count` points evenly spaced along the curve
+    pub fn resample<V: SplineVector, C: Curve<V>>(&self, curve: &C, count: usize) -> Vec<V> {
+        let last = (count.max(2) - 1) as f32;
+        (0..count).map(|k| curve.position(self.parameter_normalized(k as f32 / last))).collect()
+    }
+
+}
+
+// polyline through `steps` samples per segment, in canvas coordinates
+pub fn draw<C: Curve<Vec2>>(canvas: &mut Canvas, curve: &C, steps: usize, z: f32, paint: &Paint) {
+    let count = steps.max(1) * curve.segment_count();
+    let mut points: Vec<(i32, i32)> = Vec::with_capacity(count + 1);
+    for k in 0..=count {
+        let p = curve.position(k as f32 / count as f32).round();
+        let point = (p.get_x() as i32, p.get_y() as i32);
+        // samples closer than a pixel would draw the same pixel again
+        if points.last() != Some(&point) {
+            points.push(point);
+        }
+    }
+    canvas.draw_polyline(&points, z, paint);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close(a: Vec2, b: Vec2, tolerance: f32) -> bool {
+        (a - b).length() <= tolerance
+    }
+
+    fn points() -> Vec<Vec2> {
+        vec![Vec2::new(0f32, 0f32), Vec2::new(1f32, 2f32), Vec2::new(4f32, 2f32), Vec2::new(5f32, -1f32), Vec2::new(9f32, 0f32)]
+    }
+
+    // central differences against the analytic derivatives, away from the segment joins
+    fn check_derivatives<C: Curve<Vec2>>(curve: &C) {
+        let h = 1e-3;
+        for k in 0..curve.segment_count() {
+            for u in [0.2, 0.5, 0.7] {
+                let t = (k as f32 + u) / curve.segment_count() as f32;
+                let velocity = (curve.position(t + h) - curve.position(t - h)) * (1f32 / (2f32 * h));
+                let acceleration = (curve.derivative(t + h) - curve.derivative(t - h)) * (1f32 / (2f32 * h));
+                let scale = curve.derivative(t).length().max(1f32);
+                assert!(close(velocity, curve.derivative(t), 0.02 * scale), "{:?} {:?}", velocity, curve.derivative(t));
+                let scale = curve.second_derivative(t).length().max(1f32);
+                assert!(close(acceleration, curve.second_derivative(t), 0.02 * scale));
+            }
+        }
+    }
+
+    #[test]
+    fn catmull_rom_interpolates_its_points() {
+        for alpha in [0f32, 0.5, 1f32] {
+            let mut curve = CatmullRom::new(points());
+            curve.set_alpha(alpha);
+            assert_eq!(curve.get_points(), &points()[..]);
+            for (k, p) in points().into_iter().enumerate() {
+                assert!(close(curve.position(k as f32 / 4f32), p, 1e-5), "alpha {} point {}", alpha, k);
+            }
+            check_derivatives(&curve);
+            curve.set_closed(true);
+            assert_eq!(curve.segment_count(), 5);
+            for (k, p) in points().into_iter().enumerate() {
+                assert!(close(curve.position(k as f32 / 5f32), p, 1e-5));
+            }
+            assert!(close(curve.position(1f32), points()[0], 1e-5));
+        }
+    }
+
+    #[test]
+    fn catmull_rom_joins() {
+        // uniform knots are C1, other spacings keep only the tangent direction across a join
+        for alpha in [0f32, 0.5, 1f32] {
+            let mut curve = CatmullRom::new(points());
+            curve.set_alpha(alpha);
+            for k in 1..4 {
+                let [_, b, c, d] = curve.coefficients(k - 1);
+                let [_, next, _, _] = curve.coefficients(k);
+                let end = b + c * 2f32 + d * 3f32;
+                if alpha == 0f32 {
+                    assert!(close(end, next, 1e-4));
+                }
+                let cross = end.get_x() * next.get_y() - end.get_y() * next.get_x();
+                assert!(cross.abs() < 1e-4 * end.length() * next.length() && end.dot(&next) > 0f32);
+            }
+        }
+    }
+
+    #[test]
+    fn hermite_interpolates_points_and_tangents() {
+        let tangents = vec![Vec2::new(1f32, 0f32), Vec2::new(0f32, 3f32), Vec2::new(2f32, -2f32), Vec2::new(0f32, 0f32), Vec2::new(-1f32, 1f32)];
+        let curve = Hermite::new(points(), tangents.clone());
+        assert_eq!(curve.get_tangents(), &tangents[..]);
+        for (k, (p, tangent)) in points().into_iter().zip(tangents.iter()).enumerate() {
+            assert!(close(curve.position(k as f32 / 4f32), p, 1e-5));
+            // the tangents are in u, per segment, the derivative is in t
+            let [_, b, c, d] = curve.coefficients(k.min(3));
+            let m = if k < 4 { b } else { b + c * 2f32 + d * 3f32 };
+            assert!(close(m, *tangent, 1e-5));
+        }
+        assert!(close(curve.derivative(0.5), tangents[2] * 4f32, 1e-4));
+        check_derivatives(&curve);
+    }
+
+    #[test]
+    #[should_panic(expected = "one tangent per point")]
+    fn hermite_tangent_count() {
+        Hermite::new(points(), vec![Vec2::new(0f32, 0f32)]);
+    }
+
+    #[test]
+    fn bspline_reaches_its_end_points() {
+        let curve = BSpline::new(points());
+        assert_eq!(curve.get_points(), &points()[..]);
+        assert!(close(curve.position(0f32), points()[0], 1e-5));
+        assert!(close(curve.position(1f32), points()[4], 1e-5));
+        // approximating, the interior points are not on the curve
+        let nearest = (0..=1000).map(|k| (curve.position(k as f32 / 1000f32) - points()[2]).length()).fold(f32::INFINITY, f32::min);
+        assert!(nearest > 0.1);
+        check_derivatives(&curve);
+        // C2 at the joins
+        for k in 1..curve.segment_count() {
+            let [_, _, c, d] = curve.coefficients(k - 1);
+            let [_, _, next, _] = curve.coefficients(k);
+            assert!(close(c + d * 3f32, next, 1e-4));
+        }
+    }
+
+    #[test]
+    fn closed_bspline_wraps() {
+        let mut curve = BSpline::new(points());
+        curve.set_closed(true);
+        assert_eq!(curve.segment_count(), 5);
+        assert!(close(curve.position(0f32), curve.position(1f32), 1e-5));
+        assert!(close(curve.derivative(0f32), curve.derivative(1f32), 1e-3));
+    }
+
+    #[test]
+    fn arc_length_resampling_is_uniform() {
+        // slow at the start, fast in the middle, no cusp
+        let curve = Hermite::new(
+            vec![Vec2::new(0f32, 0f32), Vec2::new(1f32, 0.5), Vec2::new(3f32, 0f32)],
+            vec![Vec2::new(0.3, 0.3), Vec2::new(3f32, 0f32), Vec2::new(0.5, -0.5)]
+        );
+        let table = ArcLengthTable::new(&curve, 32);
+        let samples = table.resample(&curve, 21);
+        assert_eq!(samples.len(), 21);
+        assert!(close(samples[0], Vec2::new(0f32, 0f32), 1e-6));
+        assert!(close(samples[20], Vec2::new(3f32, 0f32), 1e-5));
+        // equal steps in t are far from it
+        let steps: Vec<f32> = (0..20).map(|k| (curve.position((k + 1) as f32 / 20f32) - curve.position(k as f32 / 20f32)).length()).collect();
+        assert!(steps.iter().fold(0f32, |a, b| a.max(*b)) > 2f32 * steps.iter().fold(f32::INFINITY, |a, b| a.min(*b)));
+        let spacing = table.total_length() / 20f32;
+        for pair in samples.windows(2) {
+            let d = (pair[1] - pair[0]).length();
+            assert!((d - spacing).abs() < 0.01 * spacing, "{} {}", d, spacing);
+        }
+        assert_eq!(table.parameter(-1f32), 0f32);
+        assert_eq!(table.parameter(1e6), 1f32);
+    }
+
+    #[test]
+    fn arc_length_of_a_straight_line() {
+        let curve = CatmullRom::new(vec![Vec2::new(0f32, 0f32), Vec2::new(3f32, 4f32)]);
+        let table = ArcLengthTable::new(&curve, 16);
+        assert!((table.total_length() - 5f32).abs() < 1e-5);
+        assert!((table.parameter_normalized(0.5) - 0.5).abs() < 1e-5);
+    }
+
+}
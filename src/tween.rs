@@ -0,0 +1,481 @@
+use std::f32::consts::{PI, TAU};
+
+pub use super::interpolate::Interpolate;
+
+// The standard easing curves (easings.net), mapping t in [0, 1] to eased progress.
+// Back and elastic overshoot [0, 1]; colors are clamped by Color::lerp.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Repeat {
+    Once,
+    Times(u32),
+    Forever,
+}
+
+// Animates a value from `from` to `to`. Times are the ones Logic::update_routine
+// receives; the tween starts on the first update unless started explicitly.
+#[derive(Clone, Debug)]
+pub struct Tween<T: Interpolate> {
+    from: T,
+    to: T,
+    duration: f64,
+    delay: f64,
+    easing: Easing,
+    repeat: Repeat,
+    // every other repetition plays backwards
+    yoyo: bool,
+    start: Option<f64>,
+    value: T,
+    finished: bool,
+}
+
+// tweens played one after the other, each one's delay and repetitions included
+#[derive(Clone, Debug)]
+pub struct Sequence<T: Interpolate> {
+    tweens: Vec<Tween<T>>,
+    looping: bool,
+    start: Option<f64>,
+    value: Option<T>,
+    finished: bool,
+}
+
+fn power_in_out(t: f32, p: i32) -> f32 {
+    if t < 0.5 {
+        2f32.powi(p - 1) * t.powi(p)
+    } else {
+        1f32 - (-2f32 * t + 2f32).powi(p) / 2f32
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    let (n, d) = (7.5625, 2.75);
+    if t < 1f32 / d {
+        n * t * t
+    } else if t < 2f32 / d {
+        let t = t - 1.5 / d;
+        n * t * t + 0.75
+    } else if t < 2.5 / d {
+        let t = t - 2.25 / d;
+        n * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / d;
+        n * t * t + 0.984375
+    }
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0f32, 1f32);
+        let back = 1.70158;
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1f32 - (1f32 - t).powi(2),
+            Easing::QuadInOut => power_in_out(t, 2),
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1f32 - (1f32 - t).powi(3),
+            Easing::CubicInOut => power_in_out(t, 3),
+            Easing::SineIn => 1f32 - (t * PI / 2f32).cos(),
+            Easing::SineOut => (t * PI / 2f32).sin(),
+            Easing::SineInOut => -((PI * t).cos() - 1f32) / 2f32,
+            Easing::ExpoIn => if t == 0f32 { 0f32 } else { 2f32.powf(10f32 * t - 10f32) },
+            Easing::ExpoOut => if t == 1f32 { 1f32 } else { 1f32 - 2f32.powf(-10f32 * t) },
+            Easing::ExpoInOut => {
+                if t == 0f32 || t == 1f32 {
+                    t
+                } else if t < 0.5 {
+                    2f32.powf(20f32 * t - 10f32) / 2f32
+                } else {
+                    (2f32 - 2f32.powf(-20f32 * t + 10f32)) / 2f32
+                }
+            }
+            Easing::BackIn => (back + 1f32) * t * t * t - back * t * t,
+            Easing::BackOut => 1f32 + (back + 1f32) * (t - 1f32).powi(3) + back * (t - 1f32).powi(2),
+            Easing::BackInOut => {
+                let c = back * 1.525;
+                if t < 0.5 {
+                    (2f32 * t).powi(2) * ((c + 1f32) * 2f32 * t - c) / 2f32
+                } else {
+                    ((2f32 * t - 2f32).powi(2) * ((c + 1f32) * (2f32 * t - 2f32) + c) + 2f32) / 2f32
+                }
+            }
+            Easing::ElasticIn => {
+                if t == 0f32 || t == 1f32 {
+                    t
+                } else {
+                    -2f32.powf(10f32 * t - 10f32) * ((10f32 * t - 10.75) * TAU / 3f32).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t == 0f32 || t == 1f32 {
+                    t
+                } else {
+                    2f32.powf(-10f32 * t) * ((10f32 * t - 0.75) * TAU / 3f32).sin() + 1f32
+                }
+            }
+            Easing::ElasticInOut => {
+                let s = ((20f32 * t - 11.125) * TAU / 4.5).sin();
+                if t == 0f32 || t == 1f32 {
+                    t
+                } else if t < 0.5 {
+                    -(2f32.powf(20f32 * t - 10f32) * s) / 2f32
+                } else {
+                    2f32.powf(-20f32 * t + 10f32) * s / 2f32 + 1f32
+                }
+            }
+            Easing::BounceIn => 1f32 - bounce_out(1f32 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1f32 - bounce_out(1f32 - 2f32 * t)) / 2f32
+                } else {
+                    (1f32 + bounce_out(2f32 * t - 1f32)) / 2f32
+                }
+            }
+        }
+    }
+}
+
+impl<T: Interpolate> Tween<T> {
+
+    // duration in seconds
+    pub fn new(from: T, to: T, duration: f64, easing: Easing) -> Self {
+        assert!(duration > 0.0, "A tween needs a positive duration.");
+        Tween {
+            value: from.clone(),
+            from,
+            to,
+            duration,
+            delay: 0.0,
+            easing,
+            repeat: Repeat::Once,
+            yoyo: false,
+            start: None,
+            finished: false,
+        }
+    }
+
+    pub fn set_delay(&mut self, delay: f64) -> &mut Self {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    pub fn set_repeat(&mut self, repeat: Repeat) -> &mut Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn set_yoyo(&mut self, yoyo: bool) -> &mut Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    pub fn set_easing(&mut self, easing: Easing) -> &mut Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn start(&mut self, time: f64) {
+        self.start = Some(time);
+        self.finished = false;
+    }
+
+    // back to the initial state, starting again on the next update
+    pub fn reset(&mut self) {
+        self.start = None;
+        self.finished = false;
+        self.value = self.from.clone();
+    }
+
+    // delay included, None when repeating forever
+    pub fn total_duration(&self) -> Option<f64> {
+        match self.repeat {
+            Repeat::Once => Some(self.delay + self.duration),
+            Repeat::Times(count) => Some(self.delay + self.duration * count.max(1) as f64),
+            Repeat::Forever => None,
+        }
+    }
+
+    // value `elapsed` seconds after the start, and whether the tween is over by then
+    pub fn sample(&self, elapsed: f64) -> (T, bool) {
+        let elapsed = elapsed - self.delay;
+        if elapsed < 0.0 {
+            return (self.from.clone(), false);
+        }
+        let cycles = match self.repeat {
+            Repeat::Once => Some(1),
+            Repeat::Times(count) => Some(count.max(1)),
+            Repeat::Forever => None,
+        };
+        let mut cycle = (elapsed / self.duration).floor() as u64;
+        let mut t = (elapsed / self.duration).fract() as f32;
+        let finished = cycles.is_some_and(|count| cycle >= count as u64);
+        if finished {
+            // holds the end of the last cycle
+            cycle = cycles.unwrap_or(1) as u64 - 1;
+            t = 1f32;
+        }
+        if self.yoyo && cycle % 2 == 1 {
+            t = 1f32 - t;
+        }
+        (T::interpolate(&self.from, &self.to, self.easing.apply(t)), finished)
+    }
+
+    pub fn update(&mut self, time: f64) -> &T {
+        let start = *self.start.get_or_insert(time);
+        let (value, finished) = self.sample(time - start);
+        self.value = value;
+        self.finished = finished;
+        &self.value
+    }
+
+    pub fn get_value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+}
+
+impl<T: Interpolate> Sequence<T> {
+
+    pub fn new() -> Self {
+        Sequence {
+            tweens: Vec::new(),
+            looping: false,
+            start: None,
+            value: None,
+            finished: false,
+        }
+    }
+
+    // only the last tween may repeat forever, nothing after it would ever play
+    pub fn then(mut self, tween: Tween<T>) -> Self {
+        assert!(
+            self.tweens.last().is_none_or(|last| last.total_duration().is_some()),
+            "No tween can follow one that repeats forever."
+        );
+        self.tweens.push(tween);
+        self
+    }
+
+    pub fn set_looping(&mut self, looping: bool) -> &mut Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn start(&mut self, time: f64) {
+        self.start = Some(time);
+        self.finished = false;
+    }
+
+    pub fn reset(&mut self) {
+        self.start = None;
+        self.value = None;
+        self.finished = false;
+    }
+
+    pub fn total_duration(&self) -> Option<f64> {
+        self.tweens.iter().map(|tween| tween.total_duration()).sum()
+    }
+
+    pub fn sample(&self, elapsed: f64) -> Option<(T, bool)> {
+        let total = self.total_duration();
+        let mut elapsed = match total {
+            Some(total) if self.looping && total > 0.0 => elapsed.rem_euclid(total),
+            _ => elapsed,
+        };
+        for tween in &self.tweens {
+            match tween.total_duration() {
+                Some(duration) if elapsed >= duration => elapsed -= duration,
+                _ => return Some((tween.sample(elapsed).0, false)),
+            }
+        }
+        let last = self.tweens.last()?;
+        Some((last.sample(f64::INFINITY).0, true))
+    }
+
+    // None while the sequence is empty
+    pub fn update(&mut self, time: f64) -> Option<&T> {
+        let start = *self.start.get_or_insert(time);
+        let (value, finished) = self.sample(time - start)?;
+        self.finished = finished;
+        self.value = Some(value);
+        self.value.as_ref()
+    }
+
+    pub fn get_value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+}
+
+impl<T: Interpolate> Default for Sequence<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const EASINGS: [Easing; 22] = [
+        Easing::Linear, Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+        Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut,
+        Easing::SineIn, Easing::SineOut, Easing::SineInOut,
+        Easing::ExpoIn, Easing::ExpoOut, Easing::ExpoInOut,
+        Easing::BackIn, Easing::BackOut, Easing::BackInOut,
+        Easing::ElasticIn, Easing::ElasticOut, Easing::ElasticInOut,
+        Easing::BounceIn, Easing::BounceOut, Easing::BounceInOut,
+    ];
+
+    #[test]
+    fn easing_endpoints() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0f32), 0f32, "{:?}", easing);
+            assert_eq!(easing.apply(1f32), 1f32, "{:?}", easing);
+            // clamped outside [0, 1]
+            assert_eq!(easing.apply(-1f32), 0f32, "{:?}", easing);
+            assert_eq!(easing.apply(2f32), 1f32, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn easing_shapes() {
+        for easing in EASINGS {
+            for k in 0..=50 {
+                assert!(easing.apply(k as f32 / 50f32).is_finite());
+            }
+        }
+        // in-out curves are symmetric about the midpoint
+        for easing in [Easing::QuadInOut, Easing::CubicInOut, Easing::SineInOut, Easing::ExpoInOut, Easing::BounceInOut] {
+            assert!((easing.apply(0.5) - 0.5).abs() < 1e-6, "{:?}", easing);
+            assert!((easing.apply(0.2) + easing.apply(0.8) - 1f32).abs() < 1e-5, "{:?}", easing);
+        }
+        // out is in mirrored
+        assert!((Easing::CubicOut.apply(0.3) - (1f32 - Easing::CubicIn.apply(0.7))).abs() < 1e-6);
+        assert!(Easing::BackIn.apply(0.2) < 0f32);
+        assert!(Easing::BackOut.apply(0.8) > 1f32);
+        assert!(Easing::ElasticOut.apply(0.2) > 1f32);
+        assert!(Easing::QuadIn.apply(0.5) < 0.5 && Easing::QuadOut.apply(0.5) > 0.5);
+    }
+
+    #[test]
+    fn tween_plays_once_and_holds() {
+        let mut tween = Tween::new(0f32, 10f32, 2.0, Easing::Linear);
+        tween.set_delay(1.0);
+        assert_eq!(tween.total_duration(), Some(3.0));
+        assert_eq!(*tween.update(5.0), 0f32);
+        assert_eq!(*tween.update(5.5), 0f32);
+        assert_eq!(*tween.update(7.0), 5f32);
+        assert!(!tween.is_finished());
+        assert_eq!(*tween.update(8.0), 10f32);
+        assert!(tween.is_finished());
+        assert_eq!(*tween.update(100.0), 10f32);
+        tween.reset();
+        assert_eq!(*tween.get_value(), 0f32);
+        assert!(!tween.is_finished());
+        tween.start(0.0);
+        assert_eq!(*tween.update(2.0), 5f32);
+    }
+
+    #[test]
+    fn repeat_times_with_yoyo_holds_the_last_cycle_end() {
+        let mut tween = Tween::new(0f32, 10f32, 1.0, Easing::Linear);
+        tween.set_repeat(Repeat::Times(2)).set_yoyo(true);
+        assert_eq!(tween.total_duration(), Some(2.0));
+        assert_eq!(tween.sample(0.25), (2.5, false));
+        assert_eq!(tween.sample(1.25), (7.5, false));
+        // the second cycle plays backwards and ends at `from`
+        assert_eq!(tween.sample(2.0), (0f32, true));
+        assert_eq!(tween.sample(9.0), (0f32, true));
+        tween.set_repeat(Repeat::Times(3));
+        assert_eq!(tween.sample(2.25), (2.5, false));
+        assert_eq!(tween.sample(3.0), (10f32, true));
+        tween.set_yoyo(false);
+        assert_eq!(tween.sample(1.25), (2.5, false));
+        assert_eq!(tween.sample(3.5), (10f32, true));
+    }
+
+    #[test]
+    fn repeat_forever_never_finishes() {
+        let mut tween = Tween::new(0f32, 4f32, 1.0, Easing::Linear);
+        tween.set_repeat(Repeat::Forever).set_yoyo(true);
+        assert_eq!(tween.total_duration(), None);
+        assert_eq!(tween.sample(1000.25), (1f32, false));
+        assert_eq!(tween.sample(1001.25), (3f32, false));
+    }
+
+    #[test]
+    fn sequence_plays_in_order_with_delays() {
+        let mut second = Tween::new(10f32, 20f32, 2.0, Easing::Linear);
+        second.set_delay(1.0);
+        let mut sequence = Sequence::new().then(Tween::new(0f32, 10f32, 1.0, Easing::Linear)).then(second);
+        assert_eq!(sequence.total_duration(), Some(4.0));
+        assert_eq!(sequence.sample(0.5), Some((5f32, false)));
+        // the delay of the second tween holds its `from`
+        assert_eq!(sequence.sample(1.5), Some((10f32, false)));
+        assert_eq!(sequence.sample(3.0), Some((15f32, false)));
+        assert_eq!(sequence.sample(4.0), Some((20f32, true)));
+        assert_eq!(sequence.update(10.0), Some(&0f32));
+        assert_eq!(sequence.update(20.0), Some(&20f32));
+        assert!(sequence.is_finished());
+        sequence.reset();
+        assert_eq!(sequence.get_value(), None);
+        assert!(Sequence::<f32>::new().sample(1.0).is_none());
+    }
+
+    #[test]
+    fn looping_sequence_wraps() {
+        let mut sequence = Sequence::new()
+            .then(Tween::new(0f32, 10f32, 1.0, Easing::Linear))
+            .then(Tween::new(10f32, 0f32, 1.0, Easing::Linear));
+        sequence.set_looping(true);
+        assert_eq!(sequence.sample(0.5), Some((5f32, false)));
+        assert_eq!(sequence.sample(2.5), Some((5f32, false)));
+        assert_eq!(sequence.sample(3.75), Some((2.5, false)));
+        assert_eq!(sequence.sample(200.0), Some((0f32, false)));
+    }
+
+    #[test]
+    #[should_panic(expected = "repeats forever")]
+    fn nothing_follows_forever() {
+        let mut forever = Tween::new(0f32, 1f32, 1.0, Easing::Linear);
+        forever.set_repeat(Repeat::Forever);
+        let _ = Sequence::new().then(forever).then(Tween::new(0f32, 1f32, 1.0, Easing::Linear));
+    }
+
+}
@@ -3,6 +3,8 @@ use glfw::{Action, Context, CursorMode, Glfw, Key, MouseButton, PWindow, SwapInt
 
 use super::canvas::Canvas;
 use super::gl;
+use super::pixelart::PixelScaler;
+use super::resample::{self, ResampleFilter};
 
 static mut GLOBAL_GLFW_INITIALIZED: bool = false;
 
@@ -14,11 +16,25 @@ pub struct Config {
     size: u32,
     resolution_scale: f32,
     swap_interval: SwapInterval,
+    upscaler: Upscaler,
 }
 pub struct Window {
     lib: Glfw,
     handle: PWindow,
     canvas_dimension: (u32, u32),
+    presentation_dimension: (u32, u32),
+    upscaler: Upscaler,
+}
+
+// how a canvas smaller than the window (resolution_scale < 1) is stretched
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Upscaler {
+    // done by the GPU when sampling the texture
+    Nearest,
+    // done on the CPU to the window size before uploading
+    Resample(ResampleFilter),
+    // done on the CPU by an integer factor, the GPU stretches the rest with Nearest
+    PixelArt(PixelScaler),
 }
 
 impl Config {
@@ -29,8 +45,12 @@ impl Config {
             size,
             resolution_scale,
             swap_interval,
+            upscaler: Upscaler::Nearest,
         }
     }
+    pub fn set_upscaler(&mut self, upscaler: Upscaler) {
+        self.upscaler = upscaler;
+    }
     pub fn default() -> Self {
         Config {
             title: String::from("Canvas"),
@@ -38,6 +58,7 @@ impl Config {
             size: 300,
             resolution_scale: 1.0,
             swap_interval: SwapInterval::Sync(1),
+            upscaler: Upscaler::Nearest,
         }
     }
 }
@@ -94,11 +115,12 @@ impl Window {
             (width as f32 * config.resolution_scale) as u32, 
             (height as f32 * config.resolution_scale) as u32
         );
+        let presentation_dimension = config.upscaler.presentation_dimension(canvas_dimension, (width, height));
         // Creating OpenGL objects
         unsafe { 
             ogli::create_program();
             ogli::create_quad();
-            ogli::create_tex(canvas_dimension.0, canvas_dimension.1);
+            ogli::create_tex(presentation_dimension.0, presentation_dimension.1);
             if let Some(e) = ogli::any_error() {
                 println!("GL Error: {}", e);
             }
@@ -109,6 +131,8 @@ impl Window {
             lib: glfw,
             handle: kit.0,
             canvas_dimension,
+            presentation_dimension,
+            upscaler: config.upscaler,
         }
 
     }
@@ -140,6 +164,15 @@ impl Window {
         self.canvas_dimension
     }
 
+    // size of the texture swap_buffers expects
+    pub fn get_presentation_dimension(&self) -> (u32, u32) {
+        self.presentation_dimension
+    }
+
+    pub fn get_upscaler(&self) -> Upscaler {
+        self.upscaler
+    }
+
     pub fn toggle_cursor_mode(&mut self) {
         let current_mode = self.handle.get_cursor_mode();
         self.handle.set_cursor_mode(if current_mode == CursorMode::Normal { CursorMode::Disabled } else { CursorMode::Normal });
@@ -161,6 +194,26 @@ impl Window {
 
 }
 
+impl Upscaler {
+
+    pub fn presentation_dimension(&self, canvas: (u32, u32), window: (u32, u32)) -> (u32, u32) {
+        match self {
+            Upscaler::Nearest => canvas,
+            Upscaler::Resample(_) => window,
+            Upscaler::PixelArt(scaler) => (canvas.0 * scaler.factor(), canvas.1 * scaler.factor()),
+        }
+    }
+
+    pub fn upscale(&self, source: &Canvas, target: &mut Canvas) {
+        match self {
+            Upscaler::Nearest => resample::resample_into(source, target, ResampleFilter::Box),
+            Upscaler::Resample(filter) => resample::resample_into(source, target, *filter),
+            Upscaler::PixelArt(scaler) => scaler.scale_into(source, target),
+        }
+    }
+
+}
+
 fn glfw_callback(error: glfw::Error, _: String) {
     println!("An error has occurred: {}", error);
     exit(0);
@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+
+use rs_open_canvas::{
+    canvas::Color,
+    gltf::{AlphaMode, Gltf, GltfError, ImageSource, Projection, TextureRef, Wrap},
+    mathsf::{Mat4, Vec2, Vec3, Vec4},
+    mesh::Topology
+};
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+fn parse(json: &str) -> Result<Gltf, GltfError> {
+    Gltf::from_slice(json.as_bytes(), &fixtures())
+}
+
+fn close(a: &Vec3, b: &Vec3) -> bool {
+    a.distance(b) < 1e-5
+}
+
+// three zeroed floats
+const BUFFER: &str = r#""buffers": [{"byteLength": 12, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAA"}],
+    "bufferViews": [{"buffer": 0, "byteLength": 12}]"#;
+
+#[test]
+fn embedded_buffer() {
+    let gltf = Gltf::load(fixtures().join("quad.gltf")).unwrap();
+    assert_eq!(gltf.meshes.len(), 1);
+    assert_eq!(gltf.meshes[0].name.as_deref(), Some("quad"));
+    let primitive = &gltf.meshes[0].primitives[0];
+    assert_eq!(primitive.material, Some(0));
+    let mesh = &primitive.mesh;
+    assert_eq!(mesh.get_topology(), Topology::TriangleList);
+    assert_eq!(mesh.vertex_count(), 4);
+    assert_eq!(mesh.element_count(), 6);
+    assert_eq!(mesh.element(5), 3);
+    assert_eq!(mesh.vertex(2).position, Vec3::new(1f32, 1f32, 0f32));
+    assert_eq!(mesh.vertex(2).normal, Vec3::new(0f32, 0f32, 1f32));
+    assert_eq!(mesh.vertex(3).uv, Vec2::new(0f32, 0f32));
+}
+
+#[test]
+fn materials_and_textures() {
+    let gltf = Gltf::load(fixtures().join("quad.gltf")).unwrap();
+    let material = &gltf.materials[0];
+    assert_eq!(material.name.as_deref(), Some("painted"));
+    assert_eq!(material.base_color, Vec4::new(1f32, 0.5, 0.25, 1f32));
+    assert_eq!(material.base_color_texture, Some(TextureRef { texture: 0, tex_coord: 0, scale: 1f32 }));
+    assert_eq!(material.metallic, 0f32);
+    assert_eq!(material.roughness, 0.6);
+    assert_eq!(material.normal_texture.unwrap().scale, 0.5);
+    assert_eq!(material.occlusion_texture, None);
+    assert_eq!(material.emissive, Vec3::new(0.1, 0.2, 0.3));
+    assert_eq!(material.alpha_mode, AlphaMode::Mask(0.3));
+    assert!(material.double_sided);
+    assert_eq!(material.base_color(), Color::from_linear(&Vec4::new(1f32, 0.5, 0.25, 1f32)));
+
+    assert_eq!(gltf.textures[0].source, Some(0));
+    let sampler = gltf.samplers[gltf.textures[0].sampler.unwrap()];
+    assert_eq!(sampler.mag_filter, Some(9728));
+    assert_eq!((sampler.wrap_s, sampler.wrap_t), (Wrap::ClampToEdge, Wrap::MirroredRepeat));
+    // percent escapes are decoded, the file doesn't need to exist
+    assert_eq!(gltf.images[0].source, ImageSource::File(fixtures().join("textures/checker board.png")));
+}
+
+#[test]
+fn hierarchy_and_cameras() {
+    let gltf = Gltf::load(fixtures().join("quad.gltf")).unwrap();
+    assert_eq!(gltf.scene, Some(0));
+    assert_eq!(gltf.scenes[0].nodes, vec![0, 2]);
+    assert_eq!(gltf.nodes[1].parent, Some(0));
+    assert_eq!(gltf.nodes[0].children, vec![1]);
+
+    // scaled by 2, turned a quarter around z, then moved by the parent
+    let world = gltf.world_transform(1);
+    assert!(close(&world.transform_point(&Vec3::new(1f32, 0f32, 0f32)), &Vec3::new(1f32, 4f32, 3f32)));
+    let visited: Vec<usize> = gltf.traverse(0).iter().map(|(node, _)| *node).collect();
+    assert_eq!(visited, vec![0, 1, 2]);
+    assert!(gltf.traverse(0).iter().all(|(node, m)| *m == gltf.world_transform(*node)));
+
+    let eye = &gltf.nodes[2];
+    let camera = &gltf.cameras[eye.camera.unwrap()];
+    assert_eq!(camera.name.as_deref(), Some("eye"));
+    assert_eq!(camera.projection_matrix(1.5), Mat4::perspective(0.8, 1.5, 0.1, 100f32));
+    let view = gltf.world_transform(2).inverse().unwrap();
+    assert!(close(&view.transform_point(&Vec3::new(0f32, 0f32, 0f32)), &Vec3::new(0f32, 0f32, -5f32)));
+}
+
+#[test]
+fn external_buffer() {
+    let gltf = Gltf::load(fixtures().join("strip.gltf")).unwrap();
+    let primitives = &gltf.meshes[0].primitives;
+
+    // a strip without normals comes back as flat triangles
+    let strip = &primitives[0].mesh;
+    assert_eq!(primitives[0].material, None);
+    assert_eq!(strip.get_topology(), Topology::TriangleList);
+    assert!(strip.get_indices().is_none());
+    assert_eq!(strip.vertex_count(), 6);
+    assert_eq!(strip.vertex(5).position, Vec3::new(1f32, 1f32, 0f32));
+    assert_eq!(strip.vertex(5).uv, Vec2::new(1f32, 1f32));
+    assert!(strip.get_normals().iter().all(|n| *n == Vec3::new(0f32, 0f32, 1f32)));
+
+    let lines = &primitives[1].mesh;
+    assert_eq!(lines.get_topology(), Topology::LineStrip);
+    let elements: Vec<u32> = (0..lines.element_count()).map(|i| lines.element(i)).collect();
+    assert_eq!(elements, vec![0, 1, 2, 3, 0]);
+}
+
+#[test]
+fn binary_container() {
+    let gltf = Gltf::load(fixtures().join("triangle.glb")).unwrap();
+    let mesh = &gltf.meshes[0].primitives[0].mesh;
+    assert_eq!(mesh.vertex_count(), 3);
+    assert_eq!(mesh.vertex(0).color, Color::from_rgba(255, 0, 0, 255));
+    assert_eq!(mesh.vertex(1).color, Color::from_rgba(0, 255, 0, 255));
+    assert_eq!(mesh.vertex(2).color.get_alpha(), 128);
+    assert_eq!(mesh.vertex(0).normal, Vec3::new(0f32, 0f32, 1f32));
+
+    let image = &gltf.images[0];
+    assert_eq!(image.mime_type.as_deref(), Some("image/png"));
+    assert!(matches!(&image.source, ImageSource::Embedded(bytes) if bytes.starts_with(b"\x89PNG")));
+
+    let camera = &gltf.cameras[gltf.nodes[1].camera.unwrap()];
+    assert_eq!(camera.projection, Projection::Orthographic { x_mag: 2f32, y_mag: 1f32, near: 0.5, far: 10f32 });
+    assert_eq!(camera.projection_matrix(4f32), Mat4::orthographic(-2f32, 2f32, -1f32, 1f32, 0.5, 10f32));
+}
+
+#[test]
+fn malformed_files() {
+    assert!(matches!(parse(r#"{"asset": {"version": "2.0""#), Err(GltfError::Json { .. })));
+    assert!(matches!(parse(r#"{"asset": {"version": "2.0"}} x"#), Err(GltfError::Json { offset: 30, .. })));
+    assert!(matches!(parse(r#"{"asset": {"version": "1.0"}}"#), Err(GltfError::Unsupported(_))));
+    assert!(matches!(parse("{}"), Err(GltfError::Invalid { ref path, .. }) if path == "asset"));
+    let error = parse(r#"{"asset": {"version": "2.0"}, "extensionsRequired": ["KHR_draco_mesh_compression"]}"#);
+    assert_eq!(error.unwrap_err().to_string(), "unsupported: required extension KHR_draco_mesh_compression");
+    assert!(matches!(
+        parse(r#"{"asset": {"version": "2.0"}, "buffers": [{"byteLength": 4, "uri": "missing.bin"}]}"#),
+        Err(GltfError::Io(..))
+    ));
+    assert!(matches!(
+        parse(r#"{"asset": {"version": "2.0"}, "buffers": [{"byteLength": 4, "uri": "data:;base64,A!=="}]}"#),
+        Err(GltfError::Invalid { .. })
+    ));
+    assert!(matches!(
+        parse(r#"{"asset": {"version": "2.0"}, "nodes": [{"children": [1]}, {"children": [0]}]}"#),
+        Err(GltfError::Invalid { ref path, .. }) if path.starts_with("nodes[")
+    ));
+
+    let accessor = |accessor: &str, primitive: &str| parse(&format!(
+        r#"{{"asset": {{"version": "2.0"}}, {}, "accessors": [{}],
+        "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}{}}}]}}]}}"#,
+        BUFFER, accessor, primitive
+    ));
+    let position = r#"{"bufferView": 0, "componentType": 5126, "count": 1, "type": "VEC3"}"#;
+    assert!(accessor(position, "").is_ok());
+    let error = accessor(&position.replace("\"count\": 1", "\"count\": 2"), "").unwrap_err();
+    assert_eq!(error.to_string(), "accessors[0].count: the accessor runs past its buffer view");
+    let error = accessor(&position.replace("VEC3", "VEC2"), "").unwrap_err();
+    assert_eq!(error.to_string(), "accessors[0].type: unexpected type VEC2");
+    // a huge count without a view must not allocate
+    let error = accessor(&position.replace("\"bufferView\": 0, ", "").replace("\"count\": 1", "\"count\": 4000000000"), "").unwrap_err();
+    assert_eq!(error.to_string(), "unsupported: accessors[0]: accessor without bufferView");
+    let error = accessor(position, r#", "mode": 9"#).unwrap_err();
+    assert_eq!(error.to_string(), "meshes[0].primitives[0].mode: unknown mode 9");
+    let error = accessor(position, r#", "material": 0"#).unwrap_err();
+    assert_eq!(error.to_string(), "meshes[0].primitives[0].material: index 0 out of range");
+
+    let glb = std::fs::read(fixtures().join("triangle.glb")).unwrap();
+    assert!(matches!(Gltf::from_slice(&glb[..40], &fixtures()), Err(GltfError::Glb(_))));
+    assert!(matches!(Gltf::load(fixtures().join("missing.gltf")), Err(GltfError::Io(..))));
+}
+
+#[test]
+fn json_syntax() {
+    let gltf = parse(r#" {"asset": {"version": "2.0", "copyright": "é😀 \"q\"\n"},
+        "scenes": [{"name": "a\/b", "nodes": []}], "scene": 0} "#).unwrap();
+    assert_eq!(gltf.scenes[0].name.as_deref(), Some("a/b"));
+    assert!(parse(&format!("{}{}", "[".repeat(100), "]".repeat(100))).is_err());
+    assert!(matches!(parse(r#"{"asset": {"version": "2.0"}, "scene": 0.5}"#), Err(GltfError::Invalid { .. })));
+    assert!(matches!(parse(r#"{"asset": {"version": "2.0", "x": "\q"}}"#), Err(GltfError::Json { .. })));
+    assert!(matches!(parse(r#"{"asset": {"version": "2.0", "x": tru}}"#), Err(GltfError::Json { .. })));
+}
+
+#[test]
+fn default_material() {
+    let gltf = parse(r#"{"asset": {"version": "2.0"}, "materials": [{}]}"#).unwrap();
+    assert_eq!(gltf.materials[0], Default::default());
+    assert_eq!(gltf.materials[0].base_color(), Color::from_rgba(255, 255, 255, 255));
+}
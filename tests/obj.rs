@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use rs_open_canvas::{
+    canvas::{Canvas, Color},
+    mathsf::{Vec3, Vec4},
+    mesh::MeshVertex,
+    obj::{Material, Obj, ObjError},
+    pipeline::{CullMode, Fragment, FragmentShader, Pipeline, VertexShader}
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+fn load_error(name: &str) -> ObjError {
+    Obj::load(fixture(name)).expect_err("the fixture is malformed")
+}
+
+#[test]
+fn cube_splits_by_material() {
+    let obj = Obj::load(fixture("cube.obj")).unwrap();
+    assert_eq!(obj.material_libraries, vec!["cube.mtl".to_string()]);
+    assert_eq!(obj.meshes.len(), 2);
+    for (part, material) in obj.meshes.iter().zip(["red", "white"]) {
+        assert_eq!(part.name, "cube");
+        assert_eq!(part.material.as_deref(), Some(material));
+        // three quads, two triangles each, four unique corners per face
+        assert_eq!(part.mesh.element_count(), 18);
+        assert_eq!(part.mesh.vertex_count(), 12);
+        assert_eq!(part.mesh.get_uvs().len(), 12);
+    }
+    let front = &obj.meshes[0].mesh;
+    assert_eq!(front.vertex(0).normal, Vec3::new(0f32, 0f32, 1f32));
+    let bounds = front.bounds().unwrap();
+    assert_eq!(bounds.get_min(), Vec3::new(-0.5, -0.5, -0.5));
+    assert_eq!(bounds.get_max(), Vec3::new(0.5, 0.5, 0.5));
+}
+
+#[test]
+fn cube_materials() {
+    let obj = Obj::load(fixture("cube.obj")).unwrap();
+    let red = obj.material("red").unwrap();
+    assert_eq!(red.ambient, Vec3::new(0.1, 0f32, 0f32));
+    assert_eq!(red.diffuse, Vec3::new(0.8, 0.1, 0.1));
+    assert_eq!(red.specular, Vec3::new(0.5, 0.5, 0.5));
+    assert_eq!(red.shininess, 32f32);
+    assert_eq!(red.diffuse_map.as_deref(), Some("textures/red.png"));
+    let white = obj.material("white").unwrap();
+    assert_eq!(white.diffuse, Vec3::new(1f32, 1f32, 1f32));
+    assert_eq!(white.opacity, 0.5);
+    assert_eq!(white.diffuse_map, None);
+    assert!(obj.material("blue").is_none());
+}
+
+#[test]
+fn ngons_and_smoothing() {
+    let obj = Obj::load(fixture("shapes.obj")).unwrap();
+    let names: Vec<&str> = obj.meshes.iter().map(|part| part.name.as_str()).collect();
+    assert_eq!(names, ["pentagon", "smooth", "flat"]);
+
+    let pentagon = &obj.meshes[0].mesh;
+    assert_eq!(pentagon.vertex_count(), 5);
+    assert_eq!(pentagon.element_count(), 9);
+    assert!(pentagon.get_uvs().is_empty());
+    assert!(pentagon.get_normals().iter().all(|n| *n == Vec3::new(0f32, 0f32, 1f32)));
+
+    // the shared edge is welded and its normals averaged
+    let smooth = &obj.meshes[1].mesh;
+    assert_eq!(smooth.vertex_count(), 4);
+    let shared = smooth.vertex(0).normal;
+    assert!((shared.length() - 1f32).abs() < 1e-5);
+    assert!(shared.get_y() > 0f32 && shared.get_z() > 0f32);
+
+    // flat faces keep their own vertices
+    let flat = &obj.meshes[2].mesh;
+    assert_eq!(flat.vertex_count(), 6);
+    assert_eq!(flat.vertex(0).normal, Vec3::new(0f32, 0f32, 1f32));
+    assert_eq!(flat.vertex(3).normal, Vec3::new(0f32, 1f32, 0f32));
+}
+
+#[test]
+fn malformed_files() {
+    assert!(matches!(
+        load_error("bad_number.obj"),
+        ObjError::InvalidNumber { line: 3, ref text } if text == "abc"
+    ));
+    assert!(matches!(load_error("bad_index.obj"), ObjError::IndexOutOfRange { line: 4, index: 4 }));
+    assert!(matches!(load_error("bad_face.obj"), ObjError::InvalidFace { line: 3, .. }));
+    assert!(matches!(load_error("missing_values.obj"), ObjError::MissingValues { line: 2, .. }));
+    assert!(matches!(load_error("missing_library.obj"), ObjError::Io(..)));
+    assert!(matches!(load_error("does_not_exist.obj"), ObjError::Io(..)));
+    assert_eq!(load_error("bad_face.obj").to_string(), "line 3: invalid face 'f 1 2'");
+
+    let source = std::fs::read_to_string(fixture("bad_color.mtl")).unwrap();
+    let error = Material::parse_mtl(&source).unwrap_err();
+    assert_eq!(error.to_string(), "line 2: invalid number 'x'");
+}
+
+#[test]
+fn inline_sources() {
+    assert!(matches!(Obj::parse("f 1 2 3"), Err(ObjError::IndexOutOfRange { line: 1, index: 1 })));
+    assert!(matches!(Obj::parse("v 0 0 0\nf 0 1 1"), Err(ObjError::IndexOutOfRange { line: 2, index: 0 })));
+    assert!(matches!(Obj::parse("v 0 0 0\nf 1/1/1/1 1 1"), Err(ObjError::InvalidFace { .. })));
+    assert!(matches!(Obj::parse("s maybe"), Err(ObjError::InvalidNumber { .. })));
+    // unknown statements, comments and continuations
+    let obj = Obj::parse("# header\ncstype bezier\nv 0 0 0 # origin\nv 1 0 \\\n 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+    assert_eq!(obj.meshes.len(), 1);
+    assert_eq!(obj.meshes[0].mesh.vertex_count(), 3);
+    assert!(Obj::parse("").unwrap().meshes.is_empty());
+}
+
+struct Orthographic;
+
+impl VertexShader for Orthographic {
+    type Vertex = MeshVertex;
+    type Varying = ();
+    fn shade(&self, vertex: &MeshVertex) -> (Vec4, ()) {
+        // rotated a little so three faces show
+        let p = vertex.position;
+        let (x, y, z) = (p.get_x() + p.get_z() * 0.3, p.get_y() + p.get_z() * 0.2, p.get_z());
+        (Vec4::new(x, y, -z * 0.5, 1f32), ())
+    }
+}
+
+struct Solid(Color);
+
+impl FragmentShader<()> for Solid {
+    fn shade(&self, _: &(), _: &Fragment) -> Option<Color> {
+        Some(self.0)
+    }
+}
+
+#[test]
+fn cube_draws() {
+    let obj = Obj::load(fixture("cube.obj")).unwrap();
+    let mut canvas = Canvas::new(32, 32);
+    let mut pipeline = Pipeline::new();
+    pipeline.set_cull_mode(CullMode::None);
+    for part in &obj.meshes {
+        let material = obj.material(part.material.as_deref().unwrap()).unwrap();
+        let stats = part.mesh.draw(&pipeline, &mut canvas, &Orthographic, &Solid(material.diffuse_color()));
+        assert_eq!(stats.primitives, 6);
+    }
+    let red = obj.material("red").unwrap().diffuse_color();
+    assert_eq!(canvas.get_pixel(16, 16), red);
+}